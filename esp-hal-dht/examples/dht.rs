@@ -0,0 +1,37 @@
+//! Read a DHT22 sensor, and print its readings.
+//!
+//! This assumes that the data line of the sensor is connected to GPIO4, with
+//! a pull-up resistor of about 5 kΩ to 3.3V, which most sensor modules have.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{prelude::*, rmt::Rmt};
+use esp_hal_dht::{Dht, Model};
+use esp_println::println;
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    #[cfg(feature = "esp32h2")]
+    let frequency = 32.MHz();
+    #[cfg(not(feature = "esp32h2"))]
+    let frequency = 80.MHz();
+
+    let rmt = Rmt::new(peripherals.RMT, frequency).unwrap();
+
+    let mut sensor = Dht::new(rmt.channel2, peripherals.GPIO4, frequency, Model::Dht22).unwrap();
+
+    loop {
+        // Every reading waits until the sensor is ready for it
+        match sensor.read() {
+            Ok(reading) => println!(
+                "Temperature: {:.1}°C, humidity: {:.1}%",
+                reading.temperature, reading.humidity
+            ),
+            Err(e) => println!("Reading failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! # DHT
+//!
+//! ## Overview
+//! This driver provides an abstraction over RMT to read DHT11 and DHT22
+//! temperature and humidity sensors through a user-friendly API.
+//!
+//! The sensors answer on a single open-drain data line: after the line was
+//! pulled low to start a reading, the sensor sends 40 bits, whose values are
+//! coded in the width of high pulses of 26 µs or 70 µs. A [Dht] pulls the line
+//! low with a GPIO, and captures the answer with an RMT receive channel on the
+//! same pin, so the reading is not corrupted by interrupts, and no busy loops
+//! time the bits.
+//!
+//! Every reading is checked against its checksum. The sensors need some time
+//! between two readings, 1 s for the DHT11 and 2 s for the DHT22, as well as
+//! after they were powered up, so readings wait for the rest of this interval.
+//!
+//! The line needs a pull-up resistor of about 5 kΩ, which most sensor modules
+//! have. The internal pull-up of the pin is enabled, but it is too weak for
+//! long wires.
+//!
+//! [DhtAsync] does the same with an async RMT channel, with the
+//! `embassy-time` feature.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap();
+//!
+//! let mut sensor = Dht::new(rmt.channel2, peripherals.GPIO4, 80.MHz(), Model::Dht22).unwrap();
+//!
+//! let reading = sensor.read().unwrap();
+//! println!("{}°C, {}%", reading.temperature, reading.humidity);
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use core::mem;
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{Flex, InputPin, OutputPin, Pull},
+    peripheral::Peripheral,
+    rmt::{
+        Error as RmtError, PulseCode, RxChannel, RxChannelConfig, RxChannelCreator,
+        RxChannelInternal,
+    },
+    time::{now, Instant},
+    Blocking,
+};
+#[cfg(feature = "embassy-time")]
+use esp_hal::{
+    rmt::{RxChannelAsync, RxChannelCreatorAsync},
+    Async,
+};
+use fugit::HertzU32;
+
+/// Time without an edge after which the receive channel stops, in µs
+///
+/// This is longer than any pulse of the answer.
+const IDLE_THRESHOLD_US: u32 = 200;
+
+/// Time to wait for the end of the answer after the line was released, in ms
+///
+/// An answer takes less than 5 ms.
+const TIMEOUT_MS: u64 = 10;
+
+/// Shortest and longest pulse of the acknowledgement, in µs
+const ACK_US: (u32, u32) = (60, 100);
+
+/// High pulses longer than this are a 1, in µs
+const ONE_THRESHOLD_US: u32 = 48;
+
+/// Number of pulse codes received, which holds the 84 pulses of an answer
+const BUFFER_SIZE: usize = 48;
+
+/// Errors from Dht
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Errors from [RmtError]
+    Rmt(RmtError),
+
+    /// The sensor did not answer, usually because it is not connected or not
+    /// powered
+    Timeout,
+
+    /// The answer of the sensor is not a valid reading
+    InvalidResponse,
+
+    /// The checksum of the reading does not match its data
+    Checksum,
+
+    /// The frequency of the RMT peripheral is not a whole number of MHz up to
+    /// 255 MHz
+    Frequency,
+}
+
+/// Converts [RmtError] into [self::Error]
+impl From<RmtError> for Error {
+    fn from(error: RmtError) -> Self {
+        Error::Rmt(error)
+    }
+}
+
+/// Model of a sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Model {
+    /// The DHT11, which measures 0°C to 50°C in steps of 1°C and 20% to 80%
+    /// humidity in steps of 1%
+    Dht11,
+    /// The DHT22 or AM2302, which measures -40°C to 80°C in steps of 0.1°C
+    /// and 0% to 100% humidity in steps of 0.1%
+    Dht22,
+}
+
+impl Model {
+    /// Time the line is pulled low to start a reading, in µs
+    const fn start_us(&self) -> u32 {
+        match self {
+            Model::Dht11 => 18_000,
+            Model::Dht22 => 1_100,
+        }
+    }
+
+    /// Time the sensor needs between two readings, in ms
+    pub const fn min_interval_ms(&self) -> u32 {
+        match self {
+            Model::Dht11 => 1_000,
+            Model::Dht22 => 2_000,
+        }
+    }
+
+    /// Convert the data bytes of a reading.
+    fn reading(&self, data: [u8; 4]) -> Reading {
+        match self {
+            Model::Dht11 => {
+                // The fractional byte of the temperature holds its sign on
+                // recent sensors
+                let magnitude = data[2] as f32 + (data[3] & 0x7f) as f32 / 10.0;
+                let temperature = match data[3] & 0x80 != 0 {
+                    true => -magnitude,
+                    false => magnitude,
+                };
+
+                Reading {
+                    temperature,
+                    humidity: data[0] as f32 + data[1] as f32 / 10.0,
+                }
+            }
+            Model::Dht22 => {
+                let magnitude = u16::from_be_bytes([data[2] & 0x7f, data[3]]) as f32 / 10.0;
+                let temperature = match data[2] & 0x80 != 0 {
+                    true => -magnitude,
+                    false => magnitude,
+                };
+
+                Reading {
+                    temperature,
+                    humidity: u16::from_be_bytes([data[0], data[1]]) as f32 / 10.0,
+                }
+            }
+        }
+    }
+}
+
+/// A reading of a sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Reading {
+    /// The temperature, in degrees Celsius
+    pub temperature: f32,
+
+    /// The relative humidity, in percent
+    pub humidity: f32,
+}
+
+/// Decode the received pulse codes of an answer.
+fn decode(model: Model, codes: &[u32]) -> Result<Reading, Error> {
+    let mut pulses = codes
+        .iter()
+        .flat_map(|code| {
+            [
+                (code.level1(), code.length1() as u32),
+                (code.level2(), code.length2() as u32),
+            ]
+        })
+        .take_while(|(_, length)| *length != 0);
+
+    // The sensor acknowledges the start with a low and a high pulse of 80 µs,
+    // after the line was released
+    let within_ack = |length: u32| (ACK_US.0..=ACK_US.1).contains(&length);
+    let mut previous = (true, 0);
+    loop {
+        let pulse = pulses.next().ok_or(Error::InvalidResponse)?;
+        if !previous.0 && within_ack(previous.1) && pulse.0 && within_ack(pulse.1) {
+            break;
+        }
+        previous = pulse;
+    }
+
+    // Every bit is a low pulse of 50 µs, followed by a high pulse coding it
+    let mut bytes = [0u8; 5];
+    for bit in 0..40 {
+        pulses.next().ok_or(Error::InvalidResponse)?;
+        let (level, length) = pulses.next().ok_or(Error::InvalidResponse)?;
+        if !level {
+            return Err(Error::InvalidResponse);
+        }
+
+        bytes[bit / 8] = bytes[bit / 8] << 1 | (length > ONE_THRESHOLD_US) as u8;
+    }
+
+    let [data @ .., checksum] = bytes;
+    let sum = data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if sum != checksum {
+        return Err(Error::Checksum);
+    }
+
+    Ok(model.reading(data))
+}
+
+/// Time until the sensor is ready for the next reading, in µs
+fn remaining_us(model: Model, last: Instant) -> u64 {
+    let now = now();
+    match now >= last {
+        true => (model.min_interval_ms() as u64 * 1000).saturating_sub((now - last).to_micros()),
+        false => 0,
+    }
+}
+
+/// Configuration of a receive channel counting in µs, with the RMT peripheral
+/// running at `frequency`
+fn channel_config(frequency: HertzU32) -> Result<RxChannelConfig, Error> {
+    let clk_divider = match u8::try_from(frequency.to_MHz()) {
+        Ok(divider) if divider > 0 && frequency.raw() % 1_000_000 == 0 => divider,
+        _ => return Err(Error::Frequency),
+    };
+
+    Ok(RxChannelConfig {
+        clk_divider,
+        idle_threshold: IDLE_THRESHOLD_US as u16,
+
+        ..RxChannelConfig::default()
+    })
+}
+
+/// Set up the data line as an open-drain output, which is released.
+fn open_drain<'d, P>(pin: impl Peripheral<P = P> + 'd) -> Flex<'d>
+where
+    P: OutputPin + InputPin + 'd,
+{
+    let mut line = Flex::new(pin);
+    line.set_high();
+    line.set_as_open_drain(Pull::Up);
+
+    line
+}
+
+/// A DHT11 or DHT22 sensor, read by an RMT channel
+pub struct Dht<'d, RX>
+where
+    RX: RxChannel,
+{
+    channel: Option<RX>,
+    line: Flex<'d>,
+    model: Model,
+    last: Instant,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+impl<'d, RX> Dht<'d, RX>
+where
+    RX: RxChannel,
+{
+    /// Create a new sensor with its data line on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`. The first reading waits for the sensor to power up.
+    pub fn new<C, P>(channel: C, pin: P, frequency: HertzU32, model: Model) -> Result<Self, Error>
+    where
+        P: Peripheral<P = P> + OutputPin + InputPin + 'd,
+        C: RxChannelCreator<'d, RX, P>,
+    {
+        // SAFETY: the copy of the pin is only used to drive the line, which
+        // the channel only reads
+        let line = unsafe { pin.clone_unchecked() };
+        let channel = channel.configure(pin, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel: Some(channel),
+            line: open_drain(line),
+            model,
+            last: now(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// The model of the sensor
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Read the temperature and the humidity.
+    pub fn read(&mut self) -> Result<Reading, Error> {
+        let delay = Delay::new();
+
+        let remaining_us = remaining_us(self.model, self.last);
+        if remaining_us > 0 {
+            delay.delay_micros(remaining_us as u32);
+        }
+
+        self.buffer.fill(0);
+        self.line.set_low();
+        delay.delay_micros(self.model.start_us());
+
+        let channel = self.channel.take().unwrap();
+        let transaction = match channel.receive(&mut self.buffer) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                self.line.set_high();
+                self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        };
+
+        self.line.set_high();
+        let start = now();
+        self.last = start;
+
+        while !<RX as RxChannelInternal<Blocking>>::is_done() {
+            if (now() - start).to_millis() >= TIMEOUT_MS {
+                <RX as RxChannelInternal<Blocking>>::stop();
+                <RX as RxChannelInternal<Blocking>>::clear_interrupts();
+                mem::forget(transaction);
+                self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+
+                return Err(Error::Timeout);
+            }
+        }
+
+        match transaction.wait() {
+            Ok(channel) => self.channel = Some(channel),
+            Err((e, channel)) => {
+                self.channel = Some(channel);
+                return Err(e.into());
+            }
+        }
+
+        decode(self.model, &self.buffer)
+    }
+}
+
+/// A DHT11 or DHT22 sensor, read by an async RMT channel
+#[cfg(feature = "embassy-time")]
+pub struct DhtAsync<'d, RX>
+where
+    RX: RxChannelAsync,
+{
+    channel: RX,
+    line: Flex<'d>,
+    model: Model,
+    last: Instant,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+#[cfg(feature = "embassy-time")]
+impl<'d, RX> DhtAsync<'d, RX>
+where
+    RX: RxChannelAsync,
+{
+    /// Create a new sensor with its data line on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`. The first reading waits for the sensor to power up.
+    pub fn new<C, P>(channel: C, pin: P, frequency: HertzU32, model: Model) -> Result<Self, Error>
+    where
+        P: Peripheral<P = P> + OutputPin + InputPin + 'd,
+        C: RxChannelCreatorAsync<'d, RX, P>,
+    {
+        // SAFETY: the copy of the pin is only used to drive the line, which
+        // the channel only reads
+        let line = unsafe { pin.clone_unchecked() };
+        let channel = channel.configure(pin, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel,
+            line: open_drain(line),
+            model,
+            last: now(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// The model of the sensor
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Read the temperature and the humidity.
+    ///
+    /// Waits for the minimum interval since the last reading first, so
+    /// readings can be taken in a loop without a timer of their own.
+    pub async fn read(&mut self) -> Result<Reading, Error> {
+        use core::{
+            future::{poll_fn, Future},
+            pin::pin,
+        };
+
+        use embassy_time::{with_timeout, Duration, Timer};
+
+        let remaining_us = remaining_us(self.model, self.last);
+        if remaining_us > 0 {
+            Timer::after_micros(remaining_us).await;
+        }
+
+        self.buffer.fill(0);
+        self.line.set_low();
+        Timer::after_micros(self.model.start_us() as u64).await;
+
+        let result = {
+            let mut receive = pin!(with_timeout(
+                Duration::from_millis(TIMEOUT_MS),
+                self.channel.receive(&mut self.buffer)
+            ));
+
+            // The channel starts receiving when the future is first polled,
+            // which has to be before the line is released
+            let mut released = false;
+            poll_fn(|cx| {
+                let poll = receive.as_mut().poll(cx);
+                if !released {
+                    self.line.set_high();
+                    self.last = now();
+                    released = true;
+                }
+
+                poll
+            })
+            .await
+        };
+
+        match result {
+            Ok(result) => result?,
+            Err(_) => {
+                <RX as RxChannelInternal<Async>>::stop();
+                <RX as RxChannelInternal<Async>>::clear_interrupts();
+
+                return Err(Error::Timeout);
+            }
+        }
+
+        decode(self.model, &self.buffer)
+    }
+}
@@ -0,0 +1,45 @@
+//! Measure the distance with an HC-SR04 sensor, and print it every 100 ms.
+//!
+//! This assumes that the trigger pin of the sensor is connected to GPIO4, and
+//! that its echo pin is connected to GPIO5 through a voltage divider, as it
+//! runs at 5V.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{delay::Delay, prelude::*, rmt::Rmt};
+use esp_hal_hcsr04::{Error, HcSr04};
+use esp_println::println;
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    #[cfg(feature = "esp32h2")]
+    let frequency = 32.MHz();
+    #[cfg(not(feature = "esp32h2"))]
+    let frequency = 80.MHz();
+
+    let rmt = Rmt::new(peripherals.RMT, frequency).unwrap();
+
+    let mut sensor = HcSr04::new(
+        rmt.channel2,
+        peripherals.GPIO5,
+        peripherals.GPIO4,
+        frequency,
+    )
+    .unwrap();
+
+    let delay = Delay::new();
+
+    loop {
+        match sensor.distance_mm() {
+            Ok(distance) => println!("Distance: {} mm", distance),
+            Err(Error::OutOfRange) => println!("Nothing in range"),
+            Err(e) => println!("Measurement failed: {:?}", e),
+        }
+
+        delay.delay_millis(100);
+    }
+}
@@ -0,0 +1,433 @@
+//! # HC-SR04
+//!
+//! ## Overview
+//! This driver provides an abstraction over RMT to measure distances with
+//! HC-SR04 ultrasonic sensors through a user-friendly API.
+//!
+//! A pulse on the trigger pin of the sensor makes it send a burst of
+//! ultrasound, and its echo pin is high until the echo of the burst comes
+//! back. An [HcSr04] sends the trigger pulse with a GPIO, and measures the
+//! echo pulse with an RMT receive channel, so the measurement is not thrown
+//! off by interrupts, and the CPU does not busy-wait for the echo.
+//!
+//! The time of flight is converted into a distance with the speed of sound,
+//! which depends on the temperature of the air: about 1.8 mm per meter and
+//! degree Celsius. Set the temperature with [HcSr04::set_temperature] when it
+//! is known, for example from another sensor.
+//!
+//! The sensor needs 60 ms between two measurements, so echoes of the last
+//! burst do not come back during the next one. Measurements wait for the rest
+//! of this interval, and fail with [Error::Timeout] if the sensor does not
+//! answer.
+//!
+//! The echo pin of the sensor runs at 5V, so it has to be connected through a
+//! voltage divider, or a level shifter.
+//!
+//! [HcSr04Async] does the same with an async RMT channel, with the
+//! `embassy-time` feature.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap();
+//!
+//! let mut sensor =
+//!     HcSr04::new(rmt.channel2, peripherals.GPIO5, peripherals.GPIO4, 80.MHz()).unwrap();
+//! sensor.set_temperature(25.0);
+//!
+//! match sensor.distance_mm() {
+//!     Ok(distance) => println!("Distance: {distance} mm"),
+//!     Err(Error::OutOfRange) => println!("Nothing in range"),
+//!     Err(e) => println!("Measurement failed: {e:?}"),
+//! }
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use core::mem;
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{InputPin, Level, Output, OutputPin},
+    peripheral::Peripheral,
+    rmt::{
+        Error as RmtError, PulseCode, RxChannel, RxChannelConfig, RxChannelCreator,
+        RxChannelInternal,
+    },
+    time::{now, Instant},
+    Blocking,
+};
+#[cfg(feature = "embassy-time")]
+use esp_hal::{
+    rmt::{RxChannelAsync, RxChannelCreatorAsync},
+    Async,
+};
+use fugit::HertzU32;
+
+/// Longest echo of an object in range, in µs, which is about 4.3 m at 20°C
+const MAX_ECHO_US: u32 = 25_000;
+
+/// Time without an edge after which the receive channel stops, in µs
+///
+/// This is longer than the echo of any object in range. Without an object in
+/// range, the echo pin stays high for about 38 ms, and the channel stops
+/// before the end of the pulse.
+const IDLE_THRESHOLD_US: u32 = 30_000;
+
+/// Width of the trigger pulse, in µs
+const TRIGGER_US: u32 = 10;
+
+/// Time the sensor needs between two measurements, in µs
+const MIN_INTERVAL_US: u64 = 60_000;
+
+/// Time to wait for the end of the echo after the trigger pulse, in ms
+const DEFAULT_TIMEOUT_MS: u32 = 60;
+
+/// Temperature the speed of sound is computed for, in degrees Celsius
+const DEFAULT_TEMPERATURE: f32 = 20.0;
+
+/// Number of pulse codes received, which is plenty for a single pulse
+const BUFFER_SIZE: usize = 8;
+
+/// Errors from HcSr04
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Errors from [RmtError]
+    Rmt(RmtError),
+
+    /// The sensor did not answer within the timeout, usually because it is not
+    /// connected or not powered
+    Timeout,
+
+    /// No object is in range of the sensor
+    OutOfRange,
+
+    /// The frequency of the RMT peripheral is not a whole number of MHz up to
+    /// 255 MHz
+    Frequency,
+}
+
+/// Converts [RmtError] into [self::Error]
+impl From<RmtError> for Error {
+    fn from(error: RmtError) -> Self {
+        Error::Rmt(error)
+    }
+}
+
+/// Speed of sound in air at a temperature in degrees Celsius, in m/s
+pub fn speed_of_sound(celsius: f32) -> f32 {
+    331.3 + 0.606 * celsius
+}
+
+/// Settings and pacing shared by the blocking and async sensors
+#[derive(Debug)]
+struct Settings {
+    temperature: f32,
+    timeout_ms: u32,
+    last: Option<Instant>,
+}
+
+impl Settings {
+    const fn new() -> Self {
+        Self {
+            temperature: DEFAULT_TEMPERATURE,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            last: None,
+        }
+    }
+
+    /// Time until the sensor is ready for the next measurement, in µs
+    fn remaining_us(&self) -> u64 {
+        let Some(last) = self.last else {
+            return 0;
+        };
+
+        let now = now();
+        match now >= last {
+            true => MIN_INTERVAL_US.saturating_sub((now - last).to_micros()),
+            false => 0,
+        }
+    }
+
+    /// Distance for an echo of `echo_us` µs, in mm
+    fn distance_mm(&self, echo_us: u32) -> u32 {
+        // The sound travels to the object and back
+        (echo_us as f32 * speed_of_sound(self.temperature) / 2000.0 + 0.5) as u32
+    }
+}
+
+/// Send the trigger pulse.
+fn trigger(pin: &mut Output<'_>) {
+    pin.set_high();
+    Delay::new().delay_micros(TRIGGER_US);
+    pin.set_low();
+}
+
+/// The width of the echo pulse in the received pulse codes, in µs
+fn parse_echo(codes: &[u32]) -> Result<u32, Error> {
+    // The channel starts receiving at the rising edge of the echo
+    let code = codes[0];
+    let echo_us = code.length1() as u32;
+    if !code.level1() || echo_us == 0 || echo_us >= MAX_ECHO_US {
+        return Err(Error::OutOfRange);
+    }
+
+    Ok(echo_us)
+}
+
+/// Configuration of a receive channel counting in µs, for the frequency the
+/// RMT peripheral was set up with in `Rmt::new`
+fn channel_config(frequency: HertzU32) -> Result<RxChannelConfig, Error> {
+    let clk_divider = match u8::try_from(frequency.to_MHz()) {
+        Ok(divider) if divider > 0 && frequency.raw() % 1_000_000 == 0 => divider,
+        _ => return Err(Error::Frequency),
+    };
+
+    Ok(RxChannelConfig {
+        clk_divider,
+        idle_threshold: IDLE_THRESHOLD_US as u16,
+
+        ..RxChannelConfig::default()
+    })
+}
+
+/// An HC-SR04 sensor, with the echo measured by an RMT channel
+pub struct HcSr04<'d, RX>
+where
+    RX: RxChannel,
+{
+    channel: Option<RX>,
+    trigger: Output<'d>,
+    settings: Settings,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+impl<'d, RX> HcSr04<'d, RX>
+where
+    RX: RxChannel,
+{
+    /// Create a new sensor with its echo pin on `echo`, and its trigger pin on
+    /// `trigger`.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`.
+    pub fn new<C, P>(
+        channel: C,
+        echo: impl Peripheral<P = P> + 'd,
+        trigger: impl Peripheral<P = impl OutputPin> + 'd,
+        frequency: HertzU32,
+    ) -> Result<Self, Error>
+    where
+        P: InputPin + 'd,
+        C: RxChannelCreator<'d, RX, P>,
+    {
+        let channel = channel.configure(echo, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel: Some(channel),
+            trigger: Output::new(trigger, Level::Low),
+            settings: Settings::new(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// Set the temperature of the air, in degrees Celsius.
+    ///
+    /// The default is 20°C.
+    pub fn set_temperature(&mut self, celsius: f32) {
+        self.settings.temperature = celsius;
+    }
+
+    /// The temperature of the air, in degrees Celsius
+    pub fn temperature(&self) -> f32 {
+        self.settings.temperature
+    }
+
+    /// Set how long to wait for the end of the echo after the trigger pulse,
+    /// in ms.
+    ///
+    /// The default of 60 ms covers the longest echo pulse of the sensor.
+    pub fn set_timeout_ms(&mut self, timeout_ms: u32) {
+        self.settings.timeout_ms = timeout_ms;
+    }
+
+    /// Measure the distance to the closest object, in mm.
+    pub fn distance_mm(&mut self) -> Result<u32, Error> {
+        let echo_us = self.echo_us()?;
+
+        Ok(self.settings.distance_mm(echo_us))
+    }
+
+    /// Measure the time of flight of the sound to the closest object and
+    /// back, in µs.
+    pub fn echo_us(&mut self) -> Result<u32, Error> {
+        let remaining_us = self.settings.remaining_us();
+        if remaining_us > 0 {
+            Delay::new().delay_micros(remaining_us as u32);
+        }
+
+        self.buffer.fill(0);
+
+        let channel = self.channel.take().unwrap();
+        let transaction = match channel.receive(&mut self.buffer) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        };
+
+        trigger(&mut self.trigger);
+        let start = now();
+        self.settings.last = Some(start);
+
+        while !<RX as RxChannelInternal<Blocking>>::is_done() {
+            if (now() - start).to_millis() >= self.settings.timeout_ms as u64 {
+                <RX as RxChannelInternal<Blocking>>::stop();
+                <RX as RxChannelInternal<Blocking>>::clear_interrupts();
+                mem::forget(transaction);
+                self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+
+                return Err(Error::Timeout);
+            }
+        }
+
+        match transaction.wait() {
+            Ok(channel) => self.channel = Some(channel),
+            Err((e, channel)) => {
+                self.channel = Some(channel);
+                return Err(e.into());
+            }
+        }
+
+        parse_echo(&self.buffer)
+    }
+}
+
+/// An HC-SR04 sensor, with the echo measured by an async RMT channel
+#[cfg(feature = "embassy-time")]
+pub struct HcSr04Async<'d, RX>
+where
+    RX: RxChannelAsync,
+{
+    channel: RX,
+    trigger: Output<'d>,
+    settings: Settings,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+#[cfg(feature = "embassy-time")]
+impl<'d, RX> HcSr04Async<'d, RX>
+where
+    RX: RxChannelAsync,
+{
+    /// Create a new sensor with its echo pin on `echo`, and its trigger pin on
+    /// `trigger`.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`.
+    pub fn new<C, P>(
+        channel: C,
+        echo: impl Peripheral<P = P> + 'd,
+        trigger: impl Peripheral<P = impl OutputPin> + 'd,
+        frequency: HertzU32,
+    ) -> Result<Self, Error>
+    where
+        P: InputPin + 'd,
+        C: RxChannelCreatorAsync<'d, RX, P>,
+    {
+        let channel = channel.configure(echo, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel,
+            trigger: Output::new(trigger, Level::Low),
+            settings: Settings::new(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// Set the temperature of the air, in degrees Celsius.
+    ///
+    /// The default is 20°C.
+    pub fn set_temperature(&mut self, celsius: f32) {
+        self.settings.temperature = celsius;
+    }
+
+    /// The temperature of the air, in degrees Celsius
+    pub fn temperature(&self) -> f32 {
+        self.settings.temperature
+    }
+
+    /// Set how long to wait for the end of the echo after the trigger pulse,
+    /// in ms.
+    ///
+    /// The default of 60 ms covers the longest echo pulse of the sensor.
+    pub fn set_timeout_ms(&mut self, timeout_ms: u32) {
+        self.settings.timeout_ms = timeout_ms;
+    }
+
+    /// Measure the distance to the closest object, in mm.
+    pub async fn distance_mm(&mut self) -> Result<u32, Error> {
+        let echo_us = self.echo_us().await?;
+
+        Ok(self.settings.distance_mm(echo_us))
+    }
+
+    /// Measure the time of flight of the sound to the closest object and
+    /// back, in µs.
+    pub async fn echo_us(&mut self) -> Result<u32, Error> {
+        use core::{
+            future::{poll_fn, Future},
+            pin::pin,
+        };
+
+        use embassy_time::{with_timeout, Duration, Timer};
+
+        let remaining_us = self.settings.remaining_us();
+        if remaining_us > 0 {
+            Timer::after_micros(remaining_us).await;
+        }
+
+        self.buffer.fill(0);
+
+        let timeout = Duration::from_millis(self.settings.timeout_ms as u64);
+        let result = {
+            let mut receive = pin!(with_timeout(
+                timeout,
+                self.channel.receive(&mut self.buffer)
+            ));
+
+            // The channel starts receiving when the future is first polled,
+            // which has to be before the trigger pulse
+            let mut triggered = false;
+            poll_fn(|cx| {
+                let poll = receive.as_mut().poll(cx);
+                if !triggered {
+                    trigger(&mut self.trigger);
+                    self.settings.last = Some(now());
+                    triggered = true;
+                }
+
+                poll
+            })
+            .await
+        };
+
+        match result {
+            Ok(result) => result?,
+            Err(_) => {
+                <RX as RxChannelInternal<Async>>::stop();
+                <RX as RxChannelInternal<Async>>::clear_interrupts();
+
+                return Err(Error::Timeout);
+            }
+        }
+
+        parse_echo(&self.buffer)
+    }
+}
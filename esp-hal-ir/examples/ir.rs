@@ -0,0 +1,43 @@
+//! Send a NEC command with an LED, then print the commands of a remote control.
+//!
+//! This assumes that the output of an infrared demodulator like the TSOP38238
+//! is connected to GPIO4, and that an infrared LED is driven from GPIO5
+//! through a transistor.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{prelude::*, rmt::Rmt};
+use esp_hal_ir::{IrReceiver, IrTransmitter, Protocol};
+use esp_println::println;
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    #[cfg(feature = "esp32h2")]
+    let frequency = 32.MHz();
+    #[cfg(not(feature = "esp32h2"))]
+    let frequency = 80.MHz();
+
+    let rmt = Rmt::new(peripherals.RMT, frequency).unwrap();
+
+    let mut receiver = IrReceiver::new(rmt.channel2, peripherals.GPIO4, frequency).unwrap();
+    let mut transmitter =
+        IrTransmitter::new(rmt.channel0, peripherals.GPIO5, frequency, Protocol::Nec).unwrap();
+
+    transmitter.send(0x00, 0x45).unwrap();
+    println!("Sent NEC command 0x45 to address 0x00");
+
+    loop {
+        let command = receiver.receive().unwrap();
+        println!(
+            "{:?} address {:#06x} command {:#04x}{}",
+            command.protocol,
+            command.address,
+            command.command,
+            if command.repeat { " (repeat)" } else { "" },
+        );
+    }
+}
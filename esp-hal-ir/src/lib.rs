@@ -0,0 +1,135 @@
+//! # Infrared remote control
+//!
+//! ## Overview
+//! This driver provides an abstraction over RMT to send and receive the
+//! commands of infrared remote controls through a user-friendly API.
+//!
+//! Remote controls send their commands as bursts of a carrier of 36 kHz to
+//! 40 kHz, which an infrared demodulator like the TSOP38238 turns into pulses
+//! on its output pin. An [IrReceiver] measures these pulses with an RMT receive
+//! channel, and decodes them as [Command]s of the [nec] or [rc5] protocol.
+//! Commands repeated by a held key are flagged as repeats, so they can be told
+//! apart from repeated presses of the key.
+//!
+//! An [IrTransmitter] sends commands with an infrared LED: its RMT channel
+//! modulates the pulses with the carrier of the protocol, so the pin drives
+//! the LED directly through a transistor.
+//!
+//! [IrReceiverAsync] and [IrTransmitterAsync] do the same with async RMT
+//! channels.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap();
+//!
+//! let mut receiver = IrReceiver::new(rmt.channel2, peripherals.GPIO4, 80.MHz()).unwrap();
+//! let mut transmitter =
+//!     IrTransmitter::new(rmt.channel0, peripherals.GPIO5, 80.MHz(), Protocol::Nec).unwrap();
+//!
+//! transmitter.send(0x04, 0x08).unwrap();
+//!
+//! let command = receiver.receive().unwrap();
+//! println!("{:?} {:#x} {:#x}", command.protocol, command.address, command.command);
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use esp_hal::rmt::Error as RmtError;
+use fugit::HertzU32;
+
+pub use receiver::{Decoder, IrReceiver, IrReceiverAsync};
+pub use transmitter::{IrTransmitter, IrTransmitterAsync};
+
+pub mod nec;
+mod pulse;
+pub mod rc5;
+mod receiver;
+mod transmitter;
+
+/// Number of pulse codes buffered by receivers and transmitters, which is a
+/// block of channel memory on most chips
+const BUFFER_SIZE: usize = 48;
+
+/// Errors from IR
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Errors from [RmtError]
+    Rmt(RmtError),
+
+    /// The address or the command does not fit into a frame of the protocol
+    OutOfRange,
+
+    /// A repeat was sent before any command
+    NothingToRepeat,
+
+    /// The frequency of the RMT peripheral is not a whole number of MHz up to
+    /// 255 MHz
+    Frequency,
+}
+
+/// Converts [RmtError] into [self::Error]
+impl From<RmtError> for Error {
+    fn from(error: RmtError) -> Self {
+        Error::Rmt(error)
+    }
+}
+
+/// Divider of a channel counting in µs, for the frequency the RMT peripheral
+/// was set up with in `Rmt::new`
+fn clk_divider(frequency: HertzU32) -> Result<u8, Error> {
+    match u8::try_from(frequency.to_MHz()) {
+        Ok(divider) if divider > 0 && frequency.raw() % 1_000_000 == 0 => Ok(divider),
+        _ => Err(Error::Frequency),
+    }
+}
+
+/// Protocol of infrared remote controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Protocol {
+    /// The NEC protocol, and extended NEC
+    Nec,
+    /// The RC5 protocol of Philips, and extended RC5
+    Rc5,
+}
+
+impl Protocol {
+    /// Frequency of the carrier, in Hz
+    pub const fn carrier_hz(&self) -> u32 {
+        match self {
+            Protocol::Nec => nec::CARRIER_HZ,
+            Protocol::Rc5 => rc5::CARRIER_HZ,
+        }
+    }
+
+    /// Time between the starts of two frames of a held key, in ms
+    pub const fn repeat_period_ms(&self) -> u32 {
+        match self {
+            Protocol::Nec => nec::REPEAT_PERIOD_MS,
+            Protocol::Rc5 => rc5::REPEAT_PERIOD_MS,
+        }
+    }
+}
+
+/// A received command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Command {
+    /// The protocol the command was sent with
+    pub protocol: Protocol,
+
+    /// The address of the device
+    pub address: u16,
+
+    /// The command, usually the key which was pressed
+    pub command: u8,
+
+    /// Whether the command was repeated by a held key
+    pub repeat: bool,
+}
@@ -0,0 +1,140 @@
+//! The NEC protocol.
+//!
+//! A frame starts with a 9 ms burst of the 38 kHz carrier and a 4.5 ms space,
+//! followed by 32 bits sent LSB first: the address, its inverse, the command
+//! and its inverse. Every bit is a 562.5 µs burst, followed by a space of
+//! 562.5 µs for 0 or 1687.5 µs for 1, and a final burst ends the frame.
+//!
+//! Remotes with more devices send a 16 bit address instead of the address and
+//! its inverse, which is known as extended NEC. An extended address whose high
+//! byte is the inverse of its low byte can not be told apart from an 8 bit
+//! address, and is decoded as one.
+//!
+//! While a key is held, the remote sends a repeat code every 108 ms instead of
+//! the frame: a 9 ms burst, a 2.25 ms space and a final burst.
+
+use crate::pulse::{pulses, within, PulseWriter};
+
+/// Frequency of the carrier, in Hz
+pub const CARRIER_HZ: u32 = 38_000;
+
+/// Time between the starts of two frames or repeat codes of a held key, in ms
+pub const REPEAT_PERIOD_MS: u32 = 108;
+
+/// Number of pulse codes of a frame, including the end marker
+pub const MAX_CODES: usize = 34;
+
+const LEADER_MARK_US: u32 = 9000;
+const LEADER_SPACE_US: u32 = 4500;
+const REPEAT_SPACE_US: u32 = 2250;
+const BIT_MARK_US: u32 = 562;
+const ZERO_SPACE_US: u32 = 562;
+const ONE_SPACE_US: u32 = 1687;
+
+/// A decoded NEC frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Frame {
+    /// A command sent to an address
+    Command {
+        /// The 8 bit address, or the 16 bit address of extended NEC
+        address: u16,
+        /// The command
+        command: u8,
+    },
+    /// The repeat code of a held key
+    Repeat,
+}
+
+/// Encode a frame into `buffer`, and return the number of pulse codes.
+///
+/// Addresses up to 255 are sent with their inverse, and larger addresses as
+/// extended NEC. `buffer` has to hold at least [MAX_CODES] codes.
+pub fn encode(address: u16, command: u8, buffer: &mut [u32]) -> usize {
+    let address = match address {
+        0..=0xff => address | (!address & 0xff) << 8,
+        _ => address,
+    };
+    let bits = address as u32 | (command as u32) << 16 | (!command as u32) << 24;
+
+    let mut writer = PulseWriter::new(buffer);
+    writer.push(true, LEADER_MARK_US);
+    writer.push(false, LEADER_SPACE_US);
+    for bit in 0..32 {
+        let space = match bits & (1 << bit) != 0 {
+            true => ONE_SPACE_US,
+            false => ZERO_SPACE_US,
+        };
+        writer.push(true, BIT_MARK_US);
+        writer.push(false, space);
+    }
+    writer.push(true, BIT_MARK_US);
+
+    writer.finish()
+}
+
+/// Encode a repeat code into `buffer`, and return the number of pulse codes.
+pub fn encode_repeat(buffer: &mut [u32]) -> usize {
+    let mut writer = PulseWriter::new(buffer);
+    writer.push(true, LEADER_MARK_US);
+    writer.push(false, REPEAT_SPACE_US);
+    writer.push(true, BIT_MARK_US);
+
+    writer.finish()
+}
+
+/// Decode the pulse codes of a received frame or repeat code.
+///
+/// `mark_level` is the level of the pin during a burst of the carrier. Returns
+/// `None` if the pulses are not a valid NEC frame.
+pub fn decode(codes: &[u32], mark_level: bool) -> Option<Frame> {
+    let mut pulses = pulses(codes, mark_level);
+
+    let (_, leader) = pulses.next()?;
+    if !within(leader, LEADER_MARK_US) {
+        return None;
+    }
+
+    let (_, space) = pulses.next()?;
+    let repeat = within(space, REPEAT_SPACE_US);
+    if !repeat && !within(space, LEADER_SPACE_US) {
+        return None;
+    }
+
+    let mut bits = 0u32;
+    if !repeat {
+        for bit in 0..32 {
+            let (_, mark) = pulses.next()?;
+            let (_, space) = pulses.next()?;
+            if !within(mark, BIT_MARK_US) {
+                return None;
+            }
+
+            if within(space, ONE_SPACE_US) {
+                bits |= 1 << bit;
+            } else if !within(space, ZERO_SPACE_US) {
+                return None;
+            }
+        }
+    }
+
+    let (_, stop) = pulses.next()?;
+    if !within(stop, BIT_MARK_US) {
+        return None;
+    }
+
+    if repeat {
+        return Some(Frame::Repeat);
+    }
+
+    let [low, high, command, inverse] = bits.to_le_bytes();
+    if command != !inverse {
+        return None;
+    }
+    let address = match low == !high {
+        true => low as u16,
+        false => u16::from_le_bytes([low, high]),
+    };
+
+    Some(Frame::Command { address, command })
+}
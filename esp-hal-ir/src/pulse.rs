@@ -0,0 +1,75 @@
+//! Conversion between pulses and the pulse codes of the RMT peripheral.
+//!
+//! The channels count in microseconds, so the lengths of the pulse codes are
+//! the durations of the pulses. A mark is a burst of the carrier, and a space
+//! is the gap between two bursts.
+
+use esp_hal::rmt::PulseCode;
+
+/// Tolerance of the duration of a received pulse, in percent of its nominal
+/// duration
+///
+/// Demodulators stretch marks and shorten spaces by up to about 100 µs.
+const TOLERANCE_PCT: u32 = 30;
+
+/// Whether a received pulse of `us` µs matches the nominal duration
+pub(crate) fn within(us: u32, nominal_us: u32) -> bool {
+    us.abs_diff(nominal_us) <= nominal_us * TOLERANCE_PCT / 100
+}
+
+/// The pulses of received pulse codes, as whether each one is a mark, and its
+/// duration in µs
+///
+/// The pulses end at the first code of length 0, which marks the end of the
+/// data. Any space before the first mark is skipped.
+pub(crate) fn pulses(codes: &[u32], mark_level: bool) -> impl Iterator<Item = (bool, u32)> + '_ {
+    codes
+        .iter()
+        .flat_map(|code| {
+            [
+                (code.level1(), code.length1()),
+                (code.level2(), code.length2()),
+            ]
+        })
+        .take_while(|(_, length)| *length != 0)
+        .map(move |(level, length)| (level == mark_level, length as u32))
+        .skip_while(|(mark, _)| !mark)
+}
+
+/// Writes pulses into a buffer of pulse codes, two pulses per code
+pub(crate) struct PulseWriter<'a> {
+    buffer: &'a mut [u32],
+    len: usize,
+    pending: Option<(bool, u16)>,
+}
+
+impl<'a> PulseWriter<'a> {
+    /// Create a new writer at the start of `buffer`.
+    pub(crate) fn new(buffer: &'a mut [u32]) -> Self {
+        Self {
+            buffer,
+            len: 0,
+            pending: None,
+        }
+    }
+
+    /// Append a mark or a space of `us` µs.
+    pub(crate) fn push(&mut self, mark: bool, us: u32) {
+        match self.pending.take() {
+            Some((level, length)) => {
+                self.buffer[self.len] = PulseCode::new(level, length, mark, us as u16);
+                self.len += 1;
+            }
+            None => self.pending = Some((mark, us as u16)),
+        }
+    }
+
+    /// Terminate the pulse codes with the end marker, and return how many
+    /// codes were written.
+    pub(crate) fn finish(mut self) -> usize {
+        let (level, length) = self.pending.take().unwrap_or((false, 0));
+        self.buffer[self.len] = PulseCode::new(level, length, false, 0);
+
+        self.len + 1
+    }
+}
@@ -0,0 +1,132 @@
+//! The RC5 protocol of Philips.
+//!
+//! A frame holds 14 bits, sent MSB first in Manchester code on a 36 kHz
+//! carrier: every bit takes 1.778 ms, and is a space followed by a burst of
+//! the carrier for 1, or a burst followed by a space for 0. Two start bits are
+//! followed by the toggle bit, a 5 bit address and a 6 bit command. Extended
+//! RC5 sends the inverse of the 7th bit of the command as the second start
+//! bit, for commands up to 127.
+//!
+//! Remotes send the frame every 114 ms while a key is held, and flip the
+//! toggle bit whenever a key is pressed again, so a held key can be told apart
+//! from repeated presses.
+
+use crate::pulse::{pulses, within, PulseWriter};
+
+/// Frequency of the carrier, in Hz
+pub const CARRIER_HZ: u32 = 36_000;
+
+/// Time between the starts of two frames of a held key, in ms
+pub const REPEAT_PERIOD_MS: u32 = 114;
+
+/// Number of pulse codes of a frame, including the end marker
+pub const MAX_CODES: usize = 14;
+
+/// Largest address
+pub const MAX_ADDRESS: u8 = 0x1f;
+
+/// Largest command, using extended RC5
+pub const MAX_COMMAND: u8 = 0x7f;
+
+const HALF_BIT_US: u32 = 889;
+const BITS: u32 = 14;
+
+/// A decoded RC5 frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame {
+    /// The toggle bit, which flips whenever a key is pressed
+    pub toggle: bool,
+    /// The address, up to [MAX_ADDRESS]
+    pub address: u8,
+    /// The command, up to [MAX_COMMAND]
+    pub command: u8,
+}
+
+/// Encode a frame into `buffer`, and return the number of pulse codes.
+///
+/// Only the bits up to [MAX_ADDRESS] and [MAX_COMMAND] are sent. `buffer` has
+/// to hold at least [MAX_CODES] codes.
+pub fn encode(frame: &Frame, buffer: &mut [u32]) -> usize {
+    let bits = 1 << 13
+        | ((!frame.command >> 6) as u32 & 1) << 12
+        | (frame.toggle as u32) << 11
+        | ((frame.address & MAX_ADDRESS) as u32) << 6
+        | (frame.command & 0x3f) as u32;
+
+    // The first half of the first start bit is a space, which is the idle
+    // level of the line, so the pulses start with its second half
+    let mut writer = PulseWriter::new(buffer);
+    let mut pulse = (true, HALF_BIT_US);
+    for bit in (0..BITS - 1).rev() {
+        let one = bits & (1 << bit) != 0;
+        for mark in [!one, one] {
+            match pulse.0 == mark {
+                true => pulse.1 += HALF_BIT_US,
+                false => {
+                    writer.push(pulse.0, pulse.1);
+                    pulse = (mark, HALF_BIT_US);
+                }
+            }
+        }
+    }
+    // A trailing space is the idle level of the line as well
+    if pulse.0 {
+        writer.push(pulse.0, pulse.1);
+    }
+
+    writer.finish()
+}
+
+/// Decode the pulse codes of a received frame.
+///
+/// `mark_level` is the level of the pin during a burst of the carrier. Returns
+/// `None` if the pulses are not a valid RC5 frame.
+pub fn decode(codes: &[u32], mark_level: bool) -> Option<Frame> {
+    // One bit per half bit, set for marks. The first half of the first start
+    // bit is a space before the first edge.
+    let mut halves = 0u32;
+    let mut count = 1;
+    for (mark, us) in pulses(codes, mark_level) {
+        let length = match (within(us, HALF_BIT_US), within(us, 2 * HALF_BIT_US)) {
+            (true, _) => 1,
+            (_, true) => 2,
+            _ => return None,
+        };
+        for _ in 0..length {
+            if count == 2 * BITS {
+                return None;
+            }
+            halves |= (mark as u32) << count;
+            count += 1;
+        }
+    }
+    // A frame which ends with a 0 ends with a space, which merges into the
+    // idle level of the line
+    if count == 2 * BITS - 1 {
+        count += 1;
+    }
+    if count != 2 * BITS {
+        return None;
+    }
+
+    let mut bits = 0u32;
+    for bit in 0..BITS {
+        bits <<= 1;
+        match (halves >> (2 * bit)) & 0b11 {
+            // A space, then a mark
+            0b10 => bits |= 1,
+            0b01 => {}
+            _ => return None,
+        }
+    }
+    if bits & (1 << 13) == 0 {
+        return None;
+    }
+
+    Some(Frame {
+        toggle: bits & (1 << 11) != 0,
+        address: (bits >> 6) as u8 & MAX_ADDRESS,
+        command: (bits as u8 & 0x3f) | ((!(bits >> 12) as u8 & 1) << 6),
+    })
+}
@@ -0,0 +1,283 @@
+use esp_hal::{
+    gpio::InputPin,
+    peripheral::Peripheral,
+    rmt::{
+        RxChannel, RxChannelAsync, RxChannelConfig, RxChannelCreator, RxChannelCreatorAsync,
+        RxChannelInternal,
+    },
+    time::{now, Instant},
+    Blocking,
+};
+
+use fugit::HertzU32;
+
+use crate::{clk_divider, nec, rc5, Command, Error, Protocol, BUFFER_SIZE};
+
+/// Time without an edge after which the receive channel stops, in µs
+///
+/// This is longer than any pulse of the protocols, and shorter than the gap
+/// between two frames.
+const IDLE_THRESHOLD_US: u32 = 12_000;
+
+/// Pulses shorter than this many ticks of the RMT clock are ignored, which is
+/// about 3 µs at 80 MHz
+const FILTER_THRESHOLD: u8 = u8::MAX;
+
+/// The last command, for detecting repeats
+#[derive(Debug, Clone, Copy)]
+struct Last {
+    command: Command,
+    toggle: bool,
+    at: Instant,
+}
+
+impl Last {
+    /// Whether a repeat of the command received at `at` would be in time
+    fn is_recent(&self, at: Instant) -> bool {
+        let timeout_ms = self.command.protocol.repeat_period_ms() as u64 * 5 / 2;
+
+        at >= self.at && (at - self.at).to_millis() <= timeout_ms
+    }
+}
+
+/// Decodes received pulse codes into commands, and detects repeats
+///
+/// The receivers decode with it, but it can decode pulse codes captured in any
+/// other way, as long as their lengths are in µs.
+#[derive(Debug, Clone, Copy)]
+pub struct Decoder {
+    mark_level: bool,
+    last: Option<Last>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Create a new decoder for a demodulator with an active low output.
+    pub const fn new() -> Self {
+        Self {
+            mark_level: false,
+            last: None,
+        }
+    }
+
+    /// Set whether the output of the demodulator is high while it receives
+    /// the carrier.
+    ///
+    /// Demodulators like the TSOP38238 or the VS1838B pull their output low
+    /// while they receive the carrier, which is the default.
+    pub fn set_active_high(&mut self, active_high: bool) {
+        self.mark_level = active_high;
+    }
+
+    /// Forget the last command, so the next one is not taken for a repeat.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Decode the pulse codes of a frame, received at the current time.
+    ///
+    /// A NEC repeat code is decoded as the last command, and an RC5 frame as
+    /// a repeat if it holds the last command and its toggle bit is unchanged.
+    /// Repeats are only detected within two and a half repeat periods of the
+    /// last frame, so a single lost frame is tolerated. Returns `None` if the
+    /// pulses are not a valid frame, or for a NEC repeat code without a
+    /// command to repeat.
+    pub fn decode(&mut self, codes: &[u32]) -> Option<Command> {
+        let at = now();
+
+        if let Some(frame) = nec::decode(codes, self.mark_level) {
+            let command = match frame {
+                nec::Frame::Command { address, command } => Command {
+                    protocol: Protocol::Nec,
+                    address,
+                    command,
+                    repeat: false,
+                },
+                nec::Frame::Repeat => {
+                    let last = self.last.filter(|last| {
+                        last.command.protocol == Protocol::Nec && last.is_recent(at)
+                    })?;
+                    Command {
+                        repeat: true,
+                        ..last.command
+                    }
+                }
+            };
+            self.last = Some(Last {
+                command,
+                toggle: false,
+                at,
+            });
+
+            return Some(command);
+        }
+
+        let frame = rc5::decode(codes, self.mark_level)?;
+        let mut command = Command {
+            protocol: Protocol::Rc5,
+            address: frame.address as u16,
+            command: frame.command,
+            repeat: false,
+        };
+        command.repeat = self.last.is_some_and(|last| {
+            last.command.protocol == Protocol::Rc5
+                && last.command.address == command.address
+                && last.command.command == command.command
+                && last.toggle == frame.toggle
+                && last.is_recent(at)
+        });
+        self.last = Some(Last {
+            command,
+            toggle: frame.toggle,
+            at,
+        });
+
+        Some(command)
+    }
+}
+
+/// Configuration of a receive channel counting in µs, for the frequency of
+/// the RMT peripheral
+fn channel_config(frequency: HertzU32) -> Result<RxChannelConfig, Error> {
+    Ok(RxChannelConfig {
+        clk_divider: clk_divider(frequency)?,
+        idle_threshold: IDLE_THRESHOLD_US as u16,
+        filter_threshold: FILTER_THRESHOLD,
+
+        ..RxChannelConfig::default()
+    })
+}
+
+/// Receives commands from an infrared demodulator with an RMT channel
+pub struct IrReceiver<RX>
+where
+    RX: RxChannel,
+{
+    channel: Option<RX>,
+    decoder: Decoder,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+impl<'d, RX> IrReceiver<RX>
+where
+    RX: RxChannel,
+{
+    /// Create a new receiver for the demodulator on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`.
+    pub fn new<C, P>(
+        channel: C,
+        pin: impl Peripheral<P = P> + 'd,
+        frequency: HertzU32,
+    ) -> Result<Self, Error>
+    where
+        P: InputPin + 'd,
+        C: RxChannelCreator<'d, RX, P>,
+    {
+        let channel = channel.configure(pin, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel: Some(channel),
+            decoder: Decoder::new(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// The decoder of the receiver
+    pub fn decoder(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
+    /// Wait for the next command.
+    ///
+    /// Frames which can not be decoded, like those of other protocols, are
+    /// skipped.
+    pub fn receive(&mut self) -> Result<Command, Error> {
+        loop {
+            self.buffer.fill(0);
+
+            let channel = self.channel.take().unwrap();
+            let transaction = match channel.receive(&mut self.buffer) {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+                    return Err(e.into());
+                }
+            };
+            match transaction.wait() {
+                Ok(channel) => self.channel = Some(channel),
+                Err((e, channel)) => {
+                    self.channel = Some(channel);
+                    return Err(e.into());
+                }
+            }
+
+            if let Some(command) = self.decoder.decode(&self.buffer) {
+                return Ok(command);
+            }
+        }
+    }
+}
+
+/// Receives commands from an infrared demodulator with an async RMT channel
+pub struct IrReceiverAsync<RX>
+where
+    RX: RxChannelAsync,
+{
+    channel: RX,
+    decoder: Decoder,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+impl<'d, RX> IrReceiverAsync<RX>
+where
+    RX: RxChannelAsync,
+{
+    /// Create a new receiver for the demodulator on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`.
+    pub fn new<C, P>(
+        channel: C,
+        pin: impl Peripheral<P = P> + 'd,
+        frequency: HertzU32,
+    ) -> Result<Self, Error>
+    where
+        P: InputPin + 'd,
+        C: RxChannelCreatorAsync<'d, RX, P>,
+    {
+        let channel = channel.configure(pin, channel_config(frequency)?)?;
+
+        Ok(Self {
+            channel,
+            decoder: Decoder::new(),
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// The decoder of the receiver
+    pub fn decoder(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
+    /// Wait for the next command.
+    ///
+    /// Frames which can not be decoded, like those of other protocols, are
+    /// skipped.
+    pub async fn receive(&mut self) -> Result<Command, Error> {
+        loop {
+            self.buffer.fill(0);
+            self.channel.receive(&mut self.buffer).await?;
+
+            if let Some(command) = self.decoder.decode(&self.buffer) {
+                return Ok(command);
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use esp_hal::{
+    gpio::OutputPin,
+    peripheral::Peripheral,
+    rmt::{
+        TxChannel, TxChannelAsync, TxChannelConfig, TxChannelCreator, TxChannelCreatorAsync,
+        TxChannelInternal,
+    },
+    Blocking,
+};
+
+use fugit::HertzU32;
+
+use crate::{clk_divider, nec, rc5, Error, Protocol, BUFFER_SIZE};
+
+/// Share of the carrier period the LED is on, in percent
+///
+/// Short bursts let the LED be driven with more current for more range.
+const CARRIER_DUTY_PCT: u32 = 33;
+
+/// Configuration of a transmit channel counting in µs, with the carrier of
+/// the protocol, for the frequency of the RMT peripheral
+fn channel_config(protocol: Protocol, frequency: HertzU32) -> Result<TxChannelConfig, Error> {
+    let clk_divider = clk_divider(frequency)?;
+    // The carrier is generated from the undivided clock
+    let period = frequency.raw() / protocol.carrier_hz();
+    let high = period * CARRIER_DUTY_PCT / 100;
+
+    Ok(TxChannelConfig {
+        clk_divider,
+        idle_output_level: false,
+        idle_output: true,
+        carrier_modulation: true,
+        carrier_high: high as u16,
+        carrier_low: (period - high) as u16,
+        carrier_level: true,
+    })
+}
+
+/// Encodes the commands of a transmitter
+#[derive(Debug)]
+struct Encoder {
+    protocol: Protocol,
+    toggle: bool,
+    last: Option<(u16, u8)>,
+    buffer: [u32; BUFFER_SIZE],
+}
+
+impl Encoder {
+    fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            toggle: false,
+            last: None,
+            buffer: [0; BUFFER_SIZE],
+        }
+    }
+
+    /// Encode a new command, and return the number of pulse codes.
+    fn encode(&mut self, address: u16, command: u8) -> Result<usize, Error> {
+        if self.protocol == Protocol::Rc5
+            && (address > rc5::MAX_ADDRESS as u16 || command > rc5::MAX_COMMAND)
+        {
+            return Err(Error::OutOfRange);
+        }
+
+        self.toggle = !self.toggle;
+        self.last = Some((address, command));
+
+        Ok(self.encode_last(false))
+    }
+
+    /// Encode a repeat of the last command, and return the number of pulse
+    /// codes.
+    fn encode_repeat(&mut self) -> Result<usize, Error> {
+        if self.last.is_none() {
+            return Err(Error::NothingToRepeat);
+        }
+
+        Ok(self.encode_last(true))
+    }
+
+    fn encode_last(&mut self, repeat: bool) -> usize {
+        let (address, command) = self.last.unwrap_or_default();
+
+        match self.protocol {
+            Protocol::Nec if repeat => nec::encode_repeat(&mut self.buffer),
+            Protocol::Nec => nec::encode(address, command, &mut self.buffer),
+            Protocol::Rc5 => rc5::encode(
+                &rc5::Frame {
+                    toggle: self.toggle,
+                    address: address as u8,
+                    command,
+                },
+                &mut self.buffer,
+            ),
+        }
+    }
+}
+
+/// Sends commands with an infrared LED on an RMT channel
+pub struct IrTransmitter<TX>
+where
+    TX: TxChannel,
+{
+    channel: Option<TX>,
+    encoder: Encoder,
+}
+
+impl<'d, TX> IrTransmitter<TX>
+where
+    TX: TxChannel,
+{
+    /// Create a new transmitter for the protocol, with the LED on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`. The pin is high while the carrier is sent, and drives the
+    /// LED through a transistor, as a GPIO can not source enough current for
+    /// it.
+    pub fn new<C, P>(
+        channel: C,
+        pin: impl Peripheral<P = P> + 'd,
+        frequency: HertzU32,
+        protocol: Protocol,
+    ) -> Result<Self, Error>
+    where
+        P: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, P>,
+    {
+        let channel = channel.configure(pin, channel_config(protocol, frequency)?)?;
+
+        Ok(Self {
+            channel: Some(channel),
+            encoder: Encoder::new(protocol),
+        })
+    }
+
+    /// The protocol of the transmitter
+    pub fn protocol(&self) -> Protocol {
+        self.encoder.protocol
+    }
+
+    /// Send a command, for a new press of a key.
+    ///
+    /// RC5 addresses and commands have to be within [rc5::MAX_ADDRESS] and
+    /// [rc5::MAX_COMMAND].
+    pub fn send(&mut self, address: u16, command: u8) -> Result<(), Error> {
+        let len = self.encoder.encode(address, command)?;
+        self.transmit(len)
+    }
+
+    /// Repeat the last command, for a held key.
+    ///
+    /// Repeats have to be sent every [Protocol::repeat_period_ms], counted
+    /// from the start of the last command or repeat.
+    pub fn send_repeat(&mut self) -> Result<(), Error> {
+        let len = self.encoder.encode_repeat()?;
+        self.transmit(len)
+    }
+
+    fn transmit(&mut self, len: usize) -> Result<(), Error> {
+        let channel = self.channel.take().unwrap();
+        let transaction = match channel.transmit(&self.encoder.buffer[..len]) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                self.channel = Some(<TX as TxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        };
+
+        match transaction.wait() {
+            Ok(channel) => {
+                self.channel = Some(channel);
+                Ok(())
+            }
+            Err((e, channel)) => {
+                self.channel = Some(channel);
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Sends commands with an infrared LED on an async RMT channel
+pub struct IrTransmitterAsync<TX>
+where
+    TX: TxChannelAsync,
+{
+    channel: TX,
+    encoder: Encoder,
+}
+
+impl<'d, TX> IrTransmitterAsync<TX>
+where
+    TX: TxChannelAsync,
+{
+    /// Create a new transmitter for the protocol, with the LED on the pin.
+    ///
+    /// `frequency` is the one the RMT peripheral was set up with in
+    /// `Rmt::new`. The pin is high while the carrier is sent, and drives the
+    /// LED through a transistor, as a GPIO can not source enough current for
+    /// it.
+    pub fn new<C, P>(
+        channel: C,
+        pin: impl Peripheral<P = P> + 'd,
+        frequency: HertzU32,
+        protocol: Protocol,
+    ) -> Result<Self, Error>
+    where
+        P: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, P>,
+    {
+        let channel = channel.configure(pin, channel_config(protocol, frequency)?)?;
+
+        Ok(Self {
+            channel,
+            encoder: Encoder::new(protocol),
+        })
+    }
+
+    /// The protocol of the transmitter
+    pub fn protocol(&self) -> Protocol {
+        self.encoder.protocol
+    }
+
+    /// Send a command, for a new press of a key.
+    ///
+    /// RC5 addresses and commands have to be within [rc5::MAX_ADDRESS] and
+    /// [rc5::MAX_COMMAND].
+    pub async fn send(&mut self, address: u16, command: u8) -> Result<(), Error> {
+        let len = self.encoder.encode(address, command)?;
+        self.channel.transmit(&self.encoder.buffer[..len]).await?;
+
+        Ok(())
+    }
+
+    /// Repeat the last command, for a held key.
+    ///
+    /// Repeats have to be sent every [Protocol::repeat_period_ms], counted
+    /// from the start of the last command or repeat.
+    pub async fn send_repeat(&mut self) -> Result<(), Error> {
+        let len = self.encoder.encode_repeat()?;
+        self.channel.transmit(&self.encoder.buffer[..len]).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+//! Print the keys pressed and released on a 4×3 telephone keypad.
+//!
+//! This assumes that the rows of the keypad are connected to GPIO0 to GPIO3
+//! and its columns to GPIO4 to GPIO6.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{delay::Delay, gpio::Pin, prelude::*};
+use esp_hal_keypad::{Config, Event, Keypad};
+use esp_println::println;
+
+const KEYMAP: [char; 12] = ['1', '2', '3', '4', '5', '6', '7', '8', '9', '*', '0', '#'];
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let config = Config::default();
+    let mut keypad = Keypad::new(
+        [
+            peripherals.GPIO0.degrade(),
+            peripherals.GPIO1.degrade(),
+            peripherals.GPIO2.degrade(),
+            peripherals.GPIO3.degrade(),
+        ],
+        [
+            peripherals.GPIO4.degrade(),
+            peripherals.GPIO5.degrade(),
+            peripherals.GPIO6.degrade(),
+        ],
+        config,
+    )
+    .unwrap();
+
+    let delay = Delay::new();
+
+    loop {
+        keypad.scan();
+
+        while let Some(event) = keypad.poll_event() {
+            match event {
+                Event::Pressed(key) => println!("Pressed {}", KEYMAP[key.index(3)]),
+                Event::Released(key) => println!("Released {}", KEYMAP[key.index(3)]),
+            }
+        }
+
+        delay.delay_millis(config.scan_interval_ms);
+    }
+}
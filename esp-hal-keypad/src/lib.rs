@@ -0,0 +1,384 @@
+//! # Matrix Keypad
+//!
+//! ## Overview
+//! This driver scans matrix keypads, whose keys connect a row line to a column
+//! line when pressed, with plain GPIOs.
+//!
+//! The rows are driven as open-drain outputs and the columns are read as
+//! inputs with pull-ups. A scan pulls one row low at a time and reads which
+//! columns follow it, so every key of a keypad with `ROWS` rows and `COLS`
+//! columns is sampled once per scan.
+//!
+//! A key only changes state after reading the same level for a number of
+//! consecutive scans, which filters the bouncing of its contacts. Keypads
+//! without diodes cannot tell apart three keys pressed at the corners of a
+//! rectangle from all four of its corners, so scans with two rows sharing two
+//! pressed columns are discarded as ghosting until the keys are released.
+//!
+//! Every change of a debounced key queues an [Event]. Events are either polled
+//! after calling [Keypad::scan] at the scan interval, or awaited with
+//! [Keypad::next_event] with the `embassy-time` feature, which runs the scans
+//! from a timer.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut keypad = Keypad::new(
+//!     [
+//!         peripherals.GPIO0.degrade(),
+//!         peripherals.GPIO1.degrade(),
+//!         peripherals.GPIO2.degrade(),
+//!         peripherals.GPIO3.degrade(),
+//!     ],
+//!     [
+//!         peripherals.GPIO4.degrade(),
+//!         peripherals.GPIO5.degrade(),
+//!         peripherals.GPIO6.degrade(),
+//!     ],
+//!     Config::default(),
+//! )
+//! .unwrap();
+//!
+//! loop {
+//!     match keypad.next_event().await {
+//!         Event::Pressed(key) => println!("Pressed {}:{}", key.row, key.col),
+//!         Event::Released(key) => println!("Released {}:{}", key.row, key.col),
+//!     }
+//! }
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{AnyPin, Flex, Input, Pull},
+};
+
+/// Number of events held by the queue of a keypad
+pub const QUEUE_SIZE: usize = 16;
+
+/// Maximum number of columns of a keypad, one bit of a row each
+pub const MAX_COLUMNS: usize = 32;
+
+/// Time for the columns to follow a row pulled low, in µs
+const SETTLE_US: u32 = 5;
+
+/// Errors from a keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The keypad has more than [MAX_COLUMNS] columns
+    Columns,
+    /// The scan interval is 0 ms
+    ScanInterval,
+    /// The number of scans to debounce keys is 0
+    Debounce,
+}
+
+/// Configuration of a keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Interval between scans, in ms
+    pub scan_interval_ms: u32,
+    /// Number of consecutive scans a key must read the same before it changes
+    /// state
+    pub debounce_scans: u8,
+}
+
+impl Config {
+    /// Creates the default configuration, scanning every 5 ms and debouncing
+    /// keys for 4 scans
+    pub const fn new() -> Self {
+        Self {
+            scan_interval_ms: 5,
+            debounce_scans: 4,
+        }
+    }
+
+    /// Sets the interval between scans, in ms
+    pub const fn with_scan_interval_ms(mut self, scan_interval_ms: u32) -> Self {
+        self.scan_interval_ms = scan_interval_ms;
+        self
+    }
+
+    /// Sets the number of consecutive scans to debounce keys
+    pub const fn with_debounce_scans(mut self, debounce_scans: u8) -> Self {
+        self.debounce_scans = debounce_scans;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Position of a key in the matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Key {
+    /// Row of the key, from 0
+    pub row: u8,
+    /// Column of the key, from 0
+    pub col: u8,
+}
+
+impl Key {
+    /// Returns the index of the key in a keymap of `cols` columns, row by row
+    pub const fn index(&self, cols: usize) -> usize {
+        self.row as usize * cols + self.col as usize
+    }
+}
+
+/// Change of the state of a debounced key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The key was pressed
+    Pressed(Key),
+    /// The key was released
+    Released(Key),
+}
+
+impl Event {
+    /// Returns the key that changed
+    pub const fn key(&self) -> Key {
+        match self {
+            Event::Pressed(key) | Event::Released(key) => *key,
+        }
+    }
+}
+
+/// Ring buffer of events, dropping the newest ones when full
+struct Queue {
+    events: [Option<Event>; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Self {
+            events: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: Event) -> bool {
+        if self.len == QUEUE_SIZE {
+            return false;
+        }
+
+        self.events[(self.head + self.len) % QUEUE_SIZE] = Some(event);
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        event
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Returns whether two rows share two pressed columns, which is the only
+/// sign of a ghost key in a matrix without diodes
+fn is_ghosting(rows: &[u32]) -> bool {
+    rows.iter()
+        .enumerate()
+        .any(|(i, a)| rows[i + 1..].iter().any(|b| (a & b).count_ones() >= 2))
+}
+
+/// Scanner of a keypad with `ROWS` rows and `COLS` columns
+pub struct Keypad<'d, const ROWS: usize, const COLS: usize> {
+    rows: [Flex<'d>; ROWS],
+    cols: [Input<'d>; COLS],
+    config: Config,
+    delay: Delay,
+    /// Debounced state of the keys, one bit per column
+    state: [u32; ROWS],
+    /// Number of consecutive scans each key has read differently from its
+    /// state
+    counts: [[u8; COLS]; ROWS],
+    queue: Queue,
+    ghosting: bool,
+    dropped: usize,
+}
+
+impl<'d, const ROWS: usize, const COLS: usize> Keypad<'d, ROWS, COLS> {
+    /// Creates a keypad from the pins of its rows and columns
+    ///
+    /// The rows are driven as open-drain outputs, released between scans, and
+    /// the columns use the internal pull-ups.
+    pub fn new(rows: [AnyPin; ROWS], cols: [AnyPin; COLS], config: Config) -> Result<Self, Error> {
+        if COLS > MAX_COLUMNS {
+            return Err(Error::Columns);
+        }
+        if config.scan_interval_ms == 0 {
+            return Err(Error::ScanInterval);
+        }
+        if config.debounce_scans == 0 {
+            return Err(Error::Debounce);
+        }
+
+        let rows = rows.map(|pin| {
+            let mut row = Flex::new(pin);
+            row.set_high();
+            row.set_as_open_drain(Pull::None);
+            row
+        });
+        let cols = cols.map(|pin| Input::new(pin, Pull::Up));
+
+        Ok(Self {
+            rows,
+            cols,
+            config,
+            delay: Delay::new(),
+            state: [0; ROWS],
+            counts: [[0; COLS]; ROWS],
+            queue: Queue::new(),
+            ghosting: false,
+            dropped: 0,
+        })
+    }
+
+    /// Returns the configuration of the keypad
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Scans every key once, queueing the events of the keys that changed
+    ///
+    /// This should be called every [Config::scan_interval_ms] when not using
+    /// [Self::next_event], since keys are debounced by counting scans.
+    pub fn scan(&mut self) {
+        let mut raw = [0u32; ROWS];
+
+        for (row, bits) in self.rows.iter_mut().zip(raw.iter_mut()) {
+            row.set_low();
+            self.delay.delay_micros(SETTLE_US);
+
+            for (col, input) in self.cols.iter().enumerate() {
+                if input.is_low() {
+                    *bits |= 1 << col;
+                }
+            }
+
+            row.set_high();
+        }
+
+        // Keep the keys as they were until the ambiguous keys are released
+        self.ghosting = is_ghosting(&raw);
+        if self.ghosting {
+            return;
+        }
+
+        for (row, &bits) in raw.iter().enumerate() {
+            for col in 0..COLS {
+                let mask = 1 << col;
+                let count = &mut self.counts[row][col];
+
+                if (bits ^ self.state[row]) & mask == 0 {
+                    *count = 0;
+                    continue;
+                }
+
+                *count += 1;
+                if *count < self.config.debounce_scans {
+                    continue;
+                }
+
+                *count = 0;
+                self.state[row] ^= mask;
+
+                let key = Key {
+                    row: row as u8,
+                    col: col as u8,
+                };
+                let event = if bits & mask != 0 {
+                    Event::Pressed(key)
+                } else {
+                    Event::Released(key)
+                };
+
+                if !self.queue.push(event) {
+                    self.dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the oldest queued event, without scanning the keypad
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.queue.pop()
+    }
+
+    /// Returns the next event, scanning the keypad at the scan interval until
+    /// one is queued
+    #[cfg(feature = "embassy-time")]
+    pub async fn next_event(&mut self) -> Event {
+        let mut ticker = embassy_time::Ticker::every(embassy_time::Duration::from_millis(
+            self.config.scan_interval_ms as u64,
+        ));
+
+        loop {
+            if let Some(event) = self.queue.pop() {
+                return event;
+            }
+
+            ticker.next().await;
+            self.scan();
+        }
+    }
+
+    /// Drops every queued event
+    pub fn clear_events(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Returns whether a key is pressed, once debounced
+    pub fn is_pressed(&self, key: Key) -> bool {
+        (key.row as usize) < ROWS
+            && (key.col as usize) < COLS
+            && self.state[key.row as usize] & (1 << key.col) != 0
+    }
+
+    /// Returns an iterator over the pressed keys, row by row
+    pub fn pressed(&self) -> impl Iterator<Item = Key> + '_ {
+        (0..ROWS).flat_map(move |row| {
+            (0..COLS)
+                .filter(move |&col| self.state[row] & (1 << col) != 0)
+                .map(move |col| Key {
+                    row: row as u8,
+                    col: col as u8,
+                })
+        })
+    }
+
+    /// Returns whether the last scan was discarded as ghosting
+    pub fn is_ghosting(&self) -> bool {
+        self.ghosting
+    }
+
+    /// Returns the number of events dropped since the keypad was created,
+    /// because the queue was full
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
@@ -0,0 +1,38 @@
+//! Print the position of a rotary encoder whenever it is turned.
+//!
+//! This assumes that the A and B outputs of an EC11 or a similar encoder with
+//! detents are connected to GPIO4 and GPIO5, and its common pin to ground.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{delay::Delay, pcnt::Pcnt, prelude::*};
+use esp_hal_rotary_encoder::{Config, RotaryEncoder, INTERRUPT_HANDLER};
+use esp_println::println;
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let mut pcnt = Pcnt::new(peripherals.PCNT);
+    pcnt.set_interrupt_handler(INTERRUPT_HANDLER);
+
+    let mut encoder = RotaryEncoder::new(
+        pcnt.unit0,
+        peripherals.GPIO4,
+        peripherals.GPIO5,
+        Config::default(),
+    )
+    .unwrap();
+
+    let delay = Delay::new();
+
+    loop {
+        if encoder.delta() != 0 {
+            println!("Position: {}", encoder.position());
+        }
+
+        delay.delay_millis(10);
+    }
+}
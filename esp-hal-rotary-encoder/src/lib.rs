@@ -0,0 +1,390 @@
+//! # Rotary encoder
+//!
+//! ## Overview
+//! This driver provides an abstraction over PCNT to read quadrature rotary
+//! encoders through a user-friendly API.
+//!
+//! The two channels of a unit of the PCNT peripheral count every edge of both
+//! outputs of the encoder, up or down depending on the direction, so no step
+//! is lost to a busy CPU, and the glitch filter of the unit ignores the
+//! shortest bounces of mechanical encoders. Most encoders with detents, like
+//! the EC11, go through four edges from one detent to the next, so the counts
+//! are divided into steps, which makes a [RotaryEncoder] report one step per
+//! detent.
+//!
+//! The counter of a unit only holds 16 bits. It is reset whenever it reaches
+//! a full step in either direction, and the interrupt handler of the driver
+//! adds the step to a 64-bit position, which also wakes the tasks waiting in
+//! [RotaryEncoder::wait_for_change]. The handler has to be installed with
+//! `Pcnt::set_interrupt_handler` before any encoder is created.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut pcnt = Pcnt::new(peripherals.PCNT);
+//! pcnt.set_interrupt_handler(esp_hal_rotary_encoder::INTERRUPT_HANDLER);
+//!
+//! let mut encoder = RotaryEncoder::new(
+//!     pcnt.unit0,
+//!     peripherals.GPIO4,
+//!     peripherals.GPIO5,
+//!     Config::default(),
+//! )
+//! .unwrap();
+//!
+//! loop {
+//!     let position = encoder.wait_for_change().await;
+//!     println!("Position: {position}");
+//! }
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use core::{cell::Cell, future::poll_fn, task::Poll};
+
+use critical_section::{CriticalSection, Mutex};
+use embassy_sync::waitqueue::AtomicWaker;
+use esp_hal::{
+    gpio::{Input, InputPin, Pull},
+    interrupt::{InterruptHandler, Priority},
+    pcnt::{
+        channel::{CtrlMode, EdgeMode},
+        unit::Unit,
+    },
+    peripheral::Peripheral,
+    peripherals::PCNT,
+};
+
+/// Number of units of the PCNT peripheral
+#[cfg(feature = "esp32")]
+const UNITS: usize = 8;
+/// Number of units of the PCNT peripheral
+#[cfg(not(feature = "esp32"))]
+const UNITS: usize = 4;
+
+/// Longest glitch filter the PCNT peripheral supports, in APB clock cycles
+const MAX_FILTER: u16 = 1023;
+
+/// The interrupt handler of the driver, which has to be installed with
+/// `Pcnt::set_interrupt_handler`
+///
+/// Units which are not used by a [RotaryEncoder] are left alone.
+pub const INTERRUPT_HANDLER: InterruptHandler =
+    InterruptHandler::new(on_interrupt, Priority::Priority1);
+
+/// Errors from RotaryEncoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The number of counts per step is 0
+    CountsPerStep,
+
+    /// The glitch filter is longer than 1023 APB clock cycles
+    Filter,
+}
+
+/// Configuration of a [RotaryEncoder]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Number of edges counted per step, usually 4 for encoders with detents
+    pub counts_per_step: u8,
+
+    /// Pulses shorter than this many APB clock cycles are ignored, up to 1023
+    /// cycles, which is 12.8 µs at 80 MHz
+    pub filter: Option<u16>,
+
+    /// Count in the other direction, instead of swapping the pins
+    pub reverse: bool,
+
+    /// Pull resistor of both pins, for encoders without their own pull-ups
+    pub pull: Pull,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Create the default configuration, for encoders with four edges per
+    /// detent, with the longest glitch filter and with pull-ups.
+    pub const fn new() -> Self {
+        Self {
+            counts_per_step: 4,
+            filter: Some(MAX_FILTER),
+            reverse: false,
+            pull: Pull::Up,
+        }
+    }
+
+    /// Set the number of edges counted per step.
+    ///
+    /// Some encoders only go through two edges per detent, and encoders
+    /// without detents are read with the highest resolution with one count
+    /// per step, at the cost of an interrupt for every edge.
+    pub const fn with_counts_per_step(mut self, counts_per_step: u8) -> Self {
+        self.counts_per_step = counts_per_step;
+        self
+    }
+
+    /// Set the glitch filter, in APB clock cycles.
+    pub const fn with_filter(mut self, filter: Option<u16>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Set whether to count in the other direction.
+    pub const fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Set the pull resistor of both pins.
+    pub const fn with_pull(mut self, pull: Pull) -> Self {
+        self.pull = pull;
+        self
+    }
+}
+
+/// Counts of a unit which were taken from its counter
+#[derive(Clone, Copy)]
+struct Accumulator {
+    /// Counts added when the counter was reset
+    counts: i64,
+
+    /// Counts per step, which are the limits of the counter, or 0 if the unit
+    /// is not used by an encoder
+    limit: i16,
+}
+
+/// State of a unit shared with the interrupt handler
+struct UnitState {
+    accumulator: Mutex<Cell<Accumulator>>,
+    waker: AtomicWaker,
+}
+
+static UNIT_STATES: [UnitState; UNITS] = [const {
+    UnitState {
+        accumulator: Mutex::new(Cell::new(Accumulator {
+            counts: 0,
+            limit: 0,
+        })),
+        waker: AtomicWaker::new(),
+    }
+}; UNITS];
+
+/// Whether the counter of unit `unit` reached a limit which was not added to
+/// its accumulator yet
+fn is_pending(unit: usize) -> bool {
+    // SAFETY: the interrupt status is only read
+    let pcnt = unsafe { PCNT::steal() };
+    pcnt.int_raw().read().cnt_thr_event_u(unit as u8).bit()
+}
+
+/// Add the limit the counter of unit `unit` reached to its accumulator, and
+/// wake the task waiting for it.
+fn service(cs: CriticalSection<'_>, unit: usize) {
+    if !is_pending(unit) {
+        return;
+    }
+
+    // SAFETY: the interrupt of a unit is only cleared in a critical section
+    let pcnt = unsafe { PCNT::steal() };
+    let status = pcnt.u_status(unit).read();
+
+    let state = &UNIT_STATES[unit];
+    let mut accumulator = state.accumulator.borrow(cs).get();
+    if status.h_lim().bit() {
+        accumulator.counts += accumulator.limit as i64;
+    } else if status.l_lim().bit() {
+        accumulator.counts -= accumulator.limit as i64;
+    }
+    state.accumulator.borrow(cs).set(accumulator);
+
+    pcnt.int_clr()
+        .write(|w| w.cnt_thr_event_u(unit as u8).set_bit());
+    state.waker.wake();
+}
+
+extern "C" fn on_interrupt() {
+    critical_section::with(|cs| {
+        for (unit, state) in UNIT_STATES.iter().enumerate() {
+            if state.accumulator.borrow(cs).get().limit != 0 {
+                service(cs, unit);
+            }
+        }
+    });
+}
+
+/// A quadrature rotary encoder counted by a PCNT unit
+pub struct RotaryEncoder<'d, const NUM: usize> {
+    unit: Unit<'d, NUM>,
+    _pin_a: Input<'d>,
+    _pin_b: Input<'d>,
+    counts_per_step: i16,
+    last: i64,
+}
+
+impl<'d, const NUM: usize> RotaryEncoder<'d, NUM> {
+    /// Create a new encoder on the unit, with the A and B outputs of the
+    /// encoder on the pins.
+    ///
+    /// The position starts at 0, which should be a detent of the encoder.
+    pub fn new(
+        unit: Unit<'d, NUM>,
+        pin_a: impl Peripheral<P = impl InputPin> + 'd,
+        pin_b: impl Peripheral<P = impl InputPin> + 'd,
+        config: Config,
+    ) -> Result<Self, Error> {
+        if config.counts_per_step == 0 {
+            return Err(Error::CountsPerStep);
+        }
+        if config.filter.is_some_and(|filter| filter > MAX_FILTER) {
+            return Err(Error::Filter);
+        }
+
+        let counts_per_step = config.counts_per_step as i16;
+        unit.set_low_limit(Some(-counts_per_step))
+            .map_err(|_| Error::CountsPerStep)?;
+        unit.set_high_limit(Some(counts_per_step))
+            .map_err(|_| Error::CountsPerStep)?;
+        unit.set_filter(config.filter).map_err(|_| Error::Filter)?;
+        unit.clear();
+
+        let pin_a = Input::new(pin_a, config.pull);
+        let pin_b = Input::new(pin_b, config.pull);
+        let (a, b) = match config.reverse {
+            true => (pin_b.peripheral_input(), pin_a.peripheral_input()),
+            false => (pin_a.peripheral_input(), pin_b.peripheral_input()),
+        };
+
+        // Each channel counts the edges of one output, in the direction given
+        // by the level of the other one
+        let channel = &unit.channel0;
+        channel.set_ctrl_signal(a.clone());
+        channel.set_edge_signal(b.clone());
+        channel.set_ctrl_mode(CtrlMode::Reverse, CtrlMode::Keep);
+        channel.set_input_mode(EdgeMode::Increment, EdgeMode::Decrement);
+
+        let channel = &unit.channel1;
+        channel.set_ctrl_signal(b);
+        channel.set_edge_signal(a);
+        channel.set_ctrl_mode(CtrlMode::Reverse, CtrlMode::Keep);
+        channel.set_input_mode(EdgeMode::Decrement, EdgeMode::Increment);
+
+        critical_section::with(|cs| {
+            service(cs, NUM);
+            UNIT_STATES[NUM].accumulator.borrow(cs).set(Accumulator {
+                counts: 0,
+                limit: counts_per_step,
+            });
+        });
+
+        unit.listen();
+        unit.resume();
+
+        Ok(Self {
+            unit,
+            _pin_a: pin_a,
+            _pin_b: pin_b,
+            counts_per_step,
+            last: 0,
+        })
+    }
+
+    /// The accumulated counts, and the value of the counter
+    fn counts(&self, cs: CriticalSection<'_>) -> (i64, i16) {
+        // The counter is reset when it reaches a limit, so it is only read
+        // when the accumulator holds that limit
+        loop {
+            service(cs, NUM);
+            let value = self.unit.value();
+            if !is_pending(NUM) {
+                return (UNIT_STATES[NUM].accumulator.borrow(cs).get().counts, value);
+            }
+        }
+    }
+
+    /// The position, in steps
+    ///
+    /// The position only changes when the encoder reaches the next step, so
+    /// it does not jitter while the encoder rests on a detent.
+    pub fn position(&self) -> i64 {
+        let (counts, _) = critical_section::with(|cs| self.counts(cs));
+
+        counts / self.counts_per_step as i64
+    }
+
+    /// The position, in edges counted
+    pub fn count(&self) -> i64 {
+        let (counts, value) = critical_section::with(|cs| self.counts(cs));
+
+        counts + value as i64
+    }
+
+    /// Set the position, in steps.
+    ///
+    /// Any part of a step the encoder was turned is discarded.
+    pub fn set_position(&mut self, position: i64) {
+        critical_section::with(|cs| {
+            self.unit.clear();
+            service(cs, NUM);
+
+            let state = &UNIT_STATES[NUM];
+            let mut accumulator = state.accumulator.borrow(cs).get();
+            accumulator.counts = position * self.counts_per_step as i64;
+            state.accumulator.borrow(cs).set(accumulator);
+        });
+
+        self.last = position;
+    }
+
+    /// The number of steps the encoder was turned since the last call of
+    /// [RotaryEncoder::delta] or [RotaryEncoder::wait_for_change]
+    pub fn delta(&mut self) -> i64 {
+        let position = self.position();
+        let delta = position - self.last;
+        self.last = position;
+
+        delta
+    }
+
+    /// Wait until the position differs from the one at the last call of
+    /// [RotaryEncoder::delta] or [RotaryEncoder::wait_for_change], and return
+    /// it.
+    ///
+    /// This relies on [INTERRUPT_HANDLER] to be woken up.
+    pub async fn wait_for_change(&mut self) -> i64 {
+        let position = poll_fn(|cx| {
+            UNIT_STATES[NUM].waker.register(cx.waker());
+
+            match self.position() {
+                position if position != self.last => Poll::Ready(position),
+                _ => Poll::Pending,
+            }
+        })
+        .await;
+        self.last = position;
+
+        position
+    }
+}
+
+impl<const NUM: usize> Drop for RotaryEncoder<'_, NUM> {
+    fn drop(&mut self) {
+        self.unit.pause();
+
+        critical_section::with(|cs| {
+            UNIT_STATES[NUM].accumulator.borrow(cs).set(Accumulator {
+                counts: 0,
+                limit: 0,
+            });
+        });
+    }
+}
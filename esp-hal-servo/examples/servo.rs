@@ -0,0 +1,57 @@
+//! Sweep two servos plugged on GPIO4 and GPIO5.
+//!
+//! This assumes that SG90 micro servos are connected to the pins assigned to
+//! `pan` (GPIO4) and `tilt` (GPIO5), powered from a separate 5V supply which
+//! shares its ground with the board.
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{
+    delay::Delay,
+    ledc::{channel, timer, LSGlobalClkSource, Ledc},
+    prelude::*,
+};
+use esp_hal_servo::{Servo, ServoModel, ServoTimer};
+use esp_println::println;
+
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    // Both servos share the same timer
+    let timer = ServoTimer::new(&ledc, timer::Number::Timer0).unwrap();
+
+    let mut pan = Servo::new(
+        &timer,
+        ledc.channel(channel::Number::Channel0, peripherals.GPIO4),
+        ServoModel::SG90,
+    )
+    .unwrap();
+    let mut tilt = Servo::new(
+        &timer,
+        ledc.channel(channel::Number::Channel1, peripherals.GPIO5),
+        ServoModel::SG90,
+    )
+    .unwrap();
+
+    pan.center().unwrap();
+    tilt.center().unwrap();
+
+    let delay = Delay::new();
+    delay.delay_millis(1000);
+
+    loop {
+        for angle in (0..=180).chain((0..180).rev()) {
+            pan.set_angle(angle as f32).unwrap();
+            tilt.set_angle(180.0 - angle as f32).unwrap();
+            delay.delay_millis(10);
+        }
+
+        println!("Sweep done");
+    }
+}
@@ -0,0 +1,300 @@
+//! # Servo
+//!
+//! ## Overview
+//! This driver provides an abstraction over LEDC to drive hobby servos through
+//! a user-friendly API.
+//!
+//! Hobby servos take a pulse every 20 ms, whose width sets the position of the
+//! horn: usually 1 ms for one end of the range and 2 ms for the other, but
+//! many servos accept a wider range, and every model maps it to a different
+//! angle. A [ServoModel] holds the pulse range and the angle it covers, with
+//! presets for common models, and can be calibrated for a single servo.
+//!
+//! A [ServoTimer] configures a LEDC timer for the frame of the pulses. Every
+//! [Servo] drives one LEDC channel from such a timer, so several servos can
+//! share one timer across the channels of the LEDC peripheral.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut ledc = Ledc::new(peripherals.LEDC);
+//! ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+//!
+//! let timer = ServoTimer::new(&ledc, timer::Number::Timer0).unwrap();
+//!
+//! let mut pan = Servo::new(
+//!     &timer,
+//!     ledc.channel(channel::Number::Channel0, peripherals.GPIO4),
+//!     ServoModel::SG90,
+//! )
+//! .unwrap();
+//! let mut tilt = Servo::new(
+//!     &timer,
+//!     ledc.channel(channel::Number::Channel1, peripherals.GPIO5),
+//!     ServoModel::SG90.with_pulse_range(550, 2350),
+//! )
+//! .unwrap();
+//!
+//! pan.set_angle(90.0).unwrap();
+//! tilt.set_angle(45.0).unwrap();
+//! ```
+//!
+//! ## Feature Flags
+#![doc = document_features::document_features!()]
+#![doc(html_logo_url = "https://avatars.githubusercontent.com/u/46717278")]
+#![deny(missing_docs)]
+#![no_std]
+
+use esp_hal::ledc::{
+    channel::{self, Channel, ChannelHW, ChannelIFace},
+    timer::{self, Timer, TimerIFace},
+    Ledc, LowSpeed,
+};
+use fugit::RateExtU32;
+
+/// Frequency of the pulses expected by analog servos, in Hz
+pub const DEFAULT_FREQUENCY: u32 = 50;
+
+/// Resolution of the duty cycle, which gives steps of about 1.2 µs at 50 Hz
+const DUTY: timer::config::Duty = timer::config::Duty::Duty14Bit;
+
+/// Errors from Servo
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Errors from [channel::Error]
+    Channel(channel::Error),
+
+    /// Errors from [timer::Error]
+    Timer(timer::Error),
+
+    /// The pulse is not shorter than the period of the timer
+    PulseOutOfRange,
+}
+
+/// Converts [channel::Error] into [self::Error]
+impl From<channel::Error> for Error {
+    fn from(error: channel::Error) -> Self {
+        Error::Channel(error)
+    }
+}
+
+/// Converts [timer::Error] into [self::Error]
+impl From<timer::Error> for Error {
+    fn from(error: timer::Error) -> Self {
+        Error::Timer(error)
+    }
+}
+
+/// Pulse range of a servo model, and the angle it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ServoModel {
+    /// Width of the pulse at 0°, in µs
+    pub min_us: u32,
+
+    /// Width of the pulse at the end of the range, in µs
+    pub max_us: u32,
+
+    /// Angle between both ends of the range, in degrees
+    pub range_deg: u16,
+}
+
+impl ServoModel {
+    /// The classic pulse range of 1 ms to 2 ms, covering 90° on most servos
+    pub const STANDARD: Self = Self::new(1000, 2000, 90);
+
+    /// TowerPro SG90 and compatible micro servos
+    pub const SG90: Self = Self::new(500, 2400, 180);
+
+    /// TowerPro MG90S metal gear micro servos
+    pub const MG90S: Self = Self::new(500, 2400, 180);
+
+    /// TowerPro MG996R and compatible standard size servos
+    pub const MG996R: Self = Self::new(500, 2500, 180);
+
+    /// Create a new model covering `range_deg` degrees between pulses of
+    /// `min_us` and `max_us`.
+    pub const fn new(min_us: u32, max_us: u32, range_deg: u16) -> Self {
+        Self {
+            min_us,
+            max_us,
+            range_deg,
+        }
+    }
+
+    /// Calibrate the pulses at both ends of the range, keeping the angle it
+    /// covers.
+    ///
+    /// Servos of the same model differ slightly, and driving one against its
+    /// end stop makes it buzz and draw a lot of current.
+    pub const fn with_pulse_range(mut self, min_us: u32, max_us: u32) -> Self {
+        self.min_us = min_us;
+        self.max_us = max_us;
+        self
+    }
+
+    /// Width of the pulse for an angle in degrees, which is clamped to the
+    /// range of the model.
+    pub fn pulse_us(&self, degrees: f32) -> u32 {
+        let range = self.range_deg as f32;
+        let position = degrees.clamp(0.0, range) / range;
+        let span = self.max_us as f32 - self.min_us as f32;
+
+        (self.min_us as f32 + span * position + 0.5) as u32
+    }
+
+    /// Angle in degrees for a pulse width, which is clamped to the range of
+    /// the model.
+    pub fn angle(&self, pulse_us: u32) -> f32 {
+        let (low, high) = match self.min_us <= self.max_us {
+            true => (self.min_us, self.max_us),
+            false => (self.max_us, self.min_us),
+        };
+        if low == high {
+            return 0.0;
+        }
+
+        let pulse = pulse_us.clamp(low, high) as f32;
+        let position = (pulse - self.min_us as f32) / (self.max_us as f32 - self.min_us as f32);
+
+        position * self.range_deg as f32
+    }
+}
+
+/// A LEDC timer running at the frequency of servo pulses
+pub struct ServoTimer<'d> {
+    timer: Timer<'d, LowSpeed>,
+    period_us: u32,
+}
+
+impl<'d> ServoTimer<'d> {
+    /// Configure a timer for servos at 50 Hz
+    pub fn new(ledc: &Ledc<'d>, number: timer::Number) -> Result<Self, Error> {
+        Self::new_with_frequency(ledc, number, DEFAULT_FREQUENCY)
+    }
+
+    /// Configure a timer for servos at the given frequency
+    ///
+    /// Many digital servos accept pulses at up to 333 Hz, which makes them
+    /// react faster. Check the datasheet first: analog servos can overheat
+    /// at frequencies above 50 Hz.
+    pub fn new_with_frequency(
+        ledc: &Ledc<'d>,
+        number: timer::Number,
+        frequency: u32,
+    ) -> Result<Self, Error> {
+        let mut timer = ledc.timer::<LowSpeed>(number);
+        timer.configure(timer::config::Config {
+            duty: DUTY,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: frequency.Hz(),
+        })?;
+
+        Ok(Self {
+            timer,
+            period_us: 1_000_000 / frequency.max(1),
+        })
+    }
+
+    /// Time between the starts of two pulses, in µs
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+}
+
+/// A servo driven by a LEDC channel
+pub struct Servo<'a> {
+    channel: Channel<'a, LowSpeed>,
+    model: ServoModel,
+    period_us: u32,
+    pulse_us: Option<u32>,
+}
+
+impl<'a> Servo<'a> {
+    /// Create a new servo on the given channel
+    ///
+    /// No pulses are sent until the servo is positioned, so the servo does
+    /// not move until then.
+    pub fn new<'d: 'a>(
+        timer: &'a ServoTimer<'d>,
+        mut channel: Channel<'a, LowSpeed>,
+        model: ServoModel,
+    ) -> Result<Self, Error> {
+        channel.configure(channel::config::Config {
+            timer: &timer.timer,
+            duty_pct: 0,
+            pin_config: channel::config::PinConfig::PushPull,
+        })?;
+
+        Ok(Self {
+            channel,
+            model,
+            period_us: timer.period_us,
+            pulse_us: None,
+        })
+    }
+
+    /// Move the servo to an angle in degrees
+    ///
+    /// The angle is clamped to the range of the model.
+    pub fn set_angle(&mut self, degrees: f32) -> Result<(), Error> {
+        self.set_pulse_us(self.model.pulse_us(degrees))
+    }
+
+    /// Move the servo to the middle of its range
+    pub fn center(&mut self) -> Result<(), Error> {
+        self.set_angle(self.model.range_deg as f32 / 2.0)
+    }
+
+    /// Send pulses of the given width in µs
+    ///
+    /// Unlike [Servo::set_angle], the width is not limited to the range of the
+    /// model, which helps finding the range of a servo for
+    /// [ServoModel::with_pulse_range].
+    pub fn set_pulse_us(&mut self, pulse_us: u32) -> Result<(), Error> {
+        if pulse_us >= self.period_us {
+            return Err(Error::PulseOutOfRange);
+        }
+
+        let max_duty = 1u64 << DUTY as u32;
+        let duty = pulse_us as u64 * max_duty / self.period_us as u64;
+        self.channel.set_duty_hw(duty as u32);
+        self.pulse_us = Some(pulse_us);
+
+        Ok(())
+    }
+
+    /// Stop sending pulses
+    ///
+    /// Most servos stop holding their position and can be turned by hand, and
+    /// draw less current.
+    pub fn detach(&mut self) -> Result<(), Error> {
+        self.channel.set_duty(0)?;
+        self.pulse_us = None;
+
+        Ok(())
+    }
+
+    /// The angle the servo was last moved to, or `None` if it is detached
+    pub fn angle(&self) -> Option<f32> {
+        self.pulse_us.map(|pulse_us| self.model.angle(pulse_us))
+    }
+
+    /// The width of the pulses sent, or `None` if the servo is detached
+    pub fn pulse_us(&self) -> Option<u32> {
+        self.pulse_us
+    }
+
+    /// The model of the servo
+    pub fn model(&self) -> ServoModel {
+        self.model
+    }
+
+    /// Change the model of the servo, for example after calibrating it
+    ///
+    /// The servo is not moved until its next position is set.
+    pub fn set_model(&mut self, model: ServoModel) {
+        self.model = model;
+    }
+}
@@ -65,7 +65,7 @@ fn main() -> ! {
     // We use one of the RMT channels to instantiate a `SmartLedsAdapter` which can
     // be used directly with all `smart_led` implementations
     let rmt_buffer = smartLedBuffer!(1);
-    let mut led = SmartLedsAdapter::new(rmt.channel0, led_pin, rmt_buffer);
+    let mut led = SmartLedsAdapter::new(rmt.channel0, led_pin, freq, rmt_buffer);
 
     let delay = Delay::new();
 
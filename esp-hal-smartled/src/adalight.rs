@@ -0,0 +1,382 @@
+//! Parser of the Adalight serial protocol.
+//!
+//! Adalight is the protocol of most ambient lighting software on PCs, such as
+//! Hyperion, Prismatik and HyperHDR, which send the colors of the screen edges
+//! over a serial port. Every frame starts with a header of six bytes: the
+//! magic word `Ada`, the number of LEDs minus one as a big-endian `u16`, and a
+//! checksum of the two count bytes XORed with `0x55`. Three bytes of red,
+//! green and blue follow for every LED.
+//!
+//! An [`AdalightParser`] takes the bytes as they arrive, in chunks of any
+//! size, and writes the colors straight into a frame buffer in the order of
+//! the chip, so complete frames can be sent with
+//! [`SmartLedsAdapter::write_raw`](crate::SmartLedsAdapter::write_raw)
+//! without being copied or converted. Headers with a wrong checksum are
+//! skipped by searching for the next magic word, and frames with more LEDs
+//! than fit into the buffer are dropped.
+//!
+//! With the `embedded-io` feature, an [`AdalightSink`] does all of this for
+//! any `embedded_io::Read` or `embedded_io_async::Read` source, such as a
+//! UART or a USB serial port.
+//!
+//! The protocol only carries red, green and blue, so the frames suit chips
+//! with three channels.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut frame = [0u8; 3 * 120];
+//! let mut parser = AdalightParser::new(&mut frame).with_order(ColorOrder::Grb);
+//!
+//! loop {
+//!     let len = uart.read(&mut chunk)?;
+//!     let mut bytes = &chunk[..len];
+//!
+//!     while !bytes.is_empty() {
+//!         let consumed = parser.feed(bytes);
+//!         bytes = &bytes[consumed..];
+//!
+//!         if let Some(frame) = parser.frame() {
+//!             led.write_raw(frame).unwrap();
+//!         }
+//!     }
+//! }
+//!
+//! // The same with a sink
+//! let mut sink = AdalightSink::new(parser);
+//! sink.run_async(&mut uart, &mut led).await.unwrap();
+//! ```
+
+#[cfg(feature = "embedded-io")]
+use embedded_io::ReadExactError;
+#[cfg(feature = "embedded-io")]
+use esp_hal::rmt::{TxChannel, TxChannelAsync};
+
+use crate::chip::ColorOrder;
+#[cfg(feature = "embedded-io")]
+use crate::{chip::LedChip, LedAdapterError, SmartLedsAdapter, SmartLedsAdapterAsync};
+
+/// The magic word starting every header
+const MAGIC: &[u8; 3] = b"Ada";
+
+/// Progress of the parser within a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum State {
+    /// Matched the given number of bytes of the magic word
+    Magic(usize),
+    /// Waiting for the high byte of the count
+    CountHigh,
+    /// Waiting for the low byte of the count
+    CountLow(u8),
+    /// Waiting for the checksum of the count
+    Checksum(u8, u8),
+    /// Receiving the colors, at the given byte of the given length
+    Data(usize, usize),
+    /// Skipping the given number of bytes of a frame which does not fit
+    Skip(usize),
+    /// A frame of the given length is complete
+    Complete(usize),
+}
+
+/// Incremental parser of Adalight frames
+#[derive(Debug)]
+pub struct AdalightParser<'a> {
+    buffer: &'a mut [u8],
+    slots: [u8; 3],
+    state: State,
+    frames: u32,
+    dropped: u32,
+}
+
+impl<'a> AdalightParser<'a> {
+    /// Create a new parser writing frames into `buffer`, in RGB order.
+    ///
+    /// Frames of up to `buffer.len() / 3` LEDs are received.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            slots: [0, 1, 2],
+            state: State::Magic(0),
+            frames: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Write the channels of every LED in the given order, which has to
+    /// match the chip.
+    pub fn with_order(mut self, order: ColorOrder) -> Self {
+        // Position of the red, green and blue channel within a LED
+        let sent = order.arrange(0, 1, 2, 3);
+        for (position, channel) in sent.iter().take(3).enumerate() {
+            self.slots[*channel as usize] = position as u8;
+        }
+        self
+    }
+
+    /// Number of LEDs which fit into the buffer
+    pub fn capacity_leds(&self) -> usize {
+        self.buffer.len() / 3
+    }
+
+    /// Number of complete frames received
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Number of frames dropped, because their header was corrupted or they
+    /// did not fit into the buffer
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// The last frame, if it has been completed by the last call to
+    /// [`AdalightParser::push`] or [`AdalightParser::feed`].
+    ///
+    /// The frame stays available until the next byte is passed to the
+    /// parser, which starts receiving into the same buffer.
+    pub fn frame(&self) -> Option<&[u8]> {
+        match self.state {
+            State::Complete(len) => Some(&self.buffer[..len]),
+            _ => None,
+        }
+    }
+
+    /// Parse a single byte, and return whether it completed a frame.
+    pub fn push(&mut self, byte: u8) -> bool {
+        self.state = match self.state {
+            State::Magic(matched) => self.magic(matched, byte),
+            State::Complete(_) => self.magic(0, byte),
+            State::CountHigh => State::CountLow(byte),
+            State::CountLow(high) => State::Checksum(high, byte),
+            State::Checksum(high, low) => self.header(high, low, byte),
+            State::Data(position, len) => {
+                let led = position - position % 3;
+                let slot = self.slots[position % 3] as usize;
+                self.buffer[led + slot] = byte;
+
+                match position + 1 {
+                    end if end == len => {
+                        self.frames = self.frames.wrapping_add(1);
+                        State::Complete(len)
+                    }
+                    next => State::Data(next, len),
+                }
+            }
+            State::Skip(1) => State::Magic(0),
+            State::Skip(remaining) => State::Skip(remaining - 1),
+        };
+
+        matches!(self.state, State::Complete(_))
+    }
+
+    /// Parse bytes until a frame is complete or the bytes run out, and
+    /// return the number of bytes consumed.
+    ///
+    /// Check [`AdalightParser::frame`] after every call, and pass the bytes
+    /// which were not consumed in the next call.
+    pub fn feed(&mut self, bytes: &[u8]) -> usize {
+        for (index, byte) in bytes.iter().enumerate() {
+            if self.push(*byte) {
+                return index + 1;
+            }
+        }
+
+        bytes.len()
+    }
+
+    /// Start over with the next magic word, dropping a partly received frame.
+    pub fn reset(&mut self) {
+        if matches!(self.state, State::Data(..)) {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        self.state = State::Magic(0);
+    }
+
+    /// The state after `byte`, with `matched` bytes of the magic word matched
+    fn magic(&self, matched: usize, byte: u8) -> State {
+        match byte {
+            byte if byte == MAGIC[matched] && matched + 1 == MAGIC.len() => State::CountHigh,
+            byte if byte == MAGIC[matched] => State::Magic(matched + 1),
+            // The magic word does not overlap with itself, so a mismatch can
+            // only be the start of the next one
+            byte if byte == MAGIC[0] => State::Magic(1),
+            _ => State::Magic(0),
+        }
+    }
+
+    /// The state after the checksum of the count
+    fn header(&mut self, high: u8, low: u8, checksum: u8) -> State {
+        if high ^ low ^ 0x55 != checksum {
+            self.dropped = self.dropped.wrapping_add(1);
+            return State::Magic(0);
+        }
+
+        let len = (u16::from_be_bytes([high, low]) as usize + 1) * 3;
+        if len > self.buffer.len() {
+            self.dropped = self.dropped.wrapping_add(1);
+            return State::Skip(len);
+        }
+
+        State::Data(0, len)
+    }
+}
+
+/// Size of the chunks read from the source of an [`AdalightSink`]
+#[cfg(feature = "embedded-io")]
+const CHUNK_SIZE: usize = 64;
+
+/// Reads Adalight frames from a serial port and writes them to a strip
+///
+/// The sink reads the bytes in chunks, keeping the bytes after a complete
+/// frame for the next one. When reading fails, for example after an overrun
+/// of the UART, bytes of the frame may have been lost, so the frame being
+/// received is dropped and the sink waits for the next magic word.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+pub struct AdalightSink<'a> {
+    parser: AdalightParser<'a>,
+    chunk: [u8; CHUNK_SIZE],
+    start: usize,
+    end: usize,
+    read_errors: u32,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<'a> AdalightSink<'a> {
+    /// Create a new sink receiving frames with `parser`.
+    pub fn new(parser: AdalightParser<'a>) -> Self {
+        Self {
+            parser,
+            chunk: [0; CHUNK_SIZE],
+            start: 0,
+            end: 0,
+            read_errors: 0,
+        }
+    }
+
+    /// The parser, for its statistics
+    pub fn parser(&self) -> &AdalightParser<'a> {
+        &self.parser
+    }
+
+    /// Number of failed reads from the source
+    pub fn read_errors(&self) -> u32 {
+        self.read_errors
+    }
+
+    /// Read from `source` until a frame is complete, and return it.
+    ///
+    /// Returns [`ReadExactError::UnexpectedEof`] if the source has no more
+    /// bytes, and the error of the source if reading fails, after dropping
+    /// the frame being received.
+    pub fn read_frame<R>(&mut self, source: &mut R) -> Result<&[u8], ReadExactError<R::Error>>
+    where
+        R: embedded_io::Read,
+    {
+        while !self.parse() {
+            let result = source.read(&mut self.chunk);
+            self.refill(result)?;
+        }
+
+        Ok(self.frame())
+    }
+
+    /// Read from `source` until a frame is complete, and return it, see
+    /// [`AdalightSink::read_frame`].
+    pub async fn read_frame_async<R>(
+        &mut self,
+        source: &mut R,
+    ) -> Result<&[u8], ReadExactError<R::Error>>
+    where
+        R: embedded_io_async::Read,
+    {
+        while !self.parse() {
+            let result = source.read(&mut self.chunk).await;
+            self.refill(result)?;
+        }
+
+        Ok(self.frame())
+    }
+
+    /// Write every frame read from `source` to the LEDs, until the source
+    /// has no more bytes or writing to the LEDs fails.
+    ///
+    /// Failed reads are counted and skipped.
+    pub fn run<R, TX, const BUFFER_SIZE: usize, Chip>(
+        &mut self,
+        source: &mut R,
+        adapter: &mut SmartLedsAdapter<TX, BUFFER_SIZE, Chip>,
+    ) -> Result<(), LedAdapterError>
+    where
+        R: embedded_io::Read,
+        TX: TxChannel,
+        Chip: LedChip,
+    {
+        loop {
+            match self.read_frame(source) {
+                Ok(frame) => adapter.write_raw(frame)?,
+                Err(ReadExactError::UnexpectedEof) => return Ok(()),
+                Err(ReadExactError::Other(_)) => {}
+            }
+        }
+    }
+
+    /// Write every frame read from `source` to the LEDs, see
+    /// [`AdalightSink::run`].
+    pub async fn run_async<R, TX, const BUFFER_SIZE: usize, Chip>(
+        &mut self,
+        source: &mut R,
+        adapter: &mut SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>,
+    ) -> Result<(), LedAdapterError>
+    where
+        R: embedded_io_async::Read,
+        TX: TxChannelAsync,
+        Chip: LedChip,
+    {
+        loop {
+            match self.read_frame_async(source).await {
+                Ok(frame) => adapter.write_raw(frame).await?,
+                Err(ReadExactError::UnexpectedEof) => return Ok(()),
+                Err(ReadExactError::Other(_)) => {}
+            }
+        }
+    }
+
+    /// Parse the bytes left of the last read, and return whether they
+    /// completed a frame.
+    fn parse(&mut self) -> bool {
+        if self.start == self.end {
+            return false;
+        }
+
+        let consumed = self.parser.feed(&self.chunk[self.start..self.end]);
+        self.start += consumed;
+
+        self.parser.frame().is_some()
+    }
+
+    /// Take the result of reading into the chunk.
+    fn refill<E>(&mut self, result: Result<usize, E>) -> Result<(), ReadExactError<E>> {
+        self.start = 0;
+        self.end = 0;
+
+        match result {
+            Ok(0) => Err(ReadExactError::UnexpectedEof),
+            Ok(len) => {
+                self.end = len;
+                Ok(())
+            }
+            Err(error) => {
+                self.read_errors = self.read_errors.wrapping_add(1);
+                self.parser.reset();
+                Err(ReadExactError::Other(error))
+            }
+        }
+    }
+
+    /// The frame completed by the last call to `parse`
+    fn frame(&self) -> &[u8] {
+        self.parser.frame().unwrap_or_default()
+    }
+}
@@ -0,0 +1,371 @@
+//! Playback of pre-rendered animations.
+//!
+//! Animations can be prepared offline, for example exported from a design
+//! tool, and stored in flash with `include_bytes!` or in a data partition. An
+//! [`AnimationPlayer`] reads them from any [`AnimationSource`] one frame at a
+//! time, so only a single frame has to be kept in RAM, and writes them to a
+//! strip with the frame delay of the animation.
+//!
+//! ## Format
+//!
+//! An animation starts with a header of 12 bytes, with all numbers in little
+//! endian:
+//!
+//! | Offset | Size | Content                                            |
+//! |--------|------|----------------------------------------------------|
+//! | 0      | 4    | Magic number, `LEDA` in ASCII                      |
+//! | 4      | 1    | Version of the format, currently 1                 |
+//! | 5      | 1    | Flags, where bit 0 marks run-length encoded frames |
+//! | 6      | 2    | Number of LEDs per frame                           |
+//! | 8      | 2    | Number of frames                                   |
+//! | 10     | 2    | Delay between frames, in milliseconds              |
+//!
+//! The frames follow right after the header. Without run-length encoding,
+//! every frame holds the red, green and blue bytes of every LED. With it,
+//! every frame is a sequence of runs of 4 bytes: a count from 1 to 255,
+//! followed by the red, green and blue bytes the next `count` LEDs are set
+//! to. The runs of a frame add up to exactly the number of LEDs per frame.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! static ANIMATION: &[u8] = include_bytes!("fire.leda");
+//!
+//! let mut player = AnimationPlayer::<_, NUM_LEDS>::new(ANIMATION).unwrap();
+//! player.set_looping(true);
+//!
+//! while player.play_frame(&mut led).unwrap() {}
+//! ```
+
+use core::{
+    convert::Infallible,
+    fmt::{self, Debug, Display},
+};
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::pacing::FrameLimiter;
+
+/// Magic number at the start of every animation
+const MAGIC: [u8; 4] = *b"LEDA";
+
+/// Supported version of the format
+const VERSION: u8 = 1;
+
+/// Size of the header, in bytes
+const HEADER_SIZE: u32 = 12;
+
+/// Flag marking run-length encoded frames
+const FLAG_RLE: u8 = 1 << 0;
+
+/// Storage an animation is read from
+pub trait AnimationSource {
+    /// Error reading from the storage
+    type Error;
+
+    /// Read bytes starting at `offset` into `buffer`, returning how many bytes
+    /// were read.
+    ///
+    /// Fewer bytes than the length of `buffer` are only returned at the end of
+    /// the animation.
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+impl AnimationSource for &[u8] {
+    type Error = Infallible;
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let data = self.get(offset as usize..).unwrap_or_default();
+        let len = buffer.len().min(data.len());
+        buffer[..len].copy_from_slice(&data[..len]);
+
+        Ok(len)
+    }
+}
+
+/// Errors of playing an animation
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AnimationError<E, W = Infallible> {
+    /// Reading from the source failed
+    Source(E),
+    /// The data does not start with the header of an animation
+    InvalidHeader,
+    /// The animation uses a newer version of the format
+    UnsupportedVersion(u8),
+    /// The frames of the animation have more LEDs than the player
+    TooManyLeds {
+        /// Number of LEDs per frame
+        leds: u16,
+    },
+    /// The animation ends in the middle of a frame
+    Truncated,
+    /// A run of a run-length encoded frame is empty or goes past the end of
+    /// the frame
+    InvalidRun,
+    /// Writing a frame to the LEDs failed
+    Write(W),
+}
+
+impl<E, W> Display for AnimationError<E, W>
+where
+    E: Debug,
+    W: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::Source(e) => write!(f, "reading the animation failed: {e:?}"),
+            AnimationError::InvalidHeader => write!(f, "not an animation"),
+            AnimationError::UnsupportedVersion(version) => {
+                write!(f, "unsupported animation version {version}")
+            }
+            AnimationError::TooManyLeds { leds } => {
+                write!(f, "the frames of {leds} LEDs do not fit into the player")
+            }
+            AnimationError::Truncated => write!(f, "the animation ends in the middle of a frame"),
+            AnimationError::InvalidRun => write!(f, "invalid run in a run-length encoded frame"),
+            AnimationError::Write(e) => write!(f, "writing the frame failed: {e:?}"),
+        }
+    }
+}
+
+impl<E, W> core::error::Error for AnimationError<E, W>
+where
+    E: Debug,
+    W: Debug,
+{
+}
+
+impl<E> AnimationError<E> {
+    /// Convert an error of reading the animation into one of playing it.
+    fn playing<W>(self) -> AnimationError<E, W> {
+        match self {
+            AnimationError::Source(e) => AnimationError::Source(e),
+            AnimationError::InvalidHeader => AnimationError::InvalidHeader,
+            AnimationError::UnsupportedVersion(version) => {
+                AnimationError::UnsupportedVersion(version)
+            }
+            AnimationError::TooManyLeds { leds } => AnimationError::TooManyLeds { leds },
+            AnimationError::Truncated => AnimationError::Truncated,
+            AnimationError::InvalidRun => AnimationError::InvalidRun,
+            AnimationError::Write(e) => match e {},
+        }
+    }
+}
+
+/// Plays an animation on a strip of up to `LEDS` LEDs
+pub struct AnimationPlayer<S, const LEDS: usize> {
+    source: S,
+    leds: u16,
+    frames: u16,
+    delay_ms: u16,
+    rle: bool,
+    looping: bool,
+    // Index and offset of the next frame
+    position: u16,
+    offset: u32,
+    frame: [RGB8; LEDS],
+    limiter: FrameLimiter,
+}
+
+impl<S, const LEDS: usize> AnimationPlayer<S, LEDS>
+where
+    S: AnimationSource,
+{
+    /// Create a new player for the animation, reading its header.
+    ///
+    /// LEDs beyond the frames of the animation stay off.
+    pub fn new(mut source: S) -> Result<Self, AnimationError<S::Error>> {
+        let mut header = [0; HEADER_SIZE as usize];
+        let len = source
+            .read(0, &mut header)
+            .map_err(AnimationError::Source)?;
+        if len < header.len() || header[..4] != MAGIC {
+            return Err(AnimationError::InvalidHeader);
+        }
+        if header[4] > VERSION {
+            return Err(AnimationError::UnsupportedVersion(header[4]));
+        }
+
+        let leds = u16::from_le_bytes([header[6], header[7]]);
+        if leds as usize > LEDS {
+            return Err(AnimationError::TooManyLeds { leds });
+        }
+
+        let delay_ms = u16::from_le_bytes([header[10], header[11]]);
+        let mut limiter = FrameLimiter::default();
+        limiter.set_interval_us(delay_ms as u64 * 1000);
+
+        Ok(Self {
+            source,
+            leds,
+            frames: u16::from_le_bytes([header[8], header[9]]),
+            delay_ms,
+            rle: header[5] & FLAG_RLE != 0,
+            looping: false,
+            position: 0,
+            offset: HEADER_SIZE,
+            frame: [RGB8::default(); LEDS],
+            limiter,
+        })
+    }
+
+    /// Number of LEDs per frame
+    pub fn leds(&self) -> u16 {
+        self.leds
+    }
+
+    /// Number of frames
+    pub fn frames(&self) -> u16 {
+        self.frames
+    }
+
+    /// Delay between frames, in milliseconds
+    pub fn delay_ms(&self) -> u16 {
+        self.delay_ms
+    }
+
+    /// Index of the next frame
+    pub fn position(&self) -> u16 {
+        self.position
+    }
+
+    /// Set whether the animation starts over after its last frame.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Start over at the first frame.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+        self.offset = HEADER_SIZE;
+    }
+
+    /// The frame decoded last
+    pub fn frame(&self) -> &[RGB8; LEDS] {
+        &self.frame
+    }
+
+    /// Give back the source of the animation.
+    pub fn release(self) -> S {
+        self.source
+    }
+
+    /// Decode the next frame, without waiting for the frame delay.
+    ///
+    /// Returns `None` after the last frame, unless the player is looping.
+    pub fn next_frame(&mut self) -> Result<Option<&[RGB8; LEDS]>, AnimationError<S::Error>> {
+        if self.position >= self.frames {
+            if !self.looping || self.frames == 0 {
+                return Ok(None);
+            }
+            self.rewind();
+        }
+
+        match self.rle {
+            true => self.decode_rle()?,
+            false => self.decode_raw()?,
+        }
+        self.position += 1;
+
+        Ok(Some(&self.frame))
+    }
+
+    /// Wait for the frame delay, and write the next frame to the LEDs.
+    ///
+    /// Returns `false` after the last frame, unless the player is looping.
+    pub fn play_frame<A>(
+        &mut self,
+        adapter: &mut A,
+    ) -> Result<bool, AnimationError<S::Error, A::Error>>
+    where
+        A: SmartLedsWrite<Color = RGB8>,
+    {
+        if self
+            .next_frame()
+            .map_err(AnimationError::playing)?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        self.limiter.wait();
+        adapter
+            .write(self.frame.iter().copied())
+            .map_err(AnimationError::Write)?;
+
+        Ok(true)
+    }
+
+    /// Wait for the frame delay without blocking the executor, and write the
+    /// next frame to the LEDs.
+    ///
+    /// Returns `false` after the last frame, unless the player is looping.
+    #[cfg(feature = "embassy-time")]
+    pub async fn play_frame_async<A>(
+        &mut self,
+        adapter: &mut A,
+    ) -> Result<bool, AnimationError<S::Error, A::Error>>
+    where
+        A: smart_leds_trait::SmartLedsWriteAsync<Color = RGB8>,
+    {
+        if self
+            .next_frame()
+            .map_err(AnimationError::playing)?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        self.limiter.wait_async().await;
+        adapter
+            .write(self.frame.iter().copied())
+            .await
+            .map_err(AnimationError::Write)?;
+
+        Ok(true)
+    }
+
+    /// Read exactly `buffer.len()` bytes at the current offset.
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), AnimationError<S::Error>> {
+        let len = self
+            .source
+            .read(self.offset, buffer)
+            .map_err(AnimationError::Source)?;
+        if len < buffer.len() {
+            return Err(AnimationError::Truncated);
+        }
+        self.offset += len as u32;
+
+        Ok(())
+    }
+
+    fn decode_raw(&mut self) -> Result<(), AnimationError<S::Error>> {
+        for index in 0..self.leds as usize {
+            let mut rgb = [0; 3];
+            self.read_exact(&mut rgb)?;
+            self.frame[index] = RGB8::new(rgb[0], rgb[1], rgb[2]);
+        }
+
+        Ok(())
+    }
+
+    fn decode_rle(&mut self) -> Result<(), AnimationError<S::Error>> {
+        let leds = self.leds as usize;
+        let mut index = 0;
+        while index < leds {
+            let mut run = [0; 4];
+            self.read_exact(&mut run)?;
+
+            let count = run[0] as usize;
+            if count == 0 || index + count > leds {
+                return Err(AnimationError::InvalidRun);
+            }
+
+            self.frame[index..index + count].fill(RGB8::new(run[1], run[2], run[3]));
+            index += count;
+        }
+
+        Ok(())
+    }
+}
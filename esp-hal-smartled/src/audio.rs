@@ -0,0 +1,236 @@
+//! Effects reacting to audio.
+//!
+//! The analysis of the audio, for example a FFT of the samples of an I2S
+//! microphone, is up to the application. It publishes the results to an
+//! [`AudioInput`]: the overall level, the energy of every frequency band, and
+//! beats as they are detected. The effects here read the latest results
+//! whenever they render a frame, so the analysis and the LEDs can run at
+//! different rates, in different tasks or in an interrupt handler.
+//!
+//! - [`VuMeter`] lights the strip up to the level, from green to red, with a
+//!   slowly falling peak.
+//! - [`BeatFlash`] flashes the whole strip on every beat, and fades it out.
+//!
+//! With the `embassy-sync` feature, both implement
+//! [`Effect`](crate::runner::Effect), so they can be shown by a
+//! [`LedRunner`](crate::runner::LedRunner).
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! static AUDIO: AudioInput<8> = AudioInput::new();
+//!
+//! // In the audio task
+//! AUDIO.publish(level, &bands);
+//! if beat_detected {
+//!     AUDIO.beat();
+//! }
+//!
+//! // In the LED task
+//! let mut meter = VuMeter::new(&AUDIO);
+//! meter.render(&mut frame);
+//! led.write(frame.iter().copied()).unwrap();
+//! ```
+
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+use smart_leds_trait::RGB8;
+
+use crate::color::scale;
+
+/// Results of the audio analysis, shared with the effects
+///
+/// `BANDS` is the number of frequency bands, lowest first. There must only be
+/// one publisher, but any number of effects can read the input.
+pub struct AudioInput<const BANDS: usize> {
+    level: AtomicU8,
+    bands: [AtomicU8; BANDS],
+    beats: AtomicU32,
+}
+
+impl<const BANDS: usize> AudioInput<BANDS> {
+    /// Create a new input, with silence in all bands.
+    pub const fn new() -> Self {
+        Self {
+            level: AtomicU8::new(0),
+            bands: [const { AtomicU8::new(0) }; BANDS],
+            beats: AtomicU32::new(0),
+        }
+    }
+
+    /// Publish the overall level and the energy of every band, scaled to 0 to
+    /// 255.
+    ///
+    /// Bands beyond the end of `bands` are set to 0.
+    pub fn publish(&self, level: u8, bands: &[u8]) {
+        self.level.store(level, Ordering::Relaxed);
+        for (index, band) in self.bands.iter().enumerate() {
+            band.store(bands.get(index).copied().unwrap_or(0), Ordering::Relaxed);
+        }
+    }
+
+    /// Signal that a beat has been detected.
+    pub fn beat(&self) {
+        // Only the publisher writes, so this needs no compare-and-swap, which
+        // some chips lack
+        let beats = self.beats.load(Ordering::Relaxed);
+        self.beats.store(beats.wrapping_add(1), Ordering::Relaxed);
+    }
+
+    /// The overall level
+    pub fn level(&self) -> u8 {
+        self.level.load(Ordering::Relaxed)
+    }
+
+    /// The energy of the band at `index`, or 0 beyond the last band
+    pub fn band(&self, index: usize) -> u8 {
+        self.bands
+            .get(index)
+            .map_or(0, |band| band.load(Ordering::Relaxed))
+    }
+
+    /// The energy of every band
+    pub fn bands(&self) -> [u8; BANDS] {
+        core::array::from_fn(|index| self.band(index))
+    }
+
+    /// Number of beats signalled so far, wrapping around
+    pub fn beats(&self) -> u32 {
+        self.beats.load(Ordering::Relaxed)
+    }
+}
+
+impl<const BANDS: usize> Default for AudioInput<BANDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lights the strip up to the audio level, with a falling peak
+pub struct VuMeter<'a, const BANDS: usize> {
+    input: &'a AudioInput<BANDS>,
+    low: RGB8,
+    high: RGB8,
+    peak: u8,
+    decay: u8,
+}
+
+impl<'a, const BANDS: usize> VuMeter<'a, BANDS> {
+    /// Create a new meter of the overall level of the input, going from green
+    /// to red.
+    pub const fn new(input: &'a AudioInput<BANDS>) -> Self {
+        Self {
+            input,
+            low: RGB8::new(0, 255, 0),
+            high: RGB8::new(255, 0, 0),
+            peak: 0,
+            decay: 2,
+        }
+    }
+
+    /// Set the colors at the start and at the end of the strip.
+    pub const fn with_colors(mut self, low: RGB8, high: RGB8) -> Self {
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    /// Set how far the peak falls every frame, out of 255.
+    pub const fn with_decay(mut self, decay: u8) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    /// Render the current level into the frame.
+    pub fn render(&mut self, frame: &mut [RGB8]) {
+        let level = self.input.level();
+        self.peak = self.peak.saturating_sub(self.decay).max(level);
+
+        let len = frame.len();
+        let lit = level as usize * len / 255;
+        let peak = (self.peak as usize * len / 255).min(len.saturating_sub(1));
+
+        for (index, led) in frame.iter_mut().enumerate() {
+            *led = if index < lit || (index == peak && self.peak > 0) {
+                let position = (index * 255 / len.max(2).saturating_sub(1)).min(255) as u8;
+                mix(self.low, self.high, position)
+            } else {
+                RGB8::default()
+            };
+        }
+    }
+}
+
+/// Flashes the whole strip on every beat, and fades it out
+pub struct BeatFlash<'a, const BANDS: usize> {
+    input: &'a AudioInput<BANDS>,
+    color: RGB8,
+    beats: u32,
+    brightness: u8,
+    decay: u8,
+}
+
+impl<'a, const BANDS: usize> BeatFlash<'a, BANDS> {
+    /// Create a new flash in the given color.
+    pub fn new(input: &'a AudioInput<BANDS>, color: RGB8) -> Self {
+        Self {
+            input,
+            color,
+            beats: input.beats(),
+            brightness: 0,
+            decay: 16,
+        }
+    }
+
+    /// Set how much the flash fades every frame, out of 255.
+    pub const fn with_decay(mut self, decay: u8) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    /// Change the color of the flash.
+    pub fn set_color(&mut self, color: RGB8) {
+        self.color = color;
+    }
+
+    /// Render the flash into the frame.
+    pub fn render(&mut self, frame: &mut [RGB8]) {
+        let beats = self.input.beats();
+        self.brightness = match beats != self.beats {
+            true => 255,
+            false => self.brightness.saturating_sub(self.decay),
+        };
+        self.beats = beats;
+
+        let color = RGB8::new(
+            scale(self.color.r, self.brightness),
+            scale(self.color.g, self.brightness),
+            scale(self.color.b, self.brightness),
+        );
+        frame.fill(color);
+    }
+}
+
+#[cfg(feature = "embassy-sync")]
+impl<const BANDS: usize> crate::runner::Effect for VuMeter<'_, BANDS> {
+    fn render(&mut self, frame: &mut [RGB8], _tick: u32) {
+        VuMeter::render(self, frame);
+    }
+}
+
+#[cfg(feature = "embassy-sync")]
+impl<const BANDS: usize> crate::runner::Effect for BeatFlash<'_, BANDS> {
+    fn render(&mut self, frame: &mut [RGB8], _tick: u32) {
+        BeatFlash::render(self, frame);
+    }
+}
+
+/// Mix two colors, from all `a` at 0 to all `b` at 255.
+const fn mix(a: RGB8, b: RGB8, position: u8) -> RGB8 {
+    let rest = 255 - position;
+    RGB8::new(
+        scale(a.r, rest) + scale(b.r, position),
+        scale(a.g, rest) + scale(b.g, position),
+        scale(a.b, rest) + scale(b.b, position),
+    )
+}
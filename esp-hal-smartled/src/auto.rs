@@ -0,0 +1,203 @@
+//! Picking the best backend of the target chip.
+//!
+//! Products built for several chips would otherwise need `cfg` attributes in
+//! application code to drive their LEDs with whatever peripheral the chip
+//! has. [`AutoAdapter`] is the adapter for the best backend of the chip
+//! selected with the chip feature flags:
+//!
+//! - On chips with an RMT peripheral, which are all chips supported by this
+//!   crate at the moment, it is a [`SmartLedsAdapter`](crate::SmartLedsAdapter)
+//!   on RMT channel 0.
+//! - On other chips, it is a [`SpiAdapter`](crate::spi::SpiAdapter) on SPI2,
+//!   which requires the `spi` feature.
+//!
+//! Both implement `SmartLedsWrite` for the colors of the chip, and offer
+//! [`set_color_correction`](crate::SmartLedsAdapter::set_color_correction) and
+//! [`set_gamma`](crate::SmartLedsAdapter::set_gamma). The
+//! [`auto_adapter!`](crate::auto_adapter) macro creates the adapter from the
+//! peripherals, taking the RMT or SPI peripheral it needs.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let peripherals = esp_hal::init(esp_hal::Config::default());
+//!
+//! let mut led = auto_adapter!(peripherals, peripherals.GPIO2, NUM_LEDS);
+//! led.write(data.iter().cloned()).unwrap();
+//!
+//! // For another type of LED chip
+//! let mut led = auto_adapter!(peripherals, peripherals.GPIO2, NUM_LEDS, Sk6812Rgbw);
+//! ```
+
+#[cfg(any(
+    feature = "esp32",
+    feature = "esp32c3",
+    feature = "esp32c6",
+    feature = "esp32h2",
+    feature = "esp32s2",
+    feature = "esp32s3",
+))]
+mod backend {
+    use esp_hal::{
+        gpio::OutputPin,
+        peripheral::Peripheral,
+        peripherals::RMT,
+        rmt::{Channel, Rmt},
+        Blocking,
+    };
+    use fugit::HertzU32;
+
+    use crate::{chip::LedChip, SmartLedsAdapter};
+
+    /// The adapter for the best backend of the target chip, driving LEDs with
+    /// channel 0 of the RMT peripheral
+    pub type AutoAdapter<'d, const BUFFER_SIZE: usize, Chip> =
+        SmartLedsAdapter<Channel<Blocking, 0>, BUFFER_SIZE, Chip>;
+
+    /// Number of buffer entries [`AutoAdapter`] needs to address `num_leds`
+    /// LEDs of `C`
+    pub const fn buffer_size<C>(num_leds: usize) -> usize
+    where
+        C: LedChip,
+    {
+        crate::buffer_size_for::<C>(num_leds)
+    }
+
+    /// Create an [`AutoAdapter`] driving the pin.
+    ///
+    /// This takes the whole RMT peripheral, which is clocked at 80 MHz.
+    pub fn new<'d, const BUFFER_SIZE: usize, Chip, O>(
+        rmt: impl Peripheral<P = RMT> + 'd,
+        pin: impl Peripheral<P = O> + 'd,
+    ) -> AutoAdapter<'d, BUFFER_SIZE, Chip>
+    where
+        Chip: LedChip + Default,
+        O: OutputPin + 'd,
+    {
+        let frequency = HertzU32::MHz(80);
+        let rmt = Rmt::new(rmt, frequency).unwrap();
+
+        SmartLedsAdapter::new_with_chip(
+            rmt.channel0,
+            pin,
+            frequency,
+            [0; BUFFER_SIZE],
+            Chip::default(),
+        )
+    }
+
+    /// Create the [`AutoAdapter`](crate::auto::AutoAdapter) of the target
+    /// chip, driving the given pin.
+    ///
+    /// Takes the peripherals returned by `esp_hal::init`, the pin, the number
+    /// of LEDs and optionally the type of LED chip, which defaults to
+    /// [`Sk68xx`](crate::chip::Sk68xx).
+    #[macro_export]
+    macro_rules! auto_adapter {
+        ( $peripherals: ident, $pin: expr, $num_leds: expr $(,)? ) => {
+            $crate::auto_adapter!($peripherals, $pin, $num_leds, $crate::chip::Sk68xx)
+        };
+        ( $peripherals: ident, $pin: expr, $num_leds: expr, $chip: ty $(,)? ) => {{
+            let adapter: $crate::auto::AutoAdapter<
+                '_,
+                { $crate::auto::buffer_size::<$chip>($num_leds) },
+                $chip,
+            > = $crate::auto::new($peripherals.RMT, $pin);
+            adapter
+        }};
+    }
+}
+
+#[cfg(all(
+    feature = "spi",
+    not(any(
+        feature = "esp32",
+        feature = "esp32c3",
+        feature = "esp32c6",
+        feature = "esp32h2",
+        feature = "esp32s2",
+        feature = "esp32s3",
+    ))
+))]
+mod backend {
+    use esp_hal::{
+        gpio::OutputPin,
+        peripheral::Peripheral,
+        peripherals::SPI2,
+        spi::master::{Config, Spi},
+        Blocking,
+    };
+    use fugit::HertzU32;
+
+    use crate::{
+        chip::LedChip,
+        spi::{SpiAdapter, FREQUENCY, RESET_BYTES},
+    };
+
+    /// The adapter for the best backend of the target chip, driving LEDs with
+    /// the MOSI pin of SPI2
+    pub type AutoAdapter<'d, const BUFFER_SIZE: usize, Chip> =
+        SpiAdapter<Spi<'d, Blocking>, BUFFER_SIZE, Chip>;
+
+    /// Number of buffer entries [`AutoAdapter`] needs to address `num_leds`
+    /// LEDs of `C`
+    pub const fn buffer_size<C>(num_leds: usize) -> usize
+    where
+        C: LedChip,
+    {
+        num_leds * C::CHANNELS * 4 + RESET_BYTES
+    }
+
+    /// Create an [`AutoAdapter`] driving the pin.
+    ///
+    /// This takes the SPI2 peripheral, which is clocked at
+    /// [`FREQUENCY`](crate::spi::FREQUENCY).
+    pub fn new<'d, const BUFFER_SIZE: usize, Chip, O>(
+        spi: impl Peripheral<P = SPI2> + 'd,
+        pin: impl Peripheral<P = O> + 'd,
+    ) -> AutoAdapter<'d, BUFFER_SIZE, Chip>
+    where
+        Chip: LedChip + Default,
+        O: OutputPin + 'd,
+    {
+        let config = Config {
+            frequency: HertzU32::kHz(FREQUENCY),
+            ..Config::default()
+        };
+        let spi = Spi::new_with_config(spi, config).with_mosi(pin);
+
+        SpiAdapter::new_with_chip(spi, [0; BUFFER_SIZE], Chip::default())
+    }
+
+    /// Create the [`AutoAdapter`](crate::auto::AutoAdapter) of the target
+    /// chip, driving the given pin.
+    ///
+    /// Takes the peripherals returned by `esp_hal::init`, the pin, the number
+    /// of LEDs and optionally the type of LED chip, which defaults to
+    /// [`Sk68xx`](crate::chip::Sk68xx).
+    #[macro_export]
+    macro_rules! auto_adapter {
+        ( $peripherals: ident, $pin: expr, $num_leds: expr $(,)? ) => {
+            $crate::auto_adapter!($peripherals, $pin, $num_leds, $crate::chip::Sk68xx)
+        };
+        ( $peripherals: ident, $pin: expr, $num_leds: expr, $chip: ty $(,)? ) => {{
+            let adapter: $crate::auto::AutoAdapter<
+                '_,
+                { $crate::auto::buffer_size::<$chip>($num_leds) },
+                $chip,
+            > = $crate::auto::new($peripherals.SPI2, $pin);
+            adapter
+        }};
+    }
+}
+
+#[cfg(any(
+    feature = "spi",
+    feature = "esp32",
+    feature = "esp32c3",
+    feature = "esp32c6",
+    feature = "esp32h2",
+    feature = "esp32s2",
+    feature = "esp32s3",
+))]
+pub use backend::*;
@@ -0,0 +1,411 @@
+//! Definitions of single-wire LED chips.
+//!
+//! Chips of the WS2812 family share the same protocol, but differ in their
+//! pulse timings, the number of color channels and the order in which the
+//! channels are sent. The [`LedChip`] trait captures these differences, so
+//! support for a new chip can be added by implementing it, without touching
+//! the adapters.
+//!
+//! The WS2813, WS2815 and WS2818 have a backup data line, which lets the
+//! signal bypass a broken LED. It needs no support from the adapters: the
+//! backup input of the first LED is tied to ground, or to the data line.
+//! [`LedChip::SUPPLY`] tells which chips are used on strips running from 12V.
+//!
+//! How many LEDs of a strip are lit by every LED of the protocol depends on
+//! the strip, not only on the chip: some 12V strips have a chip for every LED,
+//! others have one for every group of LEDs wired in series. Check the
+//! datasheet of the strip, and count the groups when sizing the buffers.
+//!
+//! The strips sold with a given controller do not always wire the channels
+//! in the order of its datasheet. If red and green are swapped, implement
+//! [`LedChip`] with the timing of the preset and the order of the strip, or
+//! override the order of the preset at runtime with a [`ColorOrder`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! /// A chip which expects its colors in RGB order
+//! struct MyChip;
+//!
+//! impl LedChip for MyChip {
+//!     type Color = RGB8;
+//!     const CHANNELS: usize = 3;
+//!     const TIMING: Timing = Timing::WS2812B;
+//!
+//!     fn bytes(color: RGB8) -> [u8; 4] {
+//!         [color.r, color.g, color.b, 0]
+//!     }
+//! }
+//! ```
+
+use smart_leds_trait::{RGB8, RGBW};
+
+use crate::{
+    color::{Adjustments, LedColor},
+    timing::Timing,
+    Pulses,
+};
+
+/// Protocol details of a single-wire LED chip
+pub trait LedChip {
+    /// Color of a single LED
+    type Color: LedColor;
+
+    /// Number of color channels, from 1 to 4
+    const CHANNELS: usize;
+
+    /// Number of bits sent per LED
+    const BITS_PER_LED: usize = Self::CHANNELS * 8;
+
+    /// Pulse timings of the chip
+    const TIMING: Timing;
+
+    /// Supply voltage of the strips the chip is used on
+    const SUPPLY: Supply = Supply::V5;
+
+    /// The channels of a color in the order they are sent.
+    ///
+    /// Only the first [`LedChip::CHANNELS`] bytes are sent.
+    fn bytes(color: Self::Color) -> [u8; 4];
+}
+
+/// Supply voltage of a strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Supply {
+    /// 5V
+    V5,
+    /// 12V, where depending on the strip a chip drives a single LED or a
+    /// group of LEDs
+    V12,
+}
+
+/// Chips compatible with both the SK6812 and WS2812, using [`Timing::SK68XX`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sk68xx;
+
+impl LedChip for Sk68xx {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::SK68XX;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// The original WS2812
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2812;
+
+impl LedChip for Ws2812 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2812;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// The WS2812B
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2812b;
+
+impl LedChip for Ws2812b {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2812B;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// The WS2811 in its 800 kHz mode, which sends colors in RGB order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2811;
+
+impl LedChip for Ws2811 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2811;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.r, color.g, color.b, 0]
+    }
+}
+
+/// The SK6812 with RGB LEDs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sk6812;
+
+impl LedChip for Sk6812 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::SK6812;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// The SK6812 with RGBW LEDs, which sends colors in GRBW order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sk6812Rgbw;
+
+impl LedChip for Sk6812Rgbw {
+    type Color = RGBW<u8>;
+    const CHANNELS: usize = 4;
+    const TIMING: Timing = Timing::SK6812;
+
+    fn bytes(color: RGBW<u8>) -> [u8; 4] {
+        [color.g, color.r, color.b, color.a.0]
+    }
+}
+
+/// The UCS1903 in its 800 kHz mode, which sends colors in RGB order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ucs1903;
+
+impl LedChip for Ucs1903 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::UCS1903;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.r, color.g, color.b, 0]
+    }
+}
+
+/// The SM16703, which sends colors in RGB order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sm16703;
+
+impl LedChip for Sm16703 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::SM16703;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.r, color.g, color.b, 0]
+    }
+}
+
+/// The GS8208, found on 12V strips, which sends colors in RGB order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gs8208;
+
+impl LedChip for Gs8208 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::GS8208;
+    const SUPPLY: Supply = Supply::V12;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.r, color.g, color.b, 0]
+    }
+}
+
+/// The WS2818, found on 12V strips, which sends colors in RGB order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2818;
+
+impl LedChip for Ws2818 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2818;
+    const SUPPLY: Supply = Supply::V12;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.r, color.g, color.b, 0]
+    }
+}
+
+/// The WS2813, with a backup data line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2813;
+
+impl LedChip for Ws2813 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2813;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// The WS2815, found on 12V strips with a backup data line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ws2815;
+
+impl LedChip for Ws2815 {
+    type Color = RGB8;
+    const CHANNELS: usize = 3;
+    const TIMING: Timing = Timing::WS2815;
+    const SUPPLY: Supply = Supply::V12;
+
+    fn bytes(color: RGB8) -> [u8; 4] {
+        [color.g, color.r, color.b, 0]
+    }
+}
+
+/// Order in which the red, green and blue channels are sent
+///
+/// The white channel of RGBW LEDs is always sent last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorOrder {
+    /// Red, green, blue
+    Rgb,
+    /// Red, blue, green
+    Rbg,
+    /// Green, red, blue
+    Grb,
+    /// Green, blue, red
+    Gbr,
+    /// Blue, red, green
+    Brg,
+    /// Blue, green, red
+    Bgr,
+}
+
+impl ColorOrder {
+    /// The channels of a color in this order, followed by the white channel.
+    pub const fn arrange(&self, r: u8, g: u8, b: u8, w: u8) -> [u8; 4] {
+        match self {
+            ColorOrder::Rgb => [r, g, b, w],
+            ColorOrder::Rbg => [r, b, g, w],
+            ColorOrder::Grb => [g, r, b, w],
+            ColorOrder::Gbr => [g, b, r, w],
+            ColorOrder::Brg => [b, r, g, w],
+            ColorOrder::Bgr => [b, g, r, w],
+        }
+    }
+}
+
+/// The chip presets, for choosing a chip at runtime
+///
+/// The adapters are generic over the chip, so the chip is fixed when they are
+/// created. A `ChipType` selects the timing profile and the color order of a
+/// preset for an adapter whose chip has the same number of channels, for
+/// example from a [`StripConfig`](crate::config::StripConfig).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChipType {
+    /// [`Sk68xx`]
+    #[default]
+    Sk68xx,
+    /// [`Ws2812`]
+    Ws2812,
+    /// [`Ws2812b`]
+    Ws2812b,
+    /// [`Ws2811`]
+    Ws2811,
+    /// [`Sk6812`]
+    Sk6812,
+    /// [`Sk6812Rgbw`]
+    Sk6812Rgbw,
+    /// [`Ucs1903`]
+    Ucs1903,
+    /// [`Sm16703`]
+    Sm16703,
+    /// [`Gs8208`]
+    Gs8208,
+    /// [`Ws2818`]
+    Ws2818,
+    /// [`Ws2813`]
+    Ws2813,
+    /// [`Ws2815`]
+    Ws2815,
+}
+
+impl ChipType {
+    /// Pulse timings of the chip
+    pub const fn timing(&self) -> Timing {
+        match self {
+            ChipType::Sk68xx => Sk68xx::TIMING,
+            ChipType::Ws2812 => Ws2812::TIMING,
+            ChipType::Ws2812b => Ws2812b::TIMING,
+            ChipType::Ws2811 => Ws2811::TIMING,
+            ChipType::Sk6812 => Sk6812::TIMING,
+            ChipType::Sk6812Rgbw => Sk6812Rgbw::TIMING,
+            ChipType::Ucs1903 => Ucs1903::TIMING,
+            ChipType::Sm16703 => Sm16703::TIMING,
+            ChipType::Gs8208 => Gs8208::TIMING,
+            ChipType::Ws2818 => Ws2818::TIMING,
+            ChipType::Ws2813 => Ws2813::TIMING,
+            ChipType::Ws2815 => Ws2815::TIMING,
+        }
+    }
+
+    /// Number of color channels
+    pub const fn channels(&self) -> usize {
+        match self {
+            ChipType::Sk6812Rgbw => Sk6812Rgbw::CHANNELS,
+            _ => 3,
+        }
+    }
+
+    /// Supply voltage of the strips the chip is used on
+    pub const fn supply(&self) -> Supply {
+        match self {
+            ChipType::Gs8208 | ChipType::Ws2815 | ChipType::Ws2818 => Supply::V12,
+            _ => Supply::V5,
+        }
+    }
+
+    /// Order in which the chip expects the channels
+    pub const fn color_order(&self) -> ColorOrder {
+        match self {
+            ChipType::Sk68xx
+            | ChipType::Ws2812
+            | ChipType::Ws2812b
+            | ChipType::Sk6812
+            | ChipType::Sk6812Rgbw
+            | ChipType::Ws2813
+            | ChipType::Ws2815 => ColorOrder::Grb,
+            ChipType::Ws2811
+            | ChipType::Ucs1903
+            | ChipType::Sm16703
+            | ChipType::Gs8208
+            | ChipType::Ws2818 => ColorOrder::Rgb,
+        }
+    }
+}
+
+/// Encode a color into the pulse codes of a LED.
+pub(crate) fn encode_led<C>(
+    color: C::Color,
+    adjustments: &Adjustments,
+    buffer: &mut [u32],
+    pulses: &Pulses,
+) where
+    C: LedChip,
+{
+    let color = adjustments.apply(color);
+    let bytes = match &adjustments.order {
+        Some(order) => color.arrange(order),
+        None => C::bytes(color),
+    };
+    #[cfg(feature = "lut")]
+    pulses.encode(&bytes[..C::CHANNELS], buffer);
+    #[cfg(not(feature = "lut"))]
+    crate::convert_bytes_to_pulses(&bytes[..C::CHANNELS], buffer, *pulses);
+}
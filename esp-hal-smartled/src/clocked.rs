@@ -0,0 +1,241 @@
+//! Adapters for clocked LEDs driven through an SPI bus.
+//!
+//! Unlike the WS2812 family, clocked LEDs use a separate data and clock line,
+//! which makes them insensitive to timing and allows driving them with any SPI
+//! peripheral. The adapters in this module work with every implementation of
+//! [`embedded_hal::spi::SpiBus`], including the SPI driver of `esp-hal`. Only
+//! the SCLK and MOSI pins are needed.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let config = Config {
+//!     frequency: 10.MHz(),
+//!     ..Config::default()
+//! };
+//! let spi = Spi::new_with_config(peripherals.SPI2, config)
+//!     .with_sck(peripherals.GPIO6)
+//!     .with_mosi(peripherals.GPIO7);
+//!
+//! let mut led = Hd108::new(spi);
+//! led.write_16bit([RGB16::new(0xffff, 0x0400, 0)]).unwrap();
+//! ```
+//!
+//! APA102 LEDs (and their SK9822 clones) have a 5-bit brightness per LED,
+//! which scales the current of all channels. Combined with the 8-bit color,
+//! this allows dimming far below the lowest step of 8-bit scaling:
+//!
+//! ```rust,ignore
+//! let mut led = Apa102::new(spi);
+//! led.write_pixels([Apa102Pixel::new(RGB8::new(255, 128, 0), 1)]).unwrap();
+//! ```
+
+use embedded_hal::spi::SpiBus;
+use rgb::RGB16;
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+/// Adapter for HD108 LEDs, which use 16 bits per color channel
+///
+/// Every LED additionally has a 5-bit current gain per channel, which is set
+/// to its maximum by default and can be changed with [`Hd108::set_gain`].
+pub struct Hd108<SPI> {
+    spi: SPI,
+    gain: [u8; 3],
+}
+
+impl<SPI> Hd108<SPI>
+where
+    SPI: SpiBus<u8>,
+{
+    /// Create a new adapter writing to the given SPI bus.
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            gain: [0x1f; 3],
+        }
+    }
+
+    /// Set the current gain of the red, green and blue channels.
+    ///
+    /// Only the lower 5 bits of each value are used.
+    pub fn set_gain(&mut self, r: u8, g: u8, b: u8) {
+        self.gain = [r & 0x1f, g & 0x1f, b & 0x1f];
+    }
+
+    /// Write colors with 16 bits per channel to the LEDs.
+    pub fn write_16bit<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB16>,
+    {
+        // Start frame of 128 zero bits
+        self.spi.write(&[0; 16])?;
+
+        let [gain_r, gain_g, gain_b] = self.gain;
+        let header = 0x8000 | ((gain_r as u16) << 10) | ((gain_g as u16) << 5) | gain_b as u16;
+
+        let mut leds = 0;
+        for item in iterator {
+            let color = item.into();
+            let [h0, h1] = header.to_be_bytes();
+            let [r0, r1] = color.r.to_be_bytes();
+            let [g0, g1] = color.g.to_be_bytes();
+            let [b0, b1] = color.b.to_be_bytes();
+
+            self.spi.write(&[h0, h1, r0, r1, g0, g1, b0, b1])?;
+            leds += 1;
+        }
+
+        // The data is delayed by half a clock cycle per LED, so enough extra
+        // clock cycles need to follow to shift it through the whole strip
+        for _ in 0..leds / 16 + 1 {
+            self.spi.write(&[0xff])?;
+        }
+
+        self.spi.flush()
+    }
+
+    /// Give back the SPI bus.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> SmartLedsWrite for Hd108<SPI>
+where
+    SPI: SpiBus<u8>,
+{
+    type Error = SPI::Error;
+    type Color = RGB8;
+
+    /// Write 8-bit colors to the LEDs, expanding them to the full 16-bit range.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_16bit(iterator.into_iter().map(|item| {
+            let color = item.into();
+            RGB16::new(
+                color.r as u16 * 0x101,
+                color.g as u16 * 0x101,
+                color.b as u16 * 0x101,
+            )
+        }))
+    }
+}
+
+/// A color with the 5-bit brightness of an APA102 LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Apa102Pixel {
+    /// The color of the LED
+    pub color: RGB8,
+    /// The brightness of the LED, of which only the lower 5 bits are used
+    pub brightness: u8,
+}
+
+impl Apa102Pixel {
+    /// Create a new pixel from a color and a brightness from 0 to 31.
+    pub const fn new(color: RGB8, brightness: u8) -> Self {
+        Self { color, brightness }
+    }
+}
+
+impl From<RGB8> for Apa102Pixel {
+    /// Use the color at full brightness.
+    fn from(color: RGB8) -> Self {
+        Self::new(color, 0x1f)
+    }
+}
+
+impl From<(RGB8, u8)> for Apa102Pixel {
+    fn from((color, brightness): (RGB8, u8)) -> Self {
+        Self::new(color, brightness)
+    }
+}
+
+/// Adapter for APA102 and SK9822 LEDs, which have a 5-bit brightness per LED
+///
+/// Writes through [`SmartLedsWrite`] use the brightness set with
+/// [`Apa102::set_brightness`] for every LED, while
+/// [`Apa102::write_pixels`] takes the brightness of each LED along with its
+/// color.
+pub struct Apa102<SPI> {
+    spi: SPI,
+    brightness: u8,
+}
+
+impl<SPI> Apa102<SPI>
+where
+    SPI: SpiBus<u8>,
+{
+    /// Create a new adapter writing to the given SPI bus.
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            brightness: 0x1f,
+        }
+    }
+
+    /// Set the brightness used by [`SmartLedsWrite::write`].
+    ///
+    /// Only the lower 5 bits are used.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness & 0x1f;
+    }
+
+    /// Write colors with a brightness per LED.
+    pub fn write_pixels<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        // Start frame of 32 zero bits
+        self.spi.write(&[0; 4])?;
+
+        let mut leds = 0;
+        for item in iterator {
+            let Apa102Pixel { color, brightness } = item.into();
+
+            self.spi
+                .write(&[0xe0 | (brightness & 0x1f), color.b, color.g, color.r])?;
+            leds += 1;
+        }
+
+        // Like with the HD108, the data is delayed by half a clock cycle per
+        // LED. The SK9822 additionally latches on a frame of 32 zero bits.
+        self.spi.write(&[0; 4])?;
+        for _ in 0..leds / 16 + 1 {
+            self.spi.write(&[0xff])?;
+        }
+
+        self.spi.flush()
+    }
+
+    /// Give back the SPI bus.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> SmartLedsWrite for Apa102<SPI>
+where
+    SPI: SpiBus<u8>,
+{
+    type Error = SPI::Error;
+    type Color = RGB8;
+
+    /// Write colors to the LEDs, using the configured brightness.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let brightness = self.brightness;
+        self.write_pixels(
+            iterator
+                .into_iter()
+                .map(|item| Apa102Pixel::new(item.into(), brightness)),
+        )
+    }
+}
@@ -0,0 +1,602 @@
+//! Color adjustments applied while encoding.
+//!
+//! The red, green and blue dies of most LEDs are not equally bright, so an
+//! uncorrected `(255, 255, 255)` usually shows up as a bluish or greenish
+//! white. A [`ColorCorrection`] scales each channel by a fixed factor to
+//! compensate. Presets are provided for common kinds of LEDs; the values match
+//! those used by [FastLED](https://github.com/FastLED/FastLED).
+//!
+//! LEDs also respond linearly to their input, while the eye perceives
+//! brightness roughly logarithmically. A [`Gamma`] curve maps colors so that
+//! fades look even. The right exponent depends on the LEDs, so it is
+//! configurable instead of fixed like the table in `smart-leds`.
+//! [`Gamma::cie1931`] follows the lightness formula of CIE 1931 instead, which
+//! gives more even dimming at low brightness. The channels of some LEDs
+//! saturate at different levels, which shifts the hue during fades with a
+//! single curve; a [`ChannelGamma`] has a curve for every channel instead.
+//! Colors taken from screens or images are encoded in sRGB, whose
+//! [`ColorSpace`] a pipeline decodes with [`Gamma::srgb`].
+//!
+//! RGBW LEDs can show the part of a color which is common to the red, green
+//! and blue channels with their white LED instead, which gives a cleaner white
+//! and draws less power. A [`WhiteExtraction`] moves it to the white channel
+//! while encoding, so code producing RGB colors can drive RGBW LEDs by
+//! converting them with [`rgbw`].
+//!
+//! Strips from different batches can be matched with a [`Calibration`], which
+//! scales every channel, including white, after all other adjustments.
+//!
+//! Frames received over a network or from C code often pack every LED into a
+//! `u32`. Colors implementing [`PackedColor`] are read from such words, so
+//! the adapters can encode packed frames without converting them first.
+//!
+//! For tunable white lighting, [`kelvin_to_rgb`] approximates the color of a
+//! color temperature, and [`kelvin_to_rgbw`] shows it with the white LEDs of
+//! RGBW LEDs.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! led.set_color_correction(ColorCorrection::TYPICAL_SMD5050);
+//! led.set_gamma(Some(Gamma::new(2.2)));
+//!
+//! rgbw_led.set_white_extraction(Some(WhiteExtraction::MinOfRgb));
+//! rgbw_led.write(frame.iter().copied().map(rgbw)).unwrap();
+//! ```
+
+use smart_leds_trait::{White, RGB8, RGBW};
+
+use crate::chip::ColorOrder;
+
+/// Per-channel scale factors, where 255 leaves a channel unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorCorrection {
+    /// Scale factor of the red channel
+    pub r: u8,
+    /// Scale factor of the green channel
+    pub g: u8,
+    /// Scale factor of the blue channel
+    pub b: u8,
+}
+
+impl ColorCorrection {
+    /// No correction
+    pub const UNCORRECTED: Self = Self::new(255, 255, 255);
+    /// Typical values for SMD5050 LEDs, such as the WS2812B
+    pub const TYPICAL_SMD5050: Self = Self::new(255, 176, 240);
+    /// Typical values for generic LED strips
+    pub const TYPICAL_LED_STRIP: Self = Self::new(255, 176, 240);
+    /// Typical values for 8mm "dome" LEDs, such as the WS2811 in through-hole
+    /// packages
+    pub const TYPICAL_8MM_PIXEL: Self = Self::new(255, 224, 140);
+    /// Typical values for strings of LEDs, such as WS2811 pixel strings
+    pub const TYPICAL_PIXEL_STRING: Self = Self::new(255, 224, 140);
+
+    /// Create a new correction from the given scale factors.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Apply the correction to a color.
+    pub const fn apply(&self, color: RGB8) -> RGB8 {
+        RGB8::new(
+            scale(color.r, self.r),
+            scale(color.g, self.g),
+            scale(color.b, self.b),
+        )
+    }
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        Self::UNCORRECTED
+    }
+}
+
+/// Per-channel scale factors of a strip, where 255 leaves a channel unchanged
+///
+/// LEDs from different batches differ slightly in the brightness of their
+/// channels. When several strips are joined into one installation, measuring
+/// each strip and scaling it down to match the dimmest one avoids visible
+/// steps in color at the joints. Unlike a [`ColorCorrection`], the
+/// calibration also scales the white channel of RGBW LEDs, and it is applied
+/// last, after the white extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Calibration {
+    /// Scale factor of the red channel
+    pub r: u8,
+    /// Scale factor of the green channel
+    pub g: u8,
+    /// Scale factor of the blue channel
+    pub b: u8,
+    /// Scale factor of the white channel
+    pub w: u8,
+}
+
+impl Calibration {
+    /// No calibration
+    pub const UNCALIBRATED: Self = Self::new(255, 255, 255, 255);
+
+    /// Create a new calibration from the given scale factors.
+    pub const fn new(r: u8, g: u8, b: u8, w: u8) -> Self {
+        Self { r, g, b, w }
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::UNCALIBRATED
+    }
+}
+
+/// Gamma curve, stored as a lookup table
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gamma {
+    lut: [u8; 256],
+}
+
+impl Gamma {
+    /// Create a curve with the given exponent.
+    ///
+    /// 2.2 suits most LEDs, while 2.8 matches the table of `smart-leds`.
+    pub fn new(exponent: f32) -> Self {
+        let mut lut = [0; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            *out = libm::roundf(libm::powf(value as f32 / 255.0, exponent) * 255.0) as u8;
+        }
+
+        Self { lut }
+    }
+
+    /// Create a curve following the lightness formula of CIE 1931.
+    ///
+    /// Steps of the input are perceived as equal steps of lightness, which
+    /// makes slow fades and dimming look smoother than a power curve,
+    /// especially close to black.
+    pub fn cie1931() -> Self {
+        let mut lut = [0; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            let lightness = value as f32 * 100.0 / 255.0;
+            let luminance = match lightness <= 8.0 {
+                true => lightness / 903.3,
+                false => libm::powf((lightness + 16.0) / 116.0, 3.0),
+            };
+            *out = libm::roundf(luminance * 255.0) as u8;
+        }
+
+        Self { lut }
+    }
+
+    /// Create a curve decoding the transfer function of sRGB.
+    ///
+    /// The curve turns colors encoded for a screen, such as those picked in a
+    /// color picker or taken from an image, into linear light, which is what
+    /// the LEDs produce from their input.
+    pub fn srgb() -> Self {
+        let mut lut = [0; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            let encoded = value as f32 / 255.0;
+            let linear = match encoded <= 0.04045 {
+                true => encoded / 12.92,
+                false => libm::powf((encoded + 0.055) / 1.055, 2.4),
+            };
+            *out = libm::roundf(linear * 255.0) as u8;
+        }
+
+        Self { lut }
+    }
+
+    /// Create a curve from a custom lookup table.
+    pub const fn from_lut(lut: [u8; 256]) -> Self {
+        Self { lut }
+    }
+
+    /// The lookup table of the curve
+    pub const fn lut(&self) -> &[u8; 256] {
+        &self.lut
+    }
+
+    /// Map a single channel value through the curve.
+    pub const fn apply(&self, value: u8) -> u8 {
+        self.lut[value as usize]
+    }
+}
+
+/// How the colors of a frame are encoded
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    /// Values proportional to the light of the LEDs, which is what they
+    /// expect
+    #[default]
+    Linear,
+    /// Values encoded with the transfer function of sRGB, like the colors of
+    /// screens, images and color pickers
+    Srgb,
+}
+
+/// Separate gamma curves for the red, green, blue and white channels
+///
+/// LEDs without a white channel ignore its curve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelGamma {
+    /// Curve of the red channel
+    pub r: Gamma,
+    /// Curve of the green channel
+    pub g: Gamma,
+    /// Curve of the blue channel
+    pub b: Gamma,
+    /// Curve of the white channel
+    pub w: Gamma,
+}
+
+impl ChannelGamma {
+    /// Create curves with the given exponents for every channel.
+    pub fn new(r: f32, g: f32, b: f32, w: f32) -> Self {
+        Self::from_curves(Gamma::new(r), Gamma::new(g), Gamma::new(b), Gamma::new(w))
+    }
+
+    /// Create per-channel curves from existing curves.
+    pub const fn from_curves(r: Gamma, g: Gamma, b: Gamma, w: Gamma) -> Self {
+        Self { r, g, b, w }
+    }
+}
+
+/// How the white channel of RGBW LEDs is computed from the other channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WhiteExtraction {
+    /// Move the part common to the red, green and blue channels to the white
+    /// channel, which assumes the white LED is as bright as all three at full
+    /// scale
+    MinOfRgb,
+    /// Move as much of the color as possible to a white LED, which appears as
+    /// the given color when mixed from the red, green and blue LEDs at full
+    /// scale
+    ///
+    /// For example, a warm white LED could be calibrated as `(255, 190, 110)`.
+    Calibrated {
+        /// Red part of the white LED
+        r: u8,
+        /// Green part of the white LED
+        g: u8,
+        /// Blue part of the white LED
+        b: u8,
+    },
+}
+
+impl WhiteExtraction {
+    /// Move the white part of the red, green and blue channels to the white
+    /// channel, adding to its current value.
+    pub const fn apply(&self, color: RGBW<u8>) -> RGBW<u8> {
+        let (wr, wg, wb) = match *self {
+            WhiteExtraction::MinOfRgb => (255, 255, 255),
+            WhiteExtraction::Calibrated { r, g, b } => (r, g, b),
+        };
+
+        // The white level is limited by the channel which runs out first, and
+        // by the room left in the white channel
+        let mut white = 255 - color.a.0 as u32;
+        white = min_level(white, color.r, wr);
+        white = min_level(white, color.g, wg);
+        white = min_level(white, color.b, wb);
+
+        RGBW::new_alpha(
+            color.r - (white * wr as u32 / 255) as u8,
+            color.g - (white * wg as u32 / 255) as u8,
+            color.b - (white * wb as u32 / 255) as u8,
+            White(color.a.0 + white as u8),
+        )
+    }
+}
+
+/// Lower `white` to the level at which a white LED with the channel value
+/// `part` uses up all of `value`.
+const fn min_level(white: u32, value: u8, part: u8) -> u32 {
+    if part == 0 {
+        return white;
+    }
+
+    let level = value as u32 * 255 / part as u32;
+    if level < white {
+        level
+    } else {
+        white
+    }
+}
+
+/// Convert an RGB color to RGBW with the white channel off, for writing to
+/// RGBW LEDs.
+///
+/// Together with a [`WhiteExtraction`], this lets code producing RGB colors
+/// make use of the white LEDs.
+pub const fn rgbw(color: RGB8) -> RGBW<u8> {
+    RGBW::new_alpha(color.r, color.g, color.b, White(0))
+}
+
+/// Approximate the color of a black body at the given temperature in Kelvin,
+/// at full brightness.
+///
+/// This uses the fit by Tanner Helland, which is accurate enough for lighting
+/// from 1000K to 40000K; temperatures outside of this range are clamped.
+pub fn kelvin_to_rgb(kelvin: u32) -> RGB8 {
+    let temp = kelvin.clamp(1000, 40000) as f32 / 100.0;
+    let channel = |value: f32| libm::roundf(value.clamp(0.0, 255.0)) as u8;
+
+    let (r, g) = match temp <= 66.0 {
+        true => (255.0, 99.470_8 * libm::logf(temp) - 161.119_57),
+        false => (
+            329.698_73 * libm::powf(temp - 60.0, -0.133_204_76),
+            288.122_17 * libm::powf(temp - 60.0, -0.075_514_85),
+        ),
+    };
+    let b = match temp {
+        t if t >= 66.0 => 255.0,
+        t if t <= 19.0 => 0.0,
+        t => 138.517_73 * libm::logf(t - 10.0) - 305.044_8,
+    };
+
+    RGB8::new(channel(r), channel(g), channel(b))
+}
+
+/// White at the given temperature in Kelvin for RGBW LEDs, with as much of it
+/// as possible shown by the white LED.
+///
+/// The extraction describes the color of the white LED, so LEDs with a warm
+/// or cool white are mixed with the right amount of red, green and blue.
+pub fn kelvin_to_rgbw(kelvin: u32, extraction: &WhiteExtraction) -> RGBW<u8> {
+    extraction.apply(rgbw(kelvin_to_rgb(kelvin)))
+}
+
+/// A color in the HSV color space, with the same layout as the `Hsv` type of
+/// `smart-leds`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsv {
+    /// Hue, where 0 and 255 are red, 85 is green and 170 is blue
+    pub hue: u8,
+    /// Saturation
+    pub sat: u8,
+    /// Value, or brightness
+    pub val: u8,
+}
+
+impl Hsv {
+    /// Create a new HSV color.
+    pub const fn new(hue: u8, sat: u8, val: u8) -> Self {
+        Self { hue, sat, val }
+    }
+}
+
+impl From<(u8, u8, u8)> for Hsv {
+    fn from((hue, sat, val): (u8, u8, u8)) -> Self {
+        Self::new(hue, sat, val)
+    }
+}
+
+impl From<Hsv> for RGB8 {
+    fn from(hsv: Hsv) -> Self {
+        hsv2rgb(hsv)
+    }
+}
+
+/// Convert an HSV color to RGB, in the same way as `smart_leds::hsv::hsv2rgb`.
+pub const fn hsv2rgb(hsv: Hsv) -> RGB8 {
+    let v = hsv.val as u32;
+    let s = hsv.sat as u32;
+    // Position within the current sixth of the hue circle, from 0 to 84
+    let f = hsv.hue as u32 * 2 % 85;
+    let p = (v * (255 - s) / 255) as u8;
+    let q = (v * (255 * 85 - s * f) / (255 * 85)) as u8;
+    let t = (v * (255 * 85 - s * (85 - f)) / (255 * 85)) as u8;
+    let v = v as u8;
+
+    match hsv.hue {
+        0..=42 => RGB8::new(v, t, p),
+        43..=84 => RGB8::new(q, v, p),
+        85..=127 => RGB8::new(p, v, t),
+        128..=169 => RGB8::new(p, q, v),
+        170..=212 => RGB8::new(t, p, v),
+        213..=254 => RGB8::new(v, p, q),
+        255 => RGB8::new(v, t, p),
+    }
+}
+
+/// Colors to which a [`ColorCorrection`], [`Gamma`] curve and [`Calibration`]
+/// can be applied
+pub trait LedColor: Copy {
+    /// Apply the correction to the color.
+    fn corrected(self, correction: &ColorCorrection) -> Self;
+
+    /// Map every channel of the color through the gamma curve.
+    fn with_gamma(self, gamma: &Gamma) -> Self;
+
+    /// Map every channel of the color through its own gamma curve.
+    ///
+    /// Colors which do not implement this are returned unchanged.
+    fn with_channel_gamma(self, _gamma: &ChannelGamma) -> Self {
+        self
+    }
+
+    /// Move the white part of the color to the white channel.
+    ///
+    /// Colors without a white channel are returned unchanged.
+    fn with_white(self, _extraction: &WhiteExtraction) -> Self {
+        self
+    }
+
+    /// Scale every channel of the color by the calibration.
+    ///
+    /// Colors which do not implement this are returned unchanged.
+    fn calibrated(self, _calibration: &Calibration) -> Self {
+        self
+    }
+
+    /// The channels of the color in the given order, followed by the white
+    /// channel.
+    fn arrange(self, order: &ColorOrder) -> [u8; 4];
+}
+
+impl LedColor for RGB8 {
+    fn corrected(self, correction: &ColorCorrection) -> Self {
+        correction.apply(self)
+    }
+
+    fn with_gamma(self, gamma: &Gamma) -> Self {
+        RGB8::new(
+            gamma.apply(self.r),
+            gamma.apply(self.g),
+            gamma.apply(self.b),
+        )
+    }
+
+    fn with_channel_gamma(self, gamma: &ChannelGamma) -> Self {
+        RGB8::new(
+            gamma.r.apply(self.r),
+            gamma.g.apply(self.g),
+            gamma.b.apply(self.b),
+        )
+    }
+
+    fn calibrated(self, calibration: &Calibration) -> Self {
+        RGB8::new(
+            scale(self.r, calibration.r),
+            scale(self.g, calibration.g),
+            scale(self.b, calibration.b),
+        )
+    }
+
+    fn arrange(self, order: &ColorOrder) -> [u8; 4] {
+        order.arrange(self.r, self.g, self.b, 0)
+    }
+}
+
+impl LedColor for RGBW<u8> {
+    /// Apply the correction to the red, green and blue channels, leaving the
+    /// white channel unchanged.
+    fn corrected(self, correction: &ColorCorrection) -> Self {
+        let RGB8 { r, g, b } = correction.apply(RGB8::new(self.r, self.g, self.b));
+        RGBW::new_alpha(r, g, b, self.a)
+    }
+
+    fn with_gamma(self, gamma: &Gamma) -> Self {
+        RGBW::new_alpha(
+            gamma.apply(self.r),
+            gamma.apply(self.g),
+            gamma.apply(self.b),
+            White(gamma.apply(self.a.0)),
+        )
+    }
+
+    fn with_channel_gamma(self, gamma: &ChannelGamma) -> Self {
+        RGBW::new_alpha(
+            gamma.r.apply(self.r),
+            gamma.g.apply(self.g),
+            gamma.b.apply(self.b),
+            White(gamma.w.apply(self.a.0)),
+        )
+    }
+
+    fn with_white(self, extraction: &WhiteExtraction) -> Self {
+        extraction.apply(self)
+    }
+
+    fn calibrated(self, calibration: &Calibration) -> Self {
+        RGBW::new_alpha(
+            scale(self.r, calibration.r),
+            scale(self.g, calibration.g),
+            scale(self.b, calibration.b),
+            White(scale(self.a.0, calibration.w)),
+        )
+    }
+
+    fn arrange(self, order: &ColorOrder) -> [u8; 4] {
+        order.arrange(self.r, self.g, self.b, self.a.0)
+    }
+}
+
+/// Colors which can be read from a `u32` packing every channel in a byte
+pub trait PackedColor {
+    /// Read a color from a packed word.
+    fn unpack(packed: u32) -> Self;
+}
+
+impl PackedColor for RGB8 {
+    /// Read a color packed as `0x00RRGGBB`.
+    fn unpack(packed: u32) -> Self {
+        let [_, r, g, b] = packed.to_be_bytes();
+        RGB8::new(r, g, b)
+    }
+}
+
+impl PackedColor for RGBW<u8> {
+    /// Read a color packed as `0xWWRRGGBB`.
+    fn unpack(packed: u32) -> Self {
+        let [w, r, g, b] = packed.to_be_bytes();
+        RGBW::new_alpha(r, g, b, White(w))
+    }
+}
+
+/// Adjustments an adapter applies to every color while encoding
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Adjustments {
+    pub(crate) correction: ColorCorrection,
+    pub(crate) gamma: Option<Gamma>,
+    pub(crate) channel_gamma: Option<ChannelGamma>,
+    pub(crate) white: Option<WhiteExtraction>,
+    pub(crate) calibration: Calibration,
+    pub(crate) order: Option<ColorOrder>,
+}
+
+impl Adjustments {
+    /// Set the gamma curve of all channels, replacing per-channel curves.
+    pub(crate) fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.gamma = gamma;
+        self.channel_gamma = None;
+    }
+
+    /// Set per-channel gamma curves, replacing the curve of all channels.
+    pub(crate) fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.channel_gamma = gamma;
+        self.gamma = None;
+    }
+
+    /// Apply the gamma curves, then the color correction, then the white
+    /// extraction, and finally the calibration.
+    pub(crate) fn apply<C>(&self, color: C) -> C
+    where
+        C: LedColor,
+    {
+        let color = match &self.gamma {
+            Some(gamma) => color.with_gamma(gamma),
+            None => color,
+        };
+        let color = match &self.channel_gamma {
+            Some(gamma) => color.with_channel_gamma(gamma),
+            None => color,
+        };
+        let color = color.corrected(&self.correction);
+
+        let color = match &self.white {
+            Some(extraction) => color.with_white(extraction),
+            None => color,
+        };
+
+        match self.calibration == Calibration::UNCALIBRATED {
+            true => color,
+            false => color.calibrated(&self.calibration),
+        }
+    }
+}
+
+/// Scale `value` by `factor / 255`, in the same way as
+/// `smart_leds::brightness`.
+pub(crate) const fn scale(value: u8, factor: u8) -> u8 {
+    ((value as u16 * (factor as u16 + 1)) >> 8) as u8
+}
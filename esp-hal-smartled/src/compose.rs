@@ -0,0 +1,92 @@
+//! Composition of frames.
+//!
+//! User interfaces often mix several layers on one strip, such as a
+//! notification shown on top of an ambient animation. [`compose`] combines a
+//! layer into a base frame with a [`BlendMode`] and an opacity, so every
+//! layer can be rendered into its own frame buffer and mixed just before
+//! writing.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut frame = ambient.next_frame();
+//!
+//! if let Some(notification) = &notification {
+//!     compose(&mut frame, notification.frame(), BlendMode::Overlay, notification.opacity());
+//! }
+//!
+//! led.write(frame.iter().copied()).unwrap();
+//! ```
+
+use smart_leds_trait::RGB8;
+
+/// How the colors of a layer are combined with the colors below it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BlendMode {
+    /// Mix the colors of the layer with the colors below
+    #[default]
+    Blend,
+    /// Like [`BlendMode::Blend`], but black LEDs of the layer are transparent
+    Overlay,
+    /// Add the channels of the layer to the channels below, saturating at 255
+    Add,
+    /// Take the larger value of every channel
+    Max,
+}
+
+impl BlendMode {
+    /// Combine the color of a layer with the color below it, with the layer
+    /// at the given opacity, where 255 is opaque.
+    pub const fn apply(self, below: RGB8, layer: RGB8, opacity: u8) -> RGB8 {
+        let combined = match self {
+            BlendMode::Blend => layer,
+            BlendMode::Overlay if layer.r == 0 && layer.g == 0 && layer.b == 0 => below,
+            BlendMode::Overlay => layer,
+            BlendMode::Add => RGB8::new(
+                below.r.saturating_add(layer.r),
+                below.g.saturating_add(layer.g),
+                below.b.saturating_add(layer.b),
+            ),
+            BlendMode::Max => RGB8::new(
+                max(below.r, layer.r),
+                max(below.g, layer.g),
+                max(below.b, layer.b),
+            ),
+        };
+
+        RGB8::new(
+            mix(below.r, combined.r, opacity),
+            mix(below.g, combined.g, opacity),
+            mix(below.b, combined.b, opacity),
+        )
+    }
+}
+
+/// Combine `layer` into `base` with the given mode and opacity, where 255 is
+/// opaque.
+///
+/// Only as many LEDs as both frames have are combined, so a shorter layer
+/// covers the start of the base frame.
+pub fn compose(base: &mut [RGB8], layer: &[RGB8], mode: BlendMode, opacity: u8) {
+    for (below, layer) in base.iter_mut().zip(layer) {
+        *below = mode.apply(*below, *layer, opacity);
+    }
+}
+
+/// Mix from `from` to `to` by `amount / 255`.
+const fn mix(from: u8, to: u8, amount: u8) -> u8 {
+    let from = from as u32;
+    let to = to as u32;
+    let amount = amount as u32;
+
+    ((from * (255 - amount) + to * amount + 127) / 255) as u8
+}
+
+const fn max(a: u8, b: u8) -> u8 {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
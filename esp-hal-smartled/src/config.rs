@@ -0,0 +1,102 @@
+//! Settings of a strip, chosen at runtime.
+//!
+//! Installations often use the same firmware for strips of different lengths
+//! and types, which are then configured on the device. A [`StripConfig`]
+//! holds the settings which differ between them: the number of LEDs, the
+//! chip, the order of the color channels, the brightness and the color
+//! correction. With the `serde` feature, it implements `Serialize` and
+//! `Deserialize`, so it can be stored in NVS or loaded from JSON at boot.
+//!
+//! The chip type of an adapter is fixed when it is created, so a config can
+//! only be applied to an adapter whose chip has the same number of channels.
+//! It sets the timing profile and the color order of the chip in the config,
+//! which lets an adapter for [`Sk68xx`](crate::chip::Sk68xx) drive any other
+//! RGB chip.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let config: StripConfig = serde_json_core::from_slice(&stored)?.0;
+//!
+//! let mut led = SmartLedsAdapter::new_with_strip_config(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//!     smart_led_buffer!(60),
+//!     Sk68xx,
+//!     &config,
+//! )?;
+//! let mut strip = LedStrip::<_, 60>::new(led).with_pipeline(config.pipeline());
+//! ```
+
+use crate::{
+    chip::{ChipType, ColorOrder},
+    color::ColorCorrection,
+    count::{CountPolicy, LedCount},
+    pipeline::LedPipeline,
+};
+
+/// Settings of a strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StripConfig {
+    /// Number of LEDs on the strip
+    pub leds: usize,
+    /// Chip of the LEDs
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub chip: ChipType,
+    /// Order of the color channels, if the strip does not use the order of
+    /// its chip
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub color_order: Option<ColorOrder>,
+    /// Brightness, where 255 is full brightness
+    #[cfg_attr(feature = "serde", serde(default = "full_brightness"))]
+    pub brightness: u8,
+    /// Color correction applied to every LED
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub correction: ColorCorrection,
+}
+
+impl StripConfig {
+    /// Create a new config for a strip of `leds` LEDs of the given chip, at
+    /// full brightness and without color correction.
+    pub const fn new(leds: usize, chip: ChipType) -> Self {
+        Self {
+            leds,
+            chip,
+            color_order: None,
+            brightness: 255,
+            correction: ColorCorrection::UNCORRECTED,
+        }
+    }
+
+    /// The order in which the channels are sent, which is the order of the
+    /// chip unless overridden
+    pub const fn color_order(&self) -> ColorOrder {
+        match self.color_order {
+            Some(order) => order,
+            None => self.chip.color_order(),
+        }
+    }
+
+    /// The number of LEDs, which frames of a different length are rejected
+    /// against
+    pub const fn led_count(&self) -> LedCount {
+        LedCount::new(self.leds, CountPolicy::Reject)
+    }
+
+    /// A pipeline scaling every color by the brightness.
+    ///
+    /// The adapters have no brightness of their own, so the brightness is
+    /// applied with a [`LedPipeline`], for example that of a
+    /// [`LedStrip`](crate::strip::LedStrip).
+    pub const fn pipeline(&self) -> LedPipeline {
+        LedPipeline::new().with_brightness(self.brightness)
+    }
+}
+
+#[cfg(feature = "serde")]
+const fn full_brightness() -> u8 {
+    255
+}
@@ -0,0 +1,136 @@
+//! The number of LEDs on a strip.
+//!
+//! A buffer which is too small for the strip fails every write, but frames
+//! which are shorter or longer than the strip are easy to miss: the LEDs past
+//! the end of a short frame keep showing whatever they received last, which
+//! looks like random colors. Giving an adapter the [`LedCount`] of the strip
+//! checks at construction that the buffer is sized for exactly that many
+//! LEDs, and enforces the count on every write, following its
+//! [`CountPolicy`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let led = SmartLedsAdapter::new_with_led_count(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//!     smart_led_buffer!(60),
+//!     Sk68xx,
+//!     LedCount::new(60, CountPolicy::Reject),
+//! )?;
+//!
+//! // Fails with `LedAdapterError::LedCountMismatch`
+//! led.write([RGB8::default(); 59])?;
+//! ```
+
+use crate::LedAdapterError;
+
+/// What to do with frames which do not have the declared number of LEDs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CountPolicy {
+    /// Fail the write with [`LedAdapterError::LedCountMismatch`]
+    #[default]
+    Reject,
+    /// Drop the LEDs beyond the end of the strip, and send shorter frames
+    /// unchanged
+    Clamp,
+}
+
+/// The declared number of LEDs on a strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LedCount {
+    leds: usize,
+    policy: CountPolicy,
+}
+
+impl LedCount {
+    /// Declare a strip of `leds` LEDs, enforced on every write with the given
+    /// policy.
+    pub const fn new(leds: usize, policy: CountPolicy) -> Self {
+        Self { leds, policy }
+    }
+
+    /// The number of LEDs
+    pub const fn leds(&self) -> usize {
+        self.leds
+    }
+
+    /// The policy for frames with a different number of LEDs
+    pub const fn policy(&self) -> CountPolicy {
+        self.policy
+    }
+
+    /// The number of LEDs of a frame of `leds` LEDs which are sent.
+    pub(crate) fn check(&self, leds: usize) -> Result<usize, LedAdapterError> {
+        match self.policy {
+            CountPolicy::Reject if leds != self.leds => Err(LedAdapterError::LedCountMismatch {
+                expected: self.leds,
+                actual: leds,
+            }),
+            _ => Ok(leds.min(self.leds)),
+        }
+    }
+}
+
+/// Iterator enforcing an optional [`LedCount`] on a frame
+///
+/// Once the iterator has been consumed, [`Counted::finish`] reports frames
+/// which were rejected.
+pub(crate) struct Counted<I> {
+    iterator: I,
+    count: Option<LedCount>,
+    seen: usize,
+    extra: usize,
+}
+
+impl<I> Counted<I>
+where
+    I: Iterator,
+{
+    pub(crate) fn new(iterator: I, count: Option<LedCount>) -> Self {
+        Self {
+            iterator,
+            count,
+            seen: 0,
+            extra: 0,
+        }
+    }
+
+    /// Check the number of LEDs of the frame against the count.
+    pub(crate) fn finish(&self) -> Result<(), LedAdapterError> {
+        match self.count {
+            Some(count) => count.check(self.seen + self.extra).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<I> Iterator for Counted<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(count) = self.count else {
+            return self.iterator.next();
+        };
+
+        if self.seen == count.leds {
+            // Count the LEDs beyond the end to report them, without encoding
+            // them
+            if count.policy == CountPolicy::Reject {
+                self.extra += self.iterator.by_ref().count();
+            }
+            return None;
+        }
+
+        let item = self.iterator.next()?;
+        self.seen += 1;
+
+        Some(item)
+    }
+}
@@ -0,0 +1,256 @@
+//! Temporal dithering for smooth dimming at low brightness.
+//!
+//! Scaling colors down to a low brightness throws away most of their
+//! resolution: at a brightness of 10 out of 255, every channel can only take
+//! one of eleven values, and slow fades become visibly steppy. Temporal
+//! dithering keeps track of the fractional part that was lost for each LED and
+//! carries it over into the next frame, so that a LED alternates between two
+//! adjacent values and averages out to the intended intermediate one.
+//!
+//! This only works if frames are sent often enough for the eye to blend them,
+//! so [`TemporalDither`] measures the time between frames and falls back to
+//! plain rounding whenever the frame rate drops below a configurable minimum.
+//! Where the time between frames is known already, for example for an
+//! animation running at a fixed rate, it can be passed to
+//! [`TemporalDither::apply_with_interval`] instead.
+//! Alternatively, it can fall back to spatial dithering, which spreads the
+//! fractional part over neighbouring LEDs instead of consecutive frames.
+//!
+//! Colors with 16 bits per channel, as used for the HD108 in
+//! [`crate::clocked`], can be dithered down to the 8 bits of the WS2812 family
+//! with [`TemporalDither::apply_16bit`]. This keeps slow fades at low
+//! brightness smooth without giving up the 8-bit LEDs.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut dither = TemporalDither::<NUM_LEDS>::new();
+//!
+//! loop {
+//!     led.write(dither.apply(data.iter().cloned(), 10)).unwrap();
+//! }
+//! ```
+//!
+//! With 16-bit colors:
+//!
+//! ```rust,ignore
+//! led.write(dither.apply_16bit([RGB16::new(0x0180, 0x0040, 0)], 255))
+//!     .unwrap();
+//! ```
+
+#[cfg(feature = "esp-hal")]
+use esp_hal::time::{now, Instant};
+#[cfg(feature = "esp-hal")]
+use rgb::RGB16;
+use smart_leds_trait::RGB8;
+
+/// Frame rate below which dithering is disabled unless configured otherwise
+const DEFAULT_MIN_FRAME_RATE: u32 = 100;
+
+/// Rounding offsets of spatial dithering, an ordered dither of four LEDs
+const THRESHOLDS: [u32; 4] = [0x20, 0xa0, 0x60, 0xe0];
+
+/// Brightness scaling with per-LED temporal error accumulation
+///
+/// `LEDS` is the number of LEDs for which state is kept. Any LEDs beyond that
+/// are scaled without dithering.
+pub struct TemporalDither<const LEDS: usize> {
+    error: [[u8; 3]; LEDS],
+    max_frame_interval_us: u64,
+    #[cfg(feature = "esp-hal")]
+    last_frame: Option<Instant>,
+    spatial: bool,
+}
+
+impl<const LEDS: usize> TemporalDither<LEDS> {
+    /// Create a new ditherer which is active at frame rates of 100 FPS and up.
+    pub const fn new() -> Self {
+        Self {
+            error: [[0; 3]; LEDS],
+            max_frame_interval_us: 1_000_000 / DEFAULT_MIN_FRAME_RATE as u64,
+            #[cfg(feature = "esp-hal")]
+            last_frame: None,
+            spatial: false,
+        }
+    }
+
+    /// Set the minimum frame rate at which dithering is applied.
+    ///
+    /// At lower frame rates the alternation between values becomes visible as
+    /// flicker, so colors are rounded instead.
+    pub fn with_min_frame_rate(mut self, fps: u32) -> Self {
+        self.max_frame_interval_us = 1_000_000 / fps.max(1) as u64;
+        self
+    }
+
+    /// Fall back to spatial dithering instead of rounding when the frame rate
+    /// is too low for temporal dithering.
+    ///
+    /// Every group of four neighbouring LEDs rounds at different thresholds,
+    /// so a gradient of intermediate values is spread over the strip. This is
+    /// best suited to strips which are viewed from a distance or through a
+    /// diffuser.
+    pub fn with_spatial_fallback(mut self, spatial: bool) -> Self {
+        self.spatial = spatial;
+        self
+    }
+
+    /// Forget the accumulated error of all LEDs.
+    pub fn reset(&mut self) {
+        self.error = [[0; 3]; LEDS];
+        #[cfg(feature = "esp-hal")]
+        {
+            self.last_frame = None;
+        }
+    }
+
+    /// Scale the colors of a frame by `brightness`, dithering the result.
+    ///
+    /// This is meant to be called once per frame, as a replacement for
+    /// `smart_leds::brightness`.
+    #[cfg(feature = "esp-hal")]
+    pub fn apply<'a, I>(
+        &'a mut self,
+        iterator: I,
+        brightness: u8,
+    ) -> impl Iterator<Item = RGB8> + 'a
+    where
+        I: IntoIterator<Item = RGB8>,
+        I::IntoIter: 'a,
+    {
+        let active = self.next_frame();
+        self.frames(scaled(iterator.into_iter(), brightness), active)
+    }
+
+    /// Scale the colors of a frame by `brightness` like
+    /// [`TemporalDither::apply`], taking the time since the previous frame in
+    /// microseconds instead of measuring it.
+    pub fn apply_with_interval<'a, I>(
+        &'a mut self,
+        iterator: I,
+        brightness: u8,
+        interval_us: u64,
+    ) -> impl Iterator<Item = RGB8> + 'a
+    where
+        I: IntoIterator<Item = RGB8>,
+        I::IntoIter: 'a,
+    {
+        let active = interval_us <= self.max_frame_interval_us;
+        self.frames(scaled(iterator.into_iter(), brightness), active)
+    }
+
+    /// Scale colors with 16 bits per channel by `brightness`, dithering the
+    /// result down to 8 bits per channel.
+    ///
+    /// The upper byte of every channel is the 8-bit value, and the lower byte
+    /// is spread over consecutive frames.
+    #[cfg(feature = "esp-hal")]
+    pub fn apply_16bit<'a, I>(
+        &'a mut self,
+        iterator: I,
+        brightness: u8,
+    ) -> impl Iterator<Item = RGB8> + 'a
+    where
+        I: IntoIterator<Item = RGB16>,
+        I::IntoIter: 'a,
+    {
+        let scale = brightness as u32 + 1;
+        let active = self.next_frame();
+
+        self.frames(
+            iterator.into_iter().map(move |color| {
+                [
+                    (color.r as u32 * scale) >> 8,
+                    (color.g as u32 * scale) >> 8,
+                    (color.b as u32 * scale) >> 8,
+                ]
+            }),
+            active,
+        )
+    }
+
+    /// Mark the start of a frame, and return whether it follows the previous
+    /// one closely enough for temporal dithering.
+    #[cfg(feature = "esp-hal")]
+    fn next_frame(&mut self) -> bool {
+        let now = now();
+        let active = self
+            .last_frame
+            .and_then(|last| now.checked_duration_since(last))
+            .is_some_and(|interval| interval.to_micros() <= self.max_frame_interval_us);
+        self.last_frame = Some(now);
+
+        active
+    }
+
+    /// Quantize a frame of 8.8 fixed-point colors to 8 bits per channel,
+    /// carrying the error over to the next frame if `active`.
+    fn frames<'a, I>(&'a mut self, iterator: I, active: bool) -> impl Iterator<Item = RGB8> + 'a
+    where
+        I: Iterator<Item = [u32; 3]> + 'a,
+    {
+        let spatial = self.spatial;
+        let error = &mut self.error;
+        iterator.enumerate().map(move |(index, color)| {
+            match error.get_mut(index).filter(|_| active) {
+                Some(error) => RGB8::new(
+                    quantize(color[0], &mut error[0]),
+                    quantize(color[1], &mut error[1]),
+                    quantize(color[2], &mut error[2]),
+                ),
+                None => {
+                    if let Some(error) = error.get_mut(index) {
+                        *error = [0; 3];
+                    }
+                    let offset = match spatial {
+                        true => THRESHOLDS[index % THRESHOLDS.len()],
+                        false => 0x80,
+                    };
+                    RGB8::new(
+                        round(color[0], offset),
+                        round(color[1], offset),
+                        round(color[2], offset),
+                    )
+                }
+            }
+        })
+    }
+}
+
+impl<const LEDS: usize> Default for TemporalDither<LEDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scale colors by `brightness` into 8.8 fixed-point values.
+fn scaled<I>(iterator: I, brightness: u8) -> impl Iterator<Item = [u32; 3]>
+where
+    I: Iterator<Item = RGB8>,
+{
+    let scale = brightness as u32 + 1;
+
+    iterator.map(move |color| {
+        [
+            color.r as u32 * scale,
+            color.g as u32 * scale,
+            color.b as u32 * scale,
+        ]
+    })
+}
+
+/// Take the integer part of an 8.8 fixed-point value and carry the fractional
+/// part over into `error`.
+fn quantize(value: u32, error: &mut u8) -> u8 {
+    let value = value.min(0xff00);
+    let (sum, carry) = error.overflowing_add(value as u8);
+    *error = sum;
+
+    (value >> 8) as u8 + carry as u8
+}
+
+/// Round an 8.8 fixed-point value up if its fractional part reaches
+/// `0x100 - offset`, where an offset of `0x80` rounds to the nearest integer.
+fn round(value: u32, offset: u32) -> u8 {
+    ((value + offset) >> 8).min(0xff) as u8
+}
@@ -0,0 +1,194 @@
+//! Rendering and sending frames on different cores.
+//!
+//! On large matrices, rendering a frame can take as long as sending it. The
+//! ESP32 and ESP32-S3 have a second core, so one core can render the next
+//! frame while the other sends the current one. A [`FrameHandoff`] passes
+//! frames between the two with a double buffer: the [`FrameRenderer`] renders
+//! into the back buffer while the [`FrameSender`] sends from the front one,
+//! and the buffers are swapped once a new frame is ready and the previous one
+//! has been sent. Frames are never copied.
+//!
+//! The frames can be colors, which the sending core encodes while sending
+//! them, or pulse codes encoded by the rendering core with a
+//! [`PulseEncoder`](crate::encoder::PulseEncoder), to move the encoding off
+//! the sending core as well.
+//!
+//! On the ESP32 and ESP32-S3, `start_renderer` runs the renderer on the
+//! second core. The handoff itself works between any two contexts, for
+//! example a task and an interrupt handler.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! static mut APP_CORE_STACK: Stack<8192> = Stack::new();
+//! let handoff = mk_static!(FrameHandoff<[RGB8; 1024]>, FrameHandoff::new([RGB8::default(); 1024]));
+//! let (renderer, mut sender) = handoff.split();
+//!
+//! let mut cpu_control = CpuControl::new(peripherals.CPU_CTRL);
+//! let _guard = start_renderer(
+//!     &mut cpu_control,
+//!     unsafe { &mut *addr_of_mut!(APP_CORE_STACK) },
+//!     renderer,
+//!     |frame| plasma.render(frame),
+//! )
+//! .unwrap();
+//!
+//! loop {
+//!     sender.write(&mut led).unwrap();
+//! }
+//! ```
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+/// Double buffer passing frames from a renderer to a sender
+pub struct FrameHandoff<T> {
+    frames: [UnsafeCell<T>; 2],
+    // Index of the buffer the sender reads from
+    front: AtomicUsize,
+    // Whether the back buffer holds a frame which has not been sent
+    ready: AtomicBool,
+}
+
+// SAFETY: the renderer only accesses the back buffer while no frame is ready,
+// and the sender only swaps the buffers while one is, so the two never access
+// the same buffer at the same time
+unsafe impl<T: Send> Sync for FrameHandoff<T> {}
+
+impl<T> FrameHandoff<T> {
+    /// Create a new handoff with both buffers set to `frame`.
+    pub const fn new(frame: T) -> Self
+    where
+        T: Copy,
+    {
+        Self {
+            frames: [UnsafeCell::new(frame), UnsafeCell::new(frame)],
+            front: AtomicUsize::new(0),
+            ready: AtomicBool::new(false),
+        }
+    }
+
+    /// Split the handoff into its renderer and its sender.
+    pub fn split(&mut self) -> (FrameRenderer<'_, T>, FrameSender<'_, T>) {
+        let handoff = &*self;
+        (FrameRenderer { handoff }, FrameSender { handoff })
+    }
+}
+
+/// Renders frames into the back buffer of a [`FrameHandoff`]
+pub struct FrameRenderer<'a, T> {
+    handoff: &'a FrameHandoff<T>,
+}
+
+impl<T> FrameRenderer<'_, T> {
+    /// Render the next frame if the previous one has been taken by the
+    /// sender, and return whether a frame was rendered.
+    pub fn try_render<F>(&mut self, render: F) -> bool
+    where
+        F: FnOnce(&mut T),
+    {
+        let handoff = self.handoff;
+        if handoff.ready.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let back = 1 - handoff.front.load(Ordering::Acquire);
+        // SAFETY: the sender does not touch the back buffer while no frame is
+        // ready
+        render(unsafe { &mut *handoff.frames[back].get() });
+        handoff.ready.store(true, Ordering::Release);
+
+        true
+    }
+
+    /// Wait until the previous frame has been taken by the sender, and render
+    /// the next one.
+    pub fn render<F>(&mut self, render: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        while self.handoff.ready.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+        self.try_render(render);
+    }
+}
+
+/// Takes rendered frames from the front buffer of a [`FrameHandoff`]
+pub struct FrameSender<'a, T> {
+    handoff: &'a FrameHandoff<T>,
+}
+
+impl<T> FrameSender<'_, T> {
+    /// Swap the buffers if a new frame is ready, and pass it to `send`.
+    ///
+    /// The renderer starts on the next frame as soon as the buffers have been
+    /// swapped, while `send` is still running.
+    pub fn try_take<F, R>(&mut self, send: F) -> Option<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let handoff = self.handoff;
+        if !handoff.ready.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let front = 1 - handoff.front.load(Ordering::Acquire);
+        handoff.front.store(front, Ordering::Release);
+        handoff.ready.store(false, Ordering::Release);
+
+        // SAFETY: the renderer only writes the back buffer, which is the other
+        // one now
+        Some(send(unsafe { &*handoff.frames[front].get() }))
+    }
+
+    /// Wait for the next frame, and pass it to `send`.
+    pub fn take<F, R>(&mut self, send: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        while !self.handoff.ready.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+        match self.try_take(send) {
+            Some(result) => result,
+            // Only the sender clears `ready`
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<T> FrameSender<'_, T>
+where
+    T: AsRef<[RGB8]>,
+{
+    /// Wait for the next frame, and write it to the adapter.
+    pub fn write<A>(&mut self, adapter: &mut A) -> Result<(), A::Error>
+    where
+        A: SmartLedsWrite<Color = RGB8>,
+    {
+        self.take(|frame| adapter.write(frame.as_ref().iter().copied()))
+    }
+}
+
+/// Run the renderer on the second core, rendering frames with `render` as
+/// fast as the sender takes them.
+#[cfg(any(feature = "esp32", feature = "esp32s3"))]
+pub fn start_renderer<'a, const SIZE: usize, T, F>(
+    cpu_control: &mut esp_hal::cpu_control::CpuControl<'_>,
+    stack: &'static mut esp_hal::cpu_control::Stack<SIZE>,
+    mut renderer: FrameRenderer<'a, T>,
+    mut render: F,
+) -> Result<esp_hal::cpu_control::AppCoreGuard<'a>, esp_hal::cpu_control::Error>
+where
+    T: Send + 'a,
+    F: FnMut(&mut T) + Send + 'a,
+{
+    cpu_control.start_app_core(stack, move || loop {
+        renderer.render(&mut render);
+    })
+}
@@ -0,0 +1,294 @@
+//! Encoding of colors into pulse codes.
+//!
+//! A [`PulseEncoder`] turns the colors of a frame into the pulse codes which
+//! make up the signal of a strip, for a timing profile and the tick rate of
+//! the transmitter. It applies the gamma curve, color correction, white
+//! extraction and calibration on the way, and ends every frame with the
+//! delimiter which holds the line for the reset time.
+//!
+//! The adapters combine an encoder with a transmitter, but the encoder does
+//! not depend on any peripheral. Every pulse code uses the format of the RMT
+//! peripheral: two pairs of a 15-bit duration in ticks and a level, in the
+//! lower and upper 16 bits. Custom transports, such as an I2S or parallel
+//! output with DMA, can encode frames with the same encoder and translate the
+//! codes to their own format.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut encoder = PulseEncoder::<Ws2812b>::new(Ws2812b::TIMING, 80, false);
+//! encoder.set_gamma(Some(Gamma::new(2.2)));
+//!
+//! let mut buffer = [0u32; buffer_size(8)];
+//! let len = encoder.encode(&mut buffer, frame.iter().copied())?;
+//!
+//! my_transport.send(&buffer[..len]);
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::{
+    chip::{encode_led, ColorOrder, LedChip, Sk68xx},
+    color::{
+        Adjustments, Calibration, ChannelGamma, ColorCorrection, Gamma, PackedColor,
+        WhiteExtraction,
+    },
+    timing::{Timing, TimingOffsets},
+    LedAdapterError, Pulses,
+};
+
+/// Encodes colors of `Chip` into pulse codes
+#[derive(Clone)]
+pub struct PulseEncoder<Chip = Sk68xx>
+where
+    Chip: LedChip,
+{
+    src_clock: u32,
+    timing: Timing,
+    offsets: TimingOffsets,
+    inverted: bool,
+    pulses: Pulses,
+    end: u32,
+    adjustments: Adjustments,
+    chip: PhantomData<Chip>,
+}
+
+impl<Chip> PulseEncoder<Chip>
+where
+    Chip: LedChip,
+{
+    /// Create a new encoder for the timing profile, for a transmitter whose
+    /// ticks run at `src_clock` MHz, inverting every pulse if `inverted` is
+    /// set.
+    pub fn new(timing: Timing, src_clock: u32, inverted: bool) -> Self {
+        Self {
+            src_clock,
+            timing,
+            offsets: TimingOffsets::NONE,
+            inverted,
+            pulses: pulses(&timing, src_clock, inverted),
+            end: timing.end(src_clock, inverted),
+            adjustments: Adjustments::default(),
+            chip: PhantomData,
+        }
+    }
+
+    /// The timing profile, without the offsets
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// The tick rate pulses are encoded for, in MHz
+    pub fn src_clock(&self) -> u32 {
+        self.src_clock
+    }
+
+    /// Whether every pulse is inverted
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// The pulse code ending every frame
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// Time the line has to stay idle after the end delimiter for the LEDs to
+    /// latch, in microseconds
+    ///
+    /// This is 0 unless the reset time is longer than the end delimiter can
+    /// hold.
+    pub fn latch_gap_us(&self) -> u64 {
+        self.timing.end_shortfall_us(self.src_clock) as u64
+    }
+
+    /// The reset time of the timing profile, in microseconds
+    pub fn reset_us(&self) -> u32 {
+        self.timing.reset_us
+    }
+
+    /// The adjustments applied to every LED
+    #[cfg(feature = "esp-hal")]
+    pub(crate) fn adjustments(&self) -> &Adjustments {
+        &self.adjustments
+    }
+
+    /// Longest time `len` pulse codes of LEDs and the end delimiter take to
+    /// send, in microseconds
+    #[cfg(all(feature = "esp-hal", feature = "stats"))]
+    pub(crate) fn max_duration_us(&self, len: usize) -> u64 {
+        let timing = self.timing.with_offsets(self.offsets);
+        let bit_ns = (timing.t0h_ns + timing.t0l_ns).max(timing.t1h_ns + timing.t1l_ns);
+
+        len.saturating_sub(1) as u64 * bit_ns as u64 / 1000 + timing.reset_us as u64
+    }
+
+    /// Change the tick rate pulses are encoded for, in MHz.
+    pub fn set_src_clock(&mut self, src_clock: u32) {
+        self.src_clock = src_clock;
+        self.set_timing(self.timing);
+    }
+
+    /// Change the timing profile.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+        self.pulses = pulses(
+            &timing.with_offsets(self.offsets),
+            self.src_clock,
+            self.inverted,
+        );
+        self.end = timing.end(self.src_clock, self.inverted);
+    }
+
+    /// Set corrections of the pulses, which are added to the pulses of the
+    /// timing profile.
+    ///
+    /// The offsets are kept when the timing profile is changed.
+    pub fn set_timing_offsets(&mut self, offsets: TimingOffsets) {
+        self.offsets = offsets;
+        self.set_timing(self.timing);
+    }
+
+    /// Set how long the line is held low after every frame, in microseconds.
+    pub fn set_reset_us(&mut self, reset_us: u32) {
+        self.set_timing(self.timing.with_reset_us(reset_us));
+    }
+
+    /// Set the color correction applied to every LED.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.adjustments.correction = correction;
+    }
+
+    /// Set the gamma curve applied to every LED, before the color correction.
+    ///
+    /// This replaces per-channel curves set with
+    /// [`PulseEncoder::set_channel_gamma`].
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.adjustments.set_gamma(gamma);
+    }
+
+    /// Set a gamma curve for every channel, applied to every LED in place of
+    /// the curve set with [`PulseEncoder::set_gamma`].
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.adjustments.set_channel_gamma(gamma);
+    }
+
+    /// Set how the white channel of RGBW LEDs is computed, after the color
+    /// correction.
+    ///
+    /// This has no effect on LEDs without a white channel.
+    pub fn set_white_extraction(&mut self, extraction: Option<WhiteExtraction>) {
+        self.adjustments.white = extraction;
+    }
+
+    /// Set the calibration of the strip, which scales every channel after all
+    /// other adjustments.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.adjustments.calibration = calibration;
+    }
+
+    /// Override the order in which the chip expects the channels, or restore
+    /// it with `None`.
+    pub fn set_color_order(&mut self, order: Option<ColorOrder>) {
+        self.adjustments.order = order;
+    }
+
+    /// Check that the tick rate can produce the pulses of the timing profile.
+    pub fn check_resolution(&self) -> Result<(), LedAdapterError> {
+        match self
+            .timing
+            .with_offsets(self.offsets)
+            .resolves(self.src_clock)
+        {
+            true => Ok(()),
+            false => Err(LedAdapterError::InsufficientResolution {
+                src_clock_mhz: self.src_clock,
+            }),
+        }
+    }
+
+    /// Encode a frame into `buffer`, followed by the end delimiter, and
+    /// return the number of pulse codes used.
+    pub fn encode<T, I>(&self, buffer: &mut [u32], iterator: T) -> Result<usize, LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        let bits = Chip::BITS_PER_LED;
+        let mut iterator = iterator.into_iter();
+        let mut position = 0;
+
+        for (index, item) in iterator.by_ref().enumerate() {
+            // Make sure there is room for the LED and the end delimiter
+            if position + bits >= buffer.len() {
+                return Err(LedAdapterError::buffer_size_exceeded::<Chip>(
+                    index,
+                    index + 1 + iterator.count(),
+                    buffer.len(),
+                ));
+            }
+
+            self.encode_led(item.into(), &mut buffer[position..position + bits]);
+            position += bits;
+        }
+
+        let available = buffer.len();
+        *buffer
+            .get_mut(position)
+            .ok_or(LedAdapterError::buffer_size_exceeded::<Chip>(
+                0, 0, available,
+            ))? = self.end;
+
+        Ok(position + 1)
+    }
+
+    /// Encode a single LED into `buffer`, which must have room for
+    /// `Chip::BITS_PER_LED` pulse codes.
+    pub fn encode_led(&self, color: Chip::Color, buffer: &mut [u32]) {
+        encode_led::<Chip>(color, &self.adjustments, buffer, &self.pulses);
+    }
+
+    /// Encode the colors into `buffer`, which must have room for the pulse
+    /// codes of every color, without an end delimiter.
+    pub fn encode_slice(&self, buffer: &mut [u32], colors: &[Chip::Color]) {
+        for (led, color) in buffer.chunks_exact_mut(Chip::BITS_PER_LED).zip(colors) {
+            self.encode_led(*color, led);
+        }
+    }
+
+    /// Encode colors packed into words, see [`PackedColor`], into `buffer`,
+    /// which must have room for the pulse codes of every color, without an
+    /// end delimiter.
+    pub fn encode_packed(&self, buffer: &mut [u32], packed: &[u32])
+    where
+        Chip::Color: PackedColor,
+    {
+        for (led, word) in buffer.chunks_exact_mut(Chip::BITS_PER_LED).zip(packed) {
+            self.encode_led(Chip::Color::unpack(*word), led);
+        }
+    }
+
+    /// Encode bytes which are already in the order the chip expects into
+    /// `buffer`, which must have room for 8 pulse codes per byte, without an
+    /// end delimiter.
+    ///
+    /// No adjustments are applied to the bytes.
+    pub fn encode_raw(&self, buffer: &mut [u32], bytes: &[u8]) {
+        #[cfg(feature = "lut")]
+        self.pulses.encode(bytes, buffer);
+        #[cfg(not(feature = "lut"))]
+        crate::convert_bytes_to_pulses(bytes, buffer, self.pulses);
+    }
+}
+
+/// Pulse codes for encoding LEDs with the given timing profile
+#[cfg(not(feature = "lut"))]
+fn pulses(timing: &Timing, src_clock: u32, inverted: bool) -> Pulses {
+    timing.pulses(src_clock, inverted)
+}
+
+/// Pulse codes for encoding LEDs with the given timing profile
+#[cfg(feature = "lut")]
+fn pulses(timing: &Timing, src_clock: u32, inverted: bool) -> Pulses {
+    crate::lut::PulseLut::new(timing.pulses(src_clock, inverted))
+}
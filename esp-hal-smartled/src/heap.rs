@@ -0,0 +1,313 @@
+//! Adapters and frame buffers sized at runtime.
+//!
+//! The buffers of [`SmartLedsAdapter`](crate::SmartLedsAdapter) and
+//! [`LedStrip`](crate::strip::LedStrip) are sized by the number of LEDs at
+//! compile time. For products where the length of the strip is a setting,
+//! for example read from flash during provisioning, the `alloc` feature adds
+//! a [`HeapAdapter`] and a [`HeapStrip`] which allocate their buffers on the
+//! heap when they are created, and can be resized later.
+//!
+//! A global allocator has to be set up, for example with `esp-alloc`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! esp_alloc::heap_allocator!(72 * 1024);
+//!
+//! let leds = config.strip_length as usize;
+//! let led = HeapAdapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), leds);
+//! let mut strip = HeapStrip::new(led, leds);
+//!
+//! strip.frame_mut().fill(RGB8::new(0, 0, 32));
+//! strip.flush().unwrap();
+//! ```
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use esp_hal::{
+    gpio::OutputPin,
+    peripheral::Peripheral,
+    rmt::{TxChannel, TxChannelCreator},
+};
+use fugit::HertzU32;
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::{
+    buffer_size_for, channel_config,
+    chip::{ColorOrder, LedChip, Sk68xx},
+    color::{Calibration, ChannelGamma, ColorCorrection, Gamma, WhiteExtraction},
+    encoder::PulseEncoder,
+    strip::frame_hash,
+    timing::{Timing, TimingOffsets},
+    LedAdapterError,
+};
+
+/// Adapter with a pulse buffer allocated on the heap
+pub struct HeapAdapter<TX, Chip = Sk68xx>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    channel: Option<TX>,
+    rmt_buffer: Vec<u32>,
+    encoder: PulseEncoder<Chip>,
+}
+
+impl<'d, TX> HeapAdapter<TX>
+where
+    TX: TxChannel,
+{
+    /// Create a new adapter that drives the pin using the RMT channel, with a
+    /// buffer for `leds` LEDs.
+    ///
+    /// `frequency` is the one the RMT peripheral has been set up with in
+    /// `Rmt::new`.
+    pub fn new<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        leds: usize,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::new_with_chip(channel, pin, frequency, leds, Sk68xx)
+    }
+}
+
+impl<'d, TX, Chip> HeapAdapter<TX, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    /// Create a new adapter that drives the pin using the RMT channel, with a
+    /// buffer for `leds` LEDs of the given type of LED chip.
+    pub fn new_with_chip<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        leds: usize,
+        _chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        let config = channel_config(false);
+        let inverted = config.idle_output_level;
+        let channel = channel.configure(pin, config).unwrap();
+
+        let src_clock = frequency.to_MHz() / config.clk_divider.max(1) as u32;
+
+        Self {
+            channel: Some(channel),
+            rmt_buffer: vec![0; buffer_size_for::<Chip>(leds)],
+            encoder: PulseEncoder::new(Chip::TIMING, src_clock, inverted),
+        }
+    }
+
+    /// Number of LEDs the buffer has room for
+    pub fn leds(&self) -> usize {
+        (self.rmt_buffer.len() - 1) / Chip::BITS_PER_LED
+    }
+
+    /// Resize the buffer to have room for `leds` LEDs.
+    pub fn resize(&mut self, leds: usize) {
+        self.rmt_buffer.resize(buffer_size_for::<Chip>(leds), 0);
+        self.rmt_buffer.shrink_to_fit();
+    }
+
+    /// Change the timing profile, for example to drive a different type of
+    /// strip from the same channel.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.encoder.set_timing(timing);
+    }
+
+    /// Set corrections of the pulses for this board, which are added to the
+    /// pulses of the timing profile.
+    pub fn set_timing_offsets(&mut self, offsets: TimingOffsets) {
+        self.encoder.set_timing_offsets(offsets);
+    }
+
+    /// Set the color correction applied to every LED while encoding.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.encoder.set_color_correction(correction);
+    }
+
+    /// Set the gamma curve applied to every LED while encoding, before the
+    /// color correction.
+    ///
+    /// This replaces per-channel curves set with `set_channel_gamma`.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.encoder.set_gamma(gamma);
+    }
+
+    /// Set a gamma curve for every channel, applied to every LED while
+    /// encoding in place of the curve set with `set_gamma`.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.encoder.set_channel_gamma(gamma);
+    }
+
+    /// Set how the white channel of RGBW LEDs is computed while encoding,
+    /// after the color correction.
+    pub fn set_white_extraction(&mut self, extraction: Option<WhiteExtraction>) {
+        self.encoder.set_white_extraction(extraction);
+    }
+
+    /// Set the calibration of the strip, which scales every channel after all
+    /// other adjustments.
+    ///
+    /// This matches strips from different batches, so they show the same
+    /// colors.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.encoder.set_calibration(calibration);
+    }
+
+    /// Override the order in which the chip expects the color channels, or
+    /// restore it with `None`.
+    pub fn set_color_order(&mut self, order: Option<ColorOrder>) {
+        self.encoder.set_color_order(order);
+    }
+}
+
+impl<TX, Chip> SmartLedsWrite for HeapAdapter<TX, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Encode all color items of the iterator into the buffer, and send them
+    /// in a single transmission.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.encoder.check_resolution()?;
+
+        let len = self.encoder.encode(&mut self.rmt_buffer, iterator)?;
+
+        trace!("transmitting {} pulse codes", len);
+
+        let channel = self.channel.take().unwrap();
+        match channel.transmit(&self.rmt_buffer[..len])?.wait() {
+            Ok(chan) => {
+                self.channel = Some(chan);
+                Ok(())
+            }
+            Err((e, chan)) => {
+                warn!("transmission failed: {}", e);
+                self.channel = Some(chan);
+                Err(LedAdapterError::TransmissionError(e))
+            }
+        }
+    }
+}
+
+/// Frame buffer allocated on the heap for a strip driven by a `smart-leds`
+/// adapter
+pub struct HeapStrip<A> {
+    adapter: A,
+    frame: Vec<RGB8>,
+    skip_unchanged: bool,
+    // Hash of the frame written last, while skipping unchanged frames
+    written: Option<u64>,
+}
+
+impl<A> HeapStrip<A> {
+    /// Create a new strip of `leds` LEDs writing to the given adapter, with
+    /// all LEDs off.
+    pub fn new(adapter: A, leds: usize) -> Self {
+        Self {
+            adapter,
+            frame: vec![RGB8::default(); leds],
+            skip_unchanged: false,
+            written: None,
+        }
+    }
+
+    /// Set whether [`HeapStrip::flush`] skips writing a frame which is the
+    /// same as the one written last, like
+    /// [`LedStrip::set_skip_unchanged`](crate::strip::LedStrip::set_skip_unchanged).
+    pub fn set_skip_unchanged(&mut self, skip_unchanged: bool) {
+        self.skip_unchanged = skip_unchanged;
+        self.written = None;
+    }
+
+    /// Make the next [`HeapStrip::flush`] write the frame even if it is
+    /// unchanged.
+    pub fn invalidate(&mut self) {
+        self.written = None;
+    }
+
+    /// Number of LEDs on the strip
+    pub fn len(&self) -> usize {
+        self.frame.len()
+    }
+
+    /// Whether the strip has no LEDs
+    pub fn is_empty(&self) -> bool {
+        self.frame.is_empty()
+    }
+
+    /// Change the number of LEDs on the strip, turning added LEDs off.
+    ///
+    /// The buffer of a [`HeapAdapter`] has to be resized separately.
+    pub fn resize(&mut self, leds: usize) {
+        self.frame.resize(leds, RGB8::default());
+        self.frame.shrink_to_fit();
+    }
+
+    /// The current frame
+    pub fn frame(&self) -> &[RGB8] {
+        &self.frame
+    }
+
+    /// The current frame, for changing it
+    pub fn frame_mut(&mut self) -> &mut [RGB8] {
+        &mut self.frame
+    }
+
+    /// The wrapped adapter, for example to change its settings
+    ///
+    /// When unchanged frames are skipped, call [`HeapStrip::invalidate`]
+    /// after changing settings which affect the colors.
+    pub fn adapter_mut(&mut self) -> &mut A {
+        &mut self.adapter
+    }
+
+    /// Give back the wrapped adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
+
+impl<A> HeapStrip<A>
+where
+    A: SmartLedsWrite<Color = RGB8>,
+{
+    /// Write the current frame to the LEDs.
+    ///
+    /// If unchanged frames are skipped, nothing is written when the frame is
+    /// the same as the one written last.
+    pub fn flush(&mut self) -> Result<(), A::Error> {
+        if !self.skip_unchanged {
+            return self.adapter.write(self.frame.iter().copied());
+        }
+
+        let hash = frame_hash(&self.frame);
+        if self.written == Some(hash) {
+            return Ok(());
+        }
+
+        self.written = None;
+        self.adapter.write(self.frame.iter().copied())?;
+        self.written = Some(hash);
+
+        Ok(())
+    }
+}
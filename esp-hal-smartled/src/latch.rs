@@ -0,0 +1,256 @@
+//! Updating several strips in the same frame.
+//!
+//! Large displays are often split into segments driven by separate RMT
+//! channels. Writing the segments one after the other makes them update at
+//! visibly different times, because each write waits for its transmission to
+//! finish. Instead, prepare the frames of all segments first, and then send
+//! them together with [`commit_all!`](crate::commit_all), which starts every
+//! transmission as soon as its adapter is ready. If all adapters have finished
+//! their previous frames, the transmissions start back to back, so all
+//! segments latch within a few microseconds of each other.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! left.prepare(left_frame)?;
+//! right.prepare(right_frame)?;
+//!
+//! commit_all!(left, right).await?;
+//! ```
+//!
+//! With blocking adapters, the same can be achieved for frames which fit into
+//! the memory of their channels by calling
+//! [`SmartLedsAdapter::start_write`](crate::SmartLedsAdapter::start_write) on
+//! every adapter before calling
+//! [`SmartLedsAdapter::flush`](crate::SmartLedsAdapter::flush) on them.
+//!
+//! [`write_all!`](crate::write_all) and
+//! [`write_all_blocking!`](crate::write_all_blocking) do both steps in one
+//! call, taking pairs of adapters and frames:
+//!
+//! ```rust,ignore
+//! write_all!((left, left_frame), (right, right_frame)).await?;
+//!
+//! write_all_blocking!((top, top_frame), (bottom, bottom_frame))?;
+//! ```
+//!
+//! Writes only return after the line has been held low for the reset time, so
+//! the LEDs latch every frame. The end of a frame can only hold the line for
+//! 32767 ticks of the RMT peripheral though, which is shorter than long reset
+//! times at high tick rates, and a failed or cancelled transmission does not
+//! hold it at all. A [`LatchGuard`] remembers when the last frame ended and how
+//! much longer the line has to stay idle, and the adapters wait for it before
+//! every write, so frames sent back to back never run into the latch time of
+//! the previous one.
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use esp_hal::time::{now, Instant};
+
+use crate::LedAdapterError;
+
+/// Commit the prepared frames of several [`SmartLedsAdapterAsync`]s together.
+///
+/// Every adapter first waits for its previous frame to be sent and latched,
+/// and for its frame rate limit, and then starts its transmission, so the
+/// transmissions only start in the same poll of the returned future if all
+/// adapters are ready. Await
+/// [`flush`](crate::SmartLedsAdapterAsync::flush) on all of them first to
+/// start them as close together as possible.
+///
+/// The future completes once all transmissions have finished. If any of them
+/// fails, the first error is returned after all transmissions have finished.
+///
+/// [`SmartLedsAdapterAsync`]: crate::SmartLedsAdapterAsync
+#[macro_export]
+macro_rules! commit_all {
+    ( $adapter: expr $(,)? ) => {
+        $adapter.commit()
+    };
+    ( $adapter: expr, $( $rest: expr ),+ $(,)? ) => {
+        $crate::latch::Join::new($adapter.commit(), $crate::commit_all!($( $rest ),+))
+    };
+}
+
+/// Prepare frames on several [`SmartLedsAdapterAsync`]s, then commit them
+/// together with [`commit_all!`](crate::commit_all).
+///
+/// Takes pairs of an adapter and an iterator of colors. Nothing is sent if
+/// any of the frames fails to encode.
+///
+/// [`SmartLedsAdapterAsync`]: crate::SmartLedsAdapterAsync
+#[macro_export]
+macro_rules! write_all {
+    ( $( ( $adapter: expr, $frame: expr ) ),+ $(,)? ) => {
+        async {
+            $( $adapter.prepare($frame)?; )+
+            $crate::commit_all!($( $adapter ),+).await
+        }
+    };
+}
+
+/// Start writing frames on several [`SmartLedsAdapter`]s, then wait until all
+/// of them have been sent.
+///
+/// Takes pairs of an adapter and an iterator of colors. The frames are sent
+/// with [`SmartLedsAdapter::start_write`], so each of them must fit into the
+/// memory of its channel.
+///
+/// [`SmartLedsAdapter`]: crate::SmartLedsAdapter
+/// [`SmartLedsAdapter::start_write`]: crate::SmartLedsAdapter::start_write
+#[macro_export]
+macro_rules! write_all_blocking {
+    ( $( ( $adapter: expr, $frame: expr ) ),+ $(,)? ) => {
+        (|| -> ::core::result::Result<(), $crate::LedAdapterError> {
+            $( $adapter.start_write($frame)?; )+
+            $( $adapter.flush()?; )+
+            Ok(())
+        })()
+    };
+}
+
+/// Waits for the LEDs to latch the previous frame
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LatchGuard {
+    idle_us: u64,
+    last_end: Option<Instant>,
+}
+
+impl LatchGuard {
+    /// Create a new guard, which does not wait until the first frame ended.
+    pub const fn new() -> Self {
+        Self {
+            idle_us: 0,
+            last_end: None,
+        }
+    }
+
+    /// Mark the end of a frame, after which the line has to stay idle for
+    /// another `idle_us` microseconds.
+    pub fn finish(&mut self, idle_us: u64) {
+        self.idle_us = idle_us;
+        self.last_end = Some(now());
+    }
+
+    /// Wait until the LEDs have latched the previous frame.
+    pub fn wait(&self) {
+        while self.remaining_us() > 0 {}
+    }
+
+    /// Wait until the LEDs have latched the previous frame without blocking
+    /// the executor.
+    #[cfg(feature = "embassy-time")]
+    pub async fn wait_async(&self) {
+        let remaining = self.remaining_us();
+        if remaining > 0 {
+            embassy_time::Timer::after_micros(remaining).await;
+        }
+    }
+
+    /// Time until the LEDs have latched the previous frame, in microseconds
+    pub fn remaining_us(&self) -> u64 {
+        let elapsed = match self.last_end {
+            Some(last) if self.idle_us > 0 => now().checked_duration_since(last),
+            _ => return 0,
+        };
+
+        elapsed.map_or(0, |elapsed| {
+            self.idle_us.saturating_sub(elapsed.to_micros())
+        })
+    }
+}
+
+/// Future polling two writes together, returned by
+/// [`commit_all!`](crate::commit_all)
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Join<A, B>
+where
+    A: Future<Output = Result<(), LedAdapterError>>,
+    B: Future<Output = Result<(), LedAdapterError>>,
+{
+    first: MaybeDone<A>,
+    second: MaybeDone<B>,
+}
+
+impl<A, B> Join<A, B>
+where
+    A: Future<Output = Result<(), LedAdapterError>>,
+    B: Future<Output = Result<(), LedAdapterError>>,
+{
+    /// Poll `first` and `second` together.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first: MaybeDone::Pending(first),
+            second: MaybeDone::Pending(second),
+        }
+    }
+}
+
+impl<A, B> Future for Join<A, B>
+where
+    A: Future<Output = Result<(), LedAdapterError>>,
+    B: Future<Output = Result<(), LedAdapterError>>,
+{
+    type Output = Result<(), LedAdapterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the fields are never moved out of the pinned future
+        let this = unsafe { self.get_unchecked_mut() };
+        let first = unsafe { Pin::new_unchecked(&mut this.first) };
+        let second = unsafe { Pin::new_unchecked(&mut this.second) };
+
+        // Always poll both, so every transmission starts as soon as its
+        // adapter is ready
+        let first_ready = first.poll(cx);
+        let second_ready = second.poll(cx);
+        if first_ready.is_pending() || second_ready.is_pending() {
+            return Poll::Pending;
+        }
+
+        match (this.first.take(), this.second.take()) {
+            (Err(e), _) | (_, Err(e)) => Poll::Ready(Err(e)),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// A future, or its result once it has completed
+enum MaybeDone<F>
+where
+    F: Future<Output = Result<(), LedAdapterError>>,
+{
+    Pending(F),
+    Done(Result<(), LedAdapterError>),
+    Taken,
+}
+
+impl<F> MaybeDone<F>
+where
+    F: Future<Output = Result<(), LedAdapterError>>,
+{
+    /// Poll the future unless it has completed already.
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // SAFETY: the future is only dropped in place, never moved
+        let this = unsafe { self.get_unchecked_mut() };
+        if let MaybeDone::Pending(future) = this {
+            match unsafe { Pin::new_unchecked(future) }.poll(cx) {
+                Poll::Ready(result) => *this = MaybeDone::Done(result),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(())
+    }
+
+    /// Take the result of the completed future.
+    fn take(&mut self) -> Result<(), LedAdapterError> {
+        match core::mem::replace(self, MaybeDone::Taken) {
+            MaybeDone::Done(result) => result,
+            _ => Ok(()),
+        }
+    }
+}
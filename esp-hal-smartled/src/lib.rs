@@ -2,11 +2,17 @@
 //! with RGB LEDs and use the convenience functions of the
 //! [`smart-leds`](https://crates.io/crates/smart-leds) crate.
 //!
-//! This is a simple implementation where every LED is adressed in an
-//! individual RMT operation. This is working perfectly fine in blocking mode,
-//! but in case this is used in combination with interrupts that might disturb
-//! the sequential sending, an alternative implementation (addressing the LEDs
-//! in a sequence in a single RMT send operation) might be required!
+//! A frame is encoded into the pulse buffer of the adapter as a whole and
+//! sent in a single RMT transmission, with the driver refilling the memory of
+//! the channel from the buffer while it is sent. Interrupts which delay these
+//! refills leave a gap in the frame, which the LEDs take as its end. The
+//! gapless mode of the adapters avoids this by sending with interrupts
+//! disabled.
+//!
+//! The adapters are enabled by the `esp-hal` feature, which every chip
+//! feature turns on. Without a chip feature, the encoding, color and effect
+//! modules build for the host, and the `std` feature adds `SimAdapter` to
+//! test what would be sent with `cargo test --features std`.
 //!
 //! ## Example
 //!
@@ -14,7 +20,36 @@
 //! let rmt = Rmt::new(peripherals.RMT, 80.MHz(), None).unwrap();
 //!
 //! let rmt_buffer = smartLedBuffer!(1);
-//! let mut led = SmartLedsAdapter::new(rmt.channel0, peripherals.GPIO2, rmt_buffer);
+//! let mut led = SmartLedsAdapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), rmt_buffer);
+//! ```
+//!
+//! The adapters default to timings which work with most chips of the WS2812
+//! and SK6812 families. Other chips, including RGBW ones, are selected with a
+//! type from the [`chip`] module:
+//!
+//! ```rust,ignore
+//! let rmt_buffer = smart_led_buffer!(1, 4);
+//! let mut led = SmartLedsAdapter::new_with_chip(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//!     rmt_buffer,
+//!     Sk6812Rgbw,
+//! );
+//!
+//! // The color type of RGBW chips is `RGBW<u8>` of `smart-leds`
+//! led.write([RGBW::new_alpha(255, 0, 0, White(64))]).unwrap();
+//! ```
+//!
+//! For use with async executors such as Embassy, [`SmartLedsAdapterAsync`]
+//! implements `SmartLedsWriteAsync` on top of an RMT channel in async mode:
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap().into_async();
+//!
+//! let rmt_buffer = smart_led_buffer!(1);
+//! let mut led = SmartLedsAdapterAsync::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), rmt_buffer);
+//! led.write(data.iter().cloned()).await.unwrap();
 //! ```
 //!
 //! ## Feature Flags
@@ -23,21 +58,137 @@
 #![deny(missing_docs)]
 #![no_std]
 
-use core::{fmt::Debug, slice::IterMut};
+use core::fmt::{self, Debug, Display};
+#[cfg(feature = "esp-hal")]
+use core::{
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    pin::pin,
+    ptr::addr_of_mut,
+    task::Poll,
+};
 
+#[cfg(feature = "esp-hal")]
 use esp_hal::{
-    clock::Clocks,
-    gpio::OutputPin,
+    gpio::{AnyPin, OutputPin},
+    interrupt::{self, Priority},
     peripheral::Peripheral,
-    rmt::{Error as RmtError, PulseCode, TxChannel, TxChannelConfig, TxChannelCreator},
+    peripherals::Interrupt,
+    rmt::{
+        Channel, Error as RmtError, TxChannel, TxChannelAsync, TxChannelConfig, TxChannelCreator,
+        TxChannelCreatorAsync, TxChannelInternal,
+    },
+    Async, Blocking,
 };
-use smart_leds_trait::{SmartLedsWrite, RGB8};
+#[cfg(feature = "esp-hal")]
+use fugit::HertzU32;
+#[cfg(feature = "esp-hal")]
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync, RGB8, RGBW};
+
+/// Re-export of the `smart-leds-trait` crate implemented by the adapters, so
+/// downstream crates can name the exact revision in use.
+pub use smart_leds_trait;
+
+use crate::chip::LedChip;
+#[cfg(all(feature = "esp-hal", feature = "stats"))]
+use crate::stats::{elapsed_us, SignalHealth, Stats, StatsRecorder, LATE_REFILL_SLACK_US};
+#[cfg(feature = "esp-hal")]
+use crate::{
+    chip::{ColorOrder, Sk68xx},
+    color::{
+        hsv2rgb, rgbw, Calibration, ChannelGamma, ColorCorrection, Gamma, Hsv, PackedColor,
+        WhiteExtraction,
+    },
+    config::StripConfig,
+    count::{Counted, LedCount},
+    encoder::PulseEncoder,
+    latch::LatchGuard,
+    output::OutputConfig,
+    pacing::FrameLimiter,
+    timing::{Timing, TimingOffsets},
+};
+#[cfg(all(feature = "esp-hal", feature = "stats"))]
+use esp_hal::time::now;
+
+/// Log a trace message with `defmt`, if the `defmt` feature is enabled.
+#[cfg(feature = "esp-hal")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::trace!($($arg)*);
+    };
+}
+
+/// Log a warning with `defmt`, if the `defmt` feature is enabled.
+#[cfg(feature = "esp-hal")]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::warn!($($arg)*);
+    };
+}
 
-const SK68XX_CODE_PERIOD: u32 = 1250; // 800kHz
-const SK68XX_T0H_NS: u32 = 400; // 300ns per SK6812 datasheet, 400 per WS2812. Some require >350ns for T0H. Others <500ns for T0H.
-const SK68XX_T0L_NS: u32 = SK68XX_CODE_PERIOD - SK68XX_T0H_NS;
-const SK68XX_T1H_NS: u32 = 850; // 900ns per SK6812 datasheet, 850 per WS2812. > 550ns is sometimes enough. Some require T1H >= 2 * T0H. Some require > 300ns T1L.
-const SK68XX_T1L_NS: u32 = SK68XX_CODE_PERIOD - SK68XX_T1H_NS;
+#[cfg(feature = "esp-hal")]
+pub mod adalight;
+#[cfg(feature = "esp-hal")]
+pub mod animation;
+pub mod audio;
+#[cfg(feature = "esp-hal")]
+pub mod auto;
+pub mod chip;
+pub mod clocked;
+pub mod color;
+pub mod compose;
+#[cfg(feature = "esp-hal")]
+pub mod config;
+#[cfg(feature = "esp-hal")]
+pub mod count;
+pub mod dither;
+#[cfg(feature = "esp-hal")]
+pub mod dual_core;
+pub mod encoder;
+#[cfg(all(feature = "esp-hal", feature = "alloc"))]
+pub mod heap;
+#[cfg(feature = "esp-hal")]
+pub mod latch;
+#[cfg(feature = "esp-hal")]
+pub mod loopback;
+#[cfg(feature = "lut")]
+mod lut;
+pub mod mask;
+pub mod matrix;
+#[cfg(feature = "esp-hal")]
+pub mod mirror;
+pub mod noise;
+#[cfg(feature = "esp-hal")]
+pub mod output;
+#[cfg(feature = "esp-hal")]
+pub mod pacing;
+pub mod palette;
+#[cfg(feature = "esp-hal")]
+pub mod pipeline;
+pub mod power;
+#[cfg(feature = "embassy-sync")]
+pub mod queue;
+#[cfg(feature = "embassy-sync")]
+pub mod runner;
+#[cfg(feature = "std")]
+pub mod sim;
+#[cfg(all(feature = "esp-hal", feature = "spi"))]
+pub mod spi;
+#[cfg(all(feature = "esp-hal", feature = "stats"))]
+pub mod stats;
+#[cfg(feature = "esp-hal")]
+pub mod status;
+#[cfg(feature = "esp-hal")]
+pub mod strip;
+#[cfg(feature = "esp-hal")]
+pub mod test_patterns;
+pub mod timing;
+#[cfg(feature = "esp-hal")]
+pub mod tm1814;
+pub mod transition;
 
 /// All types of errors that can happen during the conversion and transmission
 /// of LED commands
@@ -45,22 +196,220 @@ const SK68XX_T1L_NS: u32 = SK68XX_CODE_PERIOD - SK68XX_T1H_NS;
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LedAdapterError {
     /// Raised in the event that the provided data container is not large enough
-    BufferSizeExceeded,
+    ///
+    /// The frame needs a buffer of `required` pulse codes, but the adapter was
+    /// created with a buffer of only `available` pulse codes. Nothing has been
+    /// sent to the LEDs. Size the buffer for the number of LEDs on the strip,
+    /// using [`buffer_size`] or [`smart_led_buffer!`], or write shorter
+    /// frames.
+    BufferSizeExceeded {
+        /// Index of the first LED which did not fit into the buffer
+        led: usize,
+        /// Number of pulse codes needed for the whole frame
+        required: usize,
+        /// Number of pulse codes in the buffer
+        available: usize,
+    },
     /// Raised if something goes wrong in the transmission,
+    ///
+    /// The adapter can still be used, so the write can simply be retried.
+    /// Persistent errors usually mean that the RMT peripheral or channel is
+    /// misconfigured.
+    #[cfg(feature = "esp-hal")]
     TransmissionError(RmtError),
+    /// The frame was not sent within the timeout of
+    /// [`SmartLedsAdapterAsync::write_with_timeout`]
+    ///
+    /// The transmission has been stopped, and the adapter can still be used.
+    Timeout,
+    /// The RMT peripheral runs too slowly to produce the pulses of the timing
+    /// profile
+    ///
+    /// At least one pulse would be shorter than a tick, or a `0` and a `1`
+    /// bit would be the same. Nothing has been sent. Set up the RMT
+    /// peripheral with a higher frequency, or a smaller `clk_divider`.
+    InsufficientResolution {
+        /// Tick rate of the channel, in MHz
+        src_clock_mhz: u32,
+    },
+    /// The buffer is not sized for the declared number of LEDs
+    ///
+    /// Raised when creating an adapter with a [`LedCount`](count::LedCount).
+    BufferSizeMismatch {
+        /// Number of pulse codes needed for the declared number of LEDs
+        required: usize,
+        /// Number of pulse codes in the buffer
+        available: usize,
+    },
+    /// The frame does not have the declared number of LEDs, and the
+    /// [`CountPolicy`](count::CountPolicy) rejects it
+    ///
+    /// Nothing has been sent, unless the adapter streams long frames in parts.
+    LedCountMismatch {
+        /// The declared number of LEDs
+        expected: usize,
+        /// The number of LEDs in the frame
+        actual: usize,
+    },
+    /// The chip of a [`StripConfig`](config::StripConfig) has a different
+    /// number of channels than the chip of the adapter
+    ChannelMismatch {
+        /// The number of channels of the chip of the adapter
+        expected: usize,
+        /// The number of channels of the chip of the config
+        actual: usize,
+    },
+    /// The frame does not fit into the buffer, and sending it in parts would
+    /// leave gaps in the signal, which gapless mode rules out
+    ///
+    /// Nothing has been sent. See [`SmartLedsAdapter::set_gapless`].
+    GapUnavoidable {
+        /// Number of pulse codes needed for the frame
+        required: usize,
+        /// Number of pulse codes in the buffer
+        available: usize,
+    },
+}
+
+impl LedAdapterError {
+    /// Error for a frame of `leds` LEDs of `C`, of which the one at index `led`
+    /// did not fit into a buffer of `available` pulse codes.
+    fn buffer_size_exceeded<C>(led: usize, leds: usize, available: usize) -> Self
+    where
+        C: LedChip,
+    {
+        LedAdapterError::BufferSizeExceeded {
+            led,
+            required: leds * C::BITS_PER_LED + 1,
+            available,
+        }
+    }
+
+    /// Error for a frame of `leds` LEDs of `C` which would have to be sent in
+    /// parts from a buffer of `available` pulse codes.
+    #[cfg(feature = "esp-hal")]
+    fn gap_unavoidable<C>(leds: usize, available: usize) -> Self
+    where
+        C: LedChip,
+    {
+        LedAdapterError::GapUnavoidable {
+            required: leds * C::BITS_PER_LED + 1,
+            available,
+        }
+    }
 }
 
+impl Display for LedAdapterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedAdapterError::BufferSizeExceeded {
+                led,
+                required,
+                available,
+            } => write!(
+                f,
+                "LED {led} does not fit into the buffer: the frame needs {required} pulse codes, \
+                 but the buffer holds {available}"
+            ),
+            #[cfg(feature = "esp-hal")]
+            LedAdapterError::TransmissionError(e) => write!(f, "RMT transmission failed: {e:?}"),
+            LedAdapterError::Timeout => write!(f, "the frame was not sent in time"),
+            LedAdapterError::InsufficientResolution { src_clock_mhz } => write!(
+                f,
+                "the pulses of the timing profile cannot be produced at {src_clock_mhz} MHz"
+            ),
+            LedAdapterError::BufferSizeMismatch {
+                required,
+                available,
+            } => write!(
+                f,
+                "the buffer holds {available} pulse codes, but the LEDs need {required}"
+            ),
+            LedAdapterError::LedCountMismatch { expected, actual } => write!(
+                f,
+                "the frame has {actual} LEDs, but the strip has {expected}"
+            ),
+            LedAdapterError::ChannelMismatch { expected, actual } => write!(
+                f,
+                "the chip has {actual} channels, but the adapter sends {expected}"
+            ),
+            LedAdapterError::GapUnavoidable {
+                required,
+                available,
+            } => write!(
+                f,
+                "the frame needs {required} pulse codes, but the buffer holds {available}, \
+                 and it cannot be sent in parts without gaps"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for LedAdapterError {}
+
+#[cfg(feature = "esp-hal")]
 impl From<RmtError> for LedAdapterError {
     fn from(e: RmtError) -> Self {
         LedAdapterError::TransmissionError(e)
     }
 }
 
+/// Number of `u32` pulse codes needed to address `num_leds` RGB LEDs
+///
+/// Every LED takes 24 pulses (3 channels of 8 bits each), and one additional
+/// pulse is needed for the end delimiter.
+pub const fn buffer_size(num_leds: usize) -> usize {
+    num_leds * 24 + 1
+}
+
+/// Number of `u32` pulse codes needed to address `num_leds` RGBW LEDs
+///
+/// Every LED takes 32 pulses (4 channels of 8 bits each), and one additional
+/// pulse is needed for the end delimiter.
+pub const fn buffer_size_rgbw(num_leds: usize) -> usize {
+    num_leds * 32 + 1
+}
+
+/// Number of `u32` pulse codes needed to address `num_leds` LEDs of `C`
+///
+/// This is the size the adapters expect for the chip, so it stays correct for
+/// chips with any number of channels.
+pub const fn buffer_size_for<C>(num_leds: usize) -> usize
+where
+    C: LedChip,
+{
+    buffer_size_bits(num_leds, C::BITS_PER_LED)
+}
+
+/// Number of `u32` pulse codes needed to address `num_leds` LEDs which take
+/// `bits_per_led` bits each
+pub const fn buffer_size_bits(num_leds: usize, bits_per_led: usize) -> usize {
+    num_leds * bits_per_led + 1
+}
+
+/// Number of `u32` pulse codes needed to address `num_leds` RGB LEDs with
+/// [`SmartLedsAdapterAsync`]
+///
+/// The adapter can only send frames which fit into the memory of its channel,
+/// see [`SmartLedsAdapterAsync::capacity_leds`].
+pub const fn buffer_size_async(num_leds: usize) -> usize {
+    buffer_size(num_leds)
+}
+
+/// Number of `u32` pulse codes needed to address `num_leds` RGBW LEDs with
+/// [`SmartLedsAdapterAsync`]
+///
+/// The adapter can only send frames which fit into the memory of its channel,
+/// see [`SmartLedsAdapterAsync::capacity_leds`].
+pub const fn buffer_size_async_rgbw(num_leds: usize) -> usize {
+    buffer_size_rgbw(num_leds)
+}
+
 /// Macro to allocate a buffer sized for a specific number of LEDs to be
 /// addressed.
 ///
 /// Attempting to use more LEDs that the buffer is configured for will result in
-/// an `LedAdapterError:BufferSizeExceeded` error.
+/// an [`LedAdapterError::BufferSizeExceeded`] error.
 #[macro_export]
 macro_rules! smartLedBuffer {
     ( $buffer_size: literal ) => {
@@ -74,17 +423,215 @@ macro_rules! smartLedBuffer {
     };
 }
 
+/// Macro to allocate a buffer sized for a specific number of LEDs to be
+/// addressed, with an optional number of bytes per LED.
+///
+/// Without the second argument, the buffer is sized for RGB LEDs with 3 bytes
+/// per LED, like [`buffer_size`]. Pass 4 for RGBW LEDs, the number of bits per
+/// LED with `bits = `, or the type of LED chip with `chip = `, which sizes the
+/// buffer like [`buffer_size_for`].
+///
+/// ```rust,ignore
+/// let rgb_buffer = smart_led_buffer!(NUM_LEDS);
+/// let rgbw_buffer = smart_led_buffer!(NUM_LEDS, 4);
+/// let rgbw_buffer = smart_led_buffer!(NUM_LEDS, chip = Sk6812Rgbw);
+/// let custom_buffer = smart_led_buffer!(NUM_LEDS, bits = 48);
+/// ```
+///
+/// Attempting to use more LEDs that the buffer is configured for will result in
+/// an [`LedAdapterError::BufferSizeExceeded`] error.
+#[macro_export]
+macro_rules! smart_led_buffer {
+    ( $num_leds: expr ) => {
+        $crate::smart_led_buffer!($num_leds, 3)
+    };
+    ( $num_leds: expr, chip = $chip: path ) => {
+        [0u32; $crate::buffer_size_for::<$chip>($num_leds)]
+    };
+    ( $num_leds: expr, bits = $bits_per_led: expr ) => {
+        [0u32; $crate::buffer_size_bits($num_leds, $bits_per_led)]
+    };
+    ( $num_leds: expr, $bytes_per_led: expr ) => {
+        // 8 pulses per byte, and 1 additional pulse for the end delimiter
+        [0u32; $crate::buffer_size_bits($num_leds, $bytes_per_led * 8)]
+    };
+}
+
+/// Macro to create a [`SmartLedsAdapter`] for a number of LEDs, with an
+/// internal buffer sized for exactly that many LEDs.
+///
+/// Without the chip argument, the adapter drives [`Sk68xx`] LEDs. The number
+/// of LEDs must be a constant expression.
+///
+/// ```rust,ignore
+/// const NUM_LEDS: usize = 60;
+///
+/// let led = smart_leds_adapter!(rmt.channel0, peripherals.GPIO2, 80.MHz(), NUM_LEDS);
+/// let rgbw = smart_leds_adapter!(rmt.channel1, peripherals.GPIO3, 80.MHz(), NUM_LEDS, Sk6812Rgbw);
+/// ```
+#[macro_export]
+macro_rules! smart_leds_adapter {
+    ( $channel: expr, $pin: expr, $frequency: expr, $num_leds: expr ) => {
+        $crate::smart_leds_adapter!($channel, $pin, $frequency, $num_leds, $crate::chip::Sk68xx)
+    };
+    ( $channel: expr, $pin: expr, $frequency: expr, $num_leds: expr, $chip: path ) => {
+        $crate::SmartLedsAdapter::<
+                                    _,
+                                    { $crate::buffer_size_for::<$chip>($num_leds) },
+                                    $chip,
+                                >::new_for_leds::<{ $num_leds }, _, _>($channel, $pin, $frequency, $chip)
+    };
+}
+
+/// Macro to create a [`SmartLedsAdapterAsync`] for a number of LEDs, with
+/// internal buffers sized for exactly that many LEDs.
+///
+/// See [`smart_leds_adapter!`].
+#[macro_export]
+macro_rules! smart_leds_adapter_async {
+    ( $channel: expr, $pin: expr, $frequency: expr, $num_leds: expr ) => {
+        $crate::smart_leds_adapter_async!($channel, $pin, $frequency, $num_leds, $crate::chip::Sk68xx)
+    };
+    ( $channel: expr, $pin: expr, $frequency: expr, $num_leds: expr, $chip: path ) => {
+        $crate::SmartLedsAdapterAsync::<
+                                    _,
+                                    { $crate::buffer_size_for::<$chip>($num_leds) },
+                                    $chip,
+                                >::new_for_leds::<{ $num_leds }, _, _>($channel, $pin, $frequency, $chip)
+    };
+}
+
+/// Macro to create the layout of an LED matrix and a buffer for its LEDs at
+/// compile time.
+///
+/// The matrix is given by the width and height of the panel as wired, and
+/// `Progressive` for panels whose rows all run from left to right, or
+/// `Serpentine` for panels where every other row runs backwards. It is
+/// followed by any of these options, which correspond to the methods of
+/// [`MatrixLayout`](matrix::MatrixLayout):
+///
+/// - `order = ColumnMajor` if the strip runs along the columns,
+/// - `rotate = Deg90`, `Deg180` or `Deg270` for rotated panels,
+/// - `flip_horizontal` and `flip_vertical` for mirrored panels,
+/// - `chip = Sk6812Rgbw`, to size the buffer for another chip than [`Sk68xx`],
+/// - `leds = 256`, to check that the matrix has that many LEDs.
+///
+/// The macro evaluates to a [`MappedLayout`](matrix::MappedLayout) and a
+/// pulse buffer for exactly the LEDs of the matrix. The table is computed in
+/// a `const` item, so dimensions which do not match the number of LEDs fail
+/// the build.
+///
+/// ```rust,ignore
+/// const NUM_LEDS: usize = 256;
+///
+/// let (layout, buffer) = led_matrix!(16, 16, Serpentine, rotate = Deg180, leds = NUM_LEDS);
+/// let led = SmartLedsAdapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), buffer);
+/// let mut matrix: LedMatrix<_, _, NUM_LEDS> = LedMatrix::new(led, layout);
+/// ```
+#[macro_export]
+macro_rules! led_matrix {
+    (@options [$chip: path] ($layout: expr) order = $order: ident $(, $($rest: tt)*)?) => {
+        $crate::led_matrix!(
+            @options [$chip] ($layout.order($crate::matrix::Order::$order)) $($($rest)*)?
+        )
+    };
+    (@options [$chip: path] ($layout: expr) rotate = $rotation: ident $(, $($rest: tt)*)?) => {
+        $crate::led_matrix!(
+            @options [$chip] ($layout.rotate($crate::matrix::Rotation::$rotation)) $($($rest)*)?
+        )
+    };
+    (@options [$chip: path] ($layout: expr) flip_horizontal $(, $($rest: tt)*)?) => {
+        $crate::led_matrix!(@options [$chip] ($layout.flip_horizontal()) $($($rest)*)?)
+    };
+    (@options [$chip: path] ($layout: expr) flip_vertical $(, $($rest: tt)*)?) => {
+        $crate::led_matrix!(@options [$chip] ($layout.flip_vertical()) $($($rest)*)?)
+    };
+    (@options [$_chip: path] ($layout: expr) chip = $chip: path $(, $($rest: tt)*)?) => {
+        $crate::led_matrix!(@options [$chip] ($layout) $($($rest)*)?)
+    };
+    (@options [$chip: path] ($layout: expr) leds = $leds: expr $(, $($rest: tt)*)?) => {{
+        const {
+            let (width, height) = $layout.viewed_size();
+            assert!(
+                width * height == $leds,
+                "the matrix does not have the given number of LEDs"
+            )
+        };
+        $crate::led_matrix!(@options [$chip] ($layout) $($($rest)*)?)
+    }};
+    (@options [$chip: path] ($layout: expr)) => {{
+        const LAYOUT: $crate::matrix::MatrixLayout = $layout;
+        const WIDTH: usize = LAYOUT.viewed_size().0;
+        const HEIGHT: usize = LAYOUT.viewed_size().1;
+        const MAPPED: $crate::matrix::MappedLayout<WIDTH, HEIGHT> =
+            $crate::matrix::MappedLayout::new(LAYOUT);
+
+        (MAPPED, [0u32; $crate::buffer_size_for::<$chip>(WIDTH * HEIGHT)])
+    }};
+    ( $width: expr, $height: expr, Progressive $(, $($options: tt)*)? ) => {
+        $crate::led_matrix!(
+            @options [$crate::chip::Sk68xx]
+            ($crate::matrix::MatrixLayout::new($width, $height)) $($($options)*)?
+        )
+    };
+    ( $width: expr, $height: expr, Serpentine $(, $($options: tt)*)? ) => {
+        $crate::led_matrix!(
+            @options [$crate::chip::Sk68xx]
+            ($crate::matrix::MatrixLayout::new($width, $height).serpentine()) $($($options)*)?
+        )
+    };
+}
+
+// Every channel uses a single block of memory: the RMT driver of `esp-hal`
+// resets the memory size of a channel to one block whenever it starts a
+// transmission, so claiming the blocks of the following channels as well
+// would not let it refill less often.
+
+/// Number of pulse codes in a block of RMT channel memory
+#[cfg(all(feature = "esp-hal", any(feature = "esp32", feature = "esp32s2")))]
+const CHANNEL_RAM_SIZE: usize = 64;
+/// Number of pulse codes in a block of RMT channel memory
+#[cfg(all(feature = "esp-hal", not(any(feature = "esp32", feature = "esp32s2"))))]
+const CHANNEL_RAM_SIZE: usize = 48;
+
 /// Adapter taking an RMT channel and a specific pin and providing RGB LED
 /// interaction functionality using the `smart-leds` crate
-pub struct SmartLedsAdapter<TX, const BUFFER_SIZE: usize>
+///
+/// The adapter owns the channel and its pulse buffer, so it does not borrow
+/// anything and can be moved into a task or stored in a `static`.
+///
+/// The adapter keeps the output pin, and can move the signal to another pin
+/// at runtime with [`SmartLedsAdapter::swap_pin`], to drive several
+/// connectors from one channel in turn.
+#[cfg(feature = "esp-hal")]
+pub struct SmartLedsAdapter<TX, const BUFFER_SIZE: usize, Chip = Sk68xx>
 where
     TX: TxChannel,
+    Chip: LedChip,
 {
+    // `None` while a frame started with `start_write` is in flight
     channel: Option<TX>,
     rmt_buffer: [u32; BUFFER_SIZE],
-    pulses: (u32, u32),
+    encoder: PulseEncoder<Chip>,
+    count: Option<LedCount>,
+    // Pin the signal is sent on, and how it is driven
+    pin: AnyPin,
+    output: OutputConfig,
+    streaming: bool,
+    gapless: bool,
+    yield_hook: Option<fn()>,
+    done_hook: Option<fn()>,
+    limiter: FrameLimiter,
+    latch: LatchGuard,
+    // Set if the RMT peripheral has been set up in async mode, which binds
+    // the interrupt handler async transmissions are woken by
+    interrupt_bound: bool,
+    #[cfg(feature = "stats")]
+    stats: StatsRecorder,
+    chip: PhantomData<Chip>,
 }
 
+#[cfg(feature = "esp-hal")]
 impl<'d, TX, const BUFFER_SIZE: usize> SmartLedsAdapter<TX, BUFFER_SIZE>
 where
     TX: TxChannel,
@@ -93,115 +640,2327 @@ where
     pub fn new<C, O>(
         channel: C,
         pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::new_with_timing(channel, pin, frequency, rmt_buffer, Timing::default())
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// with an internal buffer of `BUFFER_SIZE` pulse codes.
+    ///
+    /// ```rust,ignore
+    /// let led = SmartLedsAdapter::<_, { buffer_size(NUM_LEDS) }>::new_owned(
+    ///     rmt.channel0,
+    ///     peripherals.GPIO2,
+    ///     80.MHz(),
+    /// );
+    /// ```
+    pub fn new_owned<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::new(channel, pin, frequency, [0; BUFFER_SIZE])
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// using the given timing profile.
+    pub fn new_with_timing<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        timing: Timing,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            timing,
+            channel_config(false),
+            OutputConfig::new(),
+        )
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<'d, TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapter<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip.
+    ///
+    /// ```rust,ignore
+    /// let led = SmartLedsAdapter::new_with_chip(
+    ///     rmt.channel0,
+    ///     peripherals.GPIO2,
+    ///     80.MHz(),
+    ///     rmt_buffer,
+    ///     Ws2812b,
+    /// );
+    /// ```
+    pub fn new_with_chip<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
         rmt_buffer: [u32; BUFFER_SIZE],
-    ) -> SmartLedsAdapter<TX, BUFFER_SIZE>
+        _chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            channel_config(false),
+            OutputConfig::new(),
+        )
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, with an internal buffer of `BUFFER_SIZE`
+    /// pulse codes.
+    pub fn new_owned_with_chip<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        chip: Chip,
+    ) -> Self
     where
         O: OutputPin + 'd,
         C: TxChannelCreator<'d, TX, O>,
     {
-        let config = TxChannelConfig {
-            clk_divider: 1,
-            idle_output_level: false,
-            carrier_modulation: false,
-            idle_output: true,
+        Self::new_with_chip(channel, pin, frequency, [0; BUFFER_SIZE], chip)
+    }
 
-            ..TxChannelConfig::default()
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for `LEDS` LEDs of the given type, with an internal buffer sized for
+    /// exactly that many LEDs.
+    ///
+    /// `BUFFER_SIZE` is checked against the number of LEDs at compile time, so
+    /// the two can never disagree. [`smart_leds_adapter!`] fills it in:
+    ///
+    /// ```rust,ignore
+    /// let led = smart_leds_adapter!(rmt.channel0, peripherals.GPIO2, 80.MHz(), NUM_LEDS, Ws2812b);
+    /// ```
+    pub fn new_for_leds<const LEDS: usize, C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        const {
+            assert!(
+                BUFFER_SIZE == buffer_size_for::<Chip>(LEDS),
+                "the buffer size does not match the number of LEDs"
+            )
         };
 
+        Self::new_owned_with_chip(channel, pin, frequency, chip)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for a strip of the given type of LED chip with `count` LEDs.
+    ///
+    /// Fails with [`LedAdapterError::BufferSizeMismatch`] unless the buffer is
+    /// sized for exactly that many LEDs. Every write is then checked against
+    /// the count, see [`LedCount`](count::LedCount).
+    pub fn new_with_led_count<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        chip: Chip,
+        count: LedCount,
+    ) -> Result<Self, LedAdapterError>
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        let mut adapter = Self::new_with_chip(channel, pin, frequency, rmt_buffer, chip);
+        adapter.set_led_count(Some(count))?;
+
+        Ok(adapter)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for a strip with the given settings.
+    ///
+    /// `chip` is the type of chip the adapter is created for, which must have
+    /// as many channels as the chip of the config. See
+    /// [`set_strip_config`](Self::set_strip_config).
+    pub fn new_with_strip_config<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        chip: Chip,
+        config: &StripConfig,
+    ) -> Result<Self, LedAdapterError>
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        let mut adapter = Self::new_with_chip(channel, pin, frequency, rmt_buffer, chip);
+        adapter.set_strip_config(config)?;
+
+        Ok(adapter)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, with an internal buffer of `BUFFER_SIZE`
+    /// pulse codes, directly in `slot`.
+    ///
+    /// The other constructors return the adapter by value, which moves its
+    /// buffer over the stack. For long strips, where the buffer takes 96 KiB
+    /// per thousand LEDs, this can overflow the stack, and the buffer may not
+    /// fit into internal RAM at all. Here, the buffer is zeroed where it is
+    /// stored, so the adapter can be placed in PSRAM on chips which have it,
+    /// such as the ESP32 and ESP32-S3:
+    ///
+    /// ```rust,ignore
+    /// let (start, size) = esp_hal::psram::psram_raw_parts(&peripherals.PSRAM);
+    /// type Led = SmartLedsAdapter<Channel<Blocking, 0>, { buffer_size(2000) }>;
+    /// assert!(size >= mem::size_of::<Led>());
+    ///
+    /// // SAFETY: the PSRAM is not used for anything else
+    /// let slot = unsafe { &mut *(start as *mut MaybeUninit<Led>) };
+    /// let led = Led::new_in_place(slot, rmt.channel0, peripherals.GPIO2, 80.MHz(), Sk68xx);
+    /// ```
+    ///
+    /// The RMT driver copies the pulse codes into the memory of the channel
+    /// with the CPU while waiting for a transmission, without DMA, so there are
+    /// no cache coherency concerns. Reading PSRAM is slower than internal RAM
+    /// though, and cache misses make the copies take longer, so keep the
+    /// interrupt load low while writing to avoid gaps in the signal.
+    pub fn new_in_place<'s, C, O>(
+        slot: &'s mut MaybeUninit<Self>,
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        _chip: Chip,
+    ) -> &'s mut Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        // Configure an adapter without a buffer, and move its state into the
+        // slot next to the zeroed buffer
+        let SmartLedsAdapter {
+            channel,
+            rmt_buffer: [],
+            encoder,
+            count,
+            pin,
+            output,
+            streaming,
+            gapless,
+            yield_hook,
+            done_hook,
+            limiter,
+            latch,
+            interrupt_bound,
+            #[cfg(feature = "stats")]
+            stats,
+            chip,
+        } = SmartLedsAdapter::<TX, 0, Chip>::configure(
+            channel,
+            pin,
+            frequency,
+            [],
+            Chip::TIMING,
+            channel_config(false),
+            OutputConfig::new(),
+        );
+
+        let adapter = slot.as_mut_ptr();
+        // SAFETY: the pointers are derived from the slot, and every field is
+        // written before the slot is assumed to be initialized
+        unsafe {
+            addr_of_mut!((*adapter).rmt_buffer).write_bytes(0, 1);
+            addr_of_mut!((*adapter).channel).write(channel);
+            addr_of_mut!((*adapter).encoder).write(encoder);
+            addr_of_mut!((*adapter).count).write(count);
+            addr_of_mut!((*adapter).pin).write(pin);
+            addr_of_mut!((*adapter).output).write(output);
+            addr_of_mut!((*adapter).streaming).write(streaming);
+            addr_of_mut!((*adapter).gapless).write(gapless);
+            addr_of_mut!((*adapter).yield_hook).write(yield_hook);
+            addr_of_mut!((*adapter).done_hook).write(done_hook);
+            addr_of_mut!((*adapter).limiter).write(limiter);
+            addr_of_mut!((*adapter).latch).write(latch);
+            addr_of_mut!((*adapter).interrupt_bound).write(interrupt_bound);
+            #[cfg(feature = "stats")]
+            addr_of_mut!((*adapter).stats).write(stats);
+            addr_of_mut!((*adapter).chip).write(chip);
+
+            slot.assume_init_mut()
+        }
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, with the given electrical configuration
+    /// of the pin.
+    pub fn new_with_output<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        _chip: Chip,
+        output: OutputConfig,
+    ) -> Self
+    where
+        O: OutputPin + Peripheral<P = O> + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        // SAFETY: the copy of the pin is only used to change its pad settings
+        // once it has been connected to the channel
+        let pad = unsafe { pin.clone_unchecked() };
+        let adapter = Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            output.channel_config(),
+            output,
+        );
+        output.apply(pad, <TX as TxChannelInternal<Blocking>>::output_signal());
+
+        adapter
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, letting `adjust` change the
+    /// configuration of the channel first.
+    ///
+    /// This gives access to settings of the RMT driver the adapter does not
+    /// cover. The signal is inverted if `idle_output_level` is set, and the
+    /// pulses are encoded for the tick rate resulting from `clk_divider`,
+    /// which divides `frequency`, the one passed to `Rmt::new`. It should
+    /// divide it evenly. Enabling the carrier or disabling the idle output
+    /// will garble the signal for most LEDs.
+    ///
+    /// ```rust,ignore
+    /// let led = SmartLedsAdapter::new_with_config(
+    ///     rmt.channel0,
+    ///     peripherals.GPIO2,
+    ///     80.MHz(),
+    ///     rmt_buffer,
+    ///     Ws2812b,
+    ///     |config| config.clk_divider = 2,
+    /// );
+    /// ```
+    pub fn new_with_config<C, O, F>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        _chip: Chip,
+        adjust: F,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+        F: FnOnce(&mut TxChannelConfig),
+    {
+        let mut config = channel_config(false);
+        adjust(&mut config);
+
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            config,
+            OutputConfig::new().with_inverted(config.idle_output_level),
+        )
+    }
+
+    /// Configure the channel to drive the pin with the given timing profile,
+    /// channel configuration and electrical configuration of the pin.
+    fn configure<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        timing: Timing,
+        config: TxChannelConfig,
+        output: OutputConfig,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        // SAFETY: the copy of the pin is only used to move the signal to
+        // another pin, once the channel has been configured
+        let pad = unsafe { pin.clone_unchecked() }.into();
         let channel = channel.configure(pin, config).unwrap();
 
-        // Assume the RMT peripheral is set up to use the APB clock
-        let clocks = Clocks::get();
-        let src_clock = clocks.apb_clock.to_MHz();
+        let src_clock = frequency.to_MHz() / config.clk_divider.max(1) as u32;
 
         Self {
             channel: Some(channel),
             rmt_buffer,
-            pulses: (
-                PulseCode::new (
-                    true,
-                    ((SK68XX_T0H_NS * src_clock) / 1000) as u16,
-                    false,
-                    ((SK68XX_T0L_NS * src_clock) / 1000) as u16,
-                ),
-                PulseCode::new (
-                    true,
-                    ((SK68XX_T1H_NS * src_clock) / 1000) as u16,
-                    false,
-                    ((SK68XX_T1L_NS * src_clock) / 1000) as u16,
-                ),
-            ),
+            encoder: PulseEncoder::new(timing, src_clock, output.inverted),
+            count: None,
+            pin: pad,
+            output,
+            chip: PhantomData,
+            streaming: false,
+            gapless: false,
+            yield_hook: None,
+            done_hook: None,
+            limiter: FrameLimiter::default(),
+            latch: LatchGuard::new(),
+            interrupt_bound: false,
+            #[cfg(feature = "stats")]
+            stats: StatsRecorder::default(),
         }
     }
 
-    fn convert_rgb_to_pulse(
-        value: RGB8,
-        mut_iter: &mut IterMut<u32>,
-        pulses: (u32, u32),
-    ) -> Result<(), LedAdapterError> {
-        Self::convert_rgb_channel_to_pulses(value.g, mut_iter, pulses)?;
-        Self::convert_rgb_channel_to_pulses(value.r, mut_iter, pulses)?;
-        Self::convert_rgb_channel_to_pulses(value.b, mut_iter, pulses)?;
+    /// Send the signal on another pin, and return the pin it was sent on.
+    ///
+    /// This lets one channel drive several connectors in turn, for example a
+    /// front and a back strip selected by the user. A frame started with
+    /// [`SmartLedsAdapter::start_write`] is finished first. The signal is
+    /// rerouted through the GPIO matrix, and the new pin gets the electrical
+    /// configuration the adapter was created with. The old pin is handed back
+    /// to the GPIO, holding the line at its idle level, so the LEDs on it keep
+    /// their colors, and can be passed to this again to switch back.
+    ///
+    /// ```rust,ignore
+    /// let front = led.swap_pin(peripherals.GPIO3)?;
+    /// led.write(back_frame)?;
+    /// let back = led.swap_pin(front)?;
+    /// ```
+    pub fn swap_pin<O>(&mut self, pin: O) -> Result<AnyPin, LedAdapterError>
+    where
+        O: OutputPin,
+    {
+        self.flush()?;
 
-        Ok(())
+        let mut pin = pin.into();
+        self.output.reroute(
+            &mut self.pin,
+            &mut pin,
+            <TX as TxChannelInternal<Blocking>>::output_signal(),
+        );
+
+        Ok(mem::replace(&mut self.pin, pin))
     }
 
-    fn convert_rgb_channel_to_pulses(
-        channel_value: u8,
-        mut_iter: &mut IterMut<u32>,
-        pulses: (u32, u32),
-    ) -> Result<(), LedAdapterError> {
-        for position in [128, 64, 32, 16, 8, 4, 2, 1] {
-            *mut_iter.next().ok_or(LedAdapterError::BufferSizeExceeded)? =
-                match channel_value & position {
-                    0 => pulses.0,
-                    _ => pulses.1,
-                }
-        }
+    /// Change the timing profile, for example to drive a different type of
+    /// strip from the same channel.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.encoder.set_timing(timing);
+    }
 
-        Ok(())
+    /// Set corrections of the pulses for this board, which are added to the
+    /// pulses of the timing profile.
+    ///
+    /// The offsets are kept when the timing profile is changed.
+    pub fn set_timing_offsets(&mut self, offsets: TimingOffsets) {
+        self.encoder.set_timing_offsets(offsets);
     }
-}
 
-impl<TX, const BUFFER_SIZE: usize> SmartLedsWrite for SmartLedsAdapter<TX, BUFFER_SIZE>
-where
-    TX: TxChannel,
-{
-    type Error = LedAdapterError;
-    type Color = RGB8;
+    /// Set how long the line is held low after every frame, in microseconds.
+    ///
+    /// This overrides the reset duration of the timing profile. Some clones of
+    /// the WS2812 need 280µs or more to latch the new colors.
+    pub fn set_reset_us(&mut self, reset_us: u32) {
+        self.encoder.set_reset_us(reset_us);
+    }
 
-    /// Convert all RGB8 items of the iterator to the RMT format and
-    /// add them to internal buffer, then start a singular RMT operation
-    /// based on that buffer.
-    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
-    where
-        T: IntoIterator<Item = I>,
-        I: Into<Self::Color>,
-    {
-        // We always start from the beginning of the buffer
-        let mut seq_iter = self.rmt_buffer.iter_mut();
+    /// Set the color correction applied to every LED while encoding.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.encoder.set_color_correction(correction);
+    }
 
-        // Add all converted iterator items to the buffer.
-        // This will result in an `BufferSizeExceeded` error in case
-        // the iterator provides more elements than the buffer can take.
-        for item in iterator {
-            Self::convert_rgb_to_pulse(item.into(), &mut seq_iter, self.pulses)?;
-        }
+    /// Set the gamma curve applied to every LED while encoding, before the
+    /// color correction.
+    ///
+    /// This replaces per-channel curves set with `set_channel_gamma`.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.encoder.set_gamma(gamma);
+    }
 
-        // Finally, add an end element.
-        *seq_iter.next().ok_or(LedAdapterError::BufferSizeExceeded)? = 0;
+    /// Set a gamma curve for every channel, applied to every LED while
+    /// encoding in place of the curve set with `set_gamma`.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.encoder.set_channel_gamma(gamma);
+    }
 
-        // Perform the actual RMT operation. We use the u32 values here right away.
-        let channel = self.channel.take().unwrap();
-        match channel.transmit(&self.rmt_buffer)?.wait() {
-            Ok(chan) => {
-                self.channel = Some(chan);
-                Ok(())
-            }
-            Err((e, chan)) => {
-                self.channel = Some(chan);
+    /// Set how the white channel of RGBW LEDs is computed while encoding,
+    /// after the color correction.
+    ///
+    /// This has no effect on LEDs without a white channel.
+    pub fn set_white_extraction(&mut self, extraction: Option<WhiteExtraction>) {
+        self.encoder.set_white_extraction(extraction);
+    }
+
+    /// Set the calibration of the strip, which scales every channel after all
+    /// other adjustments.
+    ///
+    /// This matches strips from different batches, so they show the same
+    /// colors.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.encoder.set_calibration(calibration);
+    }
+
+    /// Override the order in which the chip expects the color channels, or
+    /// restore it with `None`.
+    pub fn set_color_order(&mut self, order: Option<ColorOrder>) {
+        self.encoder.set_color_order(order);
+    }
+
+    /// The encoder turning colors into the pulse codes of this adapter
+    pub fn encoder(&self) -> &PulseEncoder<Chip> {
+        &self.encoder
+    }
+
+    /// Declare the number of LEDs on the strip, which every write is checked
+    /// against, or remove it with `None`.
+    ///
+    /// Fails with [`LedAdapterError::BufferSizeMismatch`] unless the buffer is
+    /// sized for exactly that many LEDs.
+    pub fn set_led_count(&mut self, count: Option<LedCount>) -> Result<(), LedAdapterError> {
+        if let Some(count) = count {
+            let required = buffer_size_for::<Chip>(count.leds());
+            if required != BUFFER_SIZE {
+                return Err(LedAdapterError::BufferSizeMismatch {
+                    required,
+                    available: BUFFER_SIZE,
+                });
+            }
+        }
+
+        self.count = count;
+        Ok(())
+    }
+
+    /// Apply the settings of a strip: its number of LEDs, the timing profile
+    /// and color order of its chip, and its color correction.
+    ///
+    /// This replaces the reset duration set with
+    /// [`set_reset_us`](Self::set_reset_us). The brightness is not applied,
+    /// see [`StripConfig::pipeline`](config::StripConfig::pipeline). Fails
+    /// with [`LedAdapterError::ChannelMismatch`] if the chip of the config has
+    /// a different number of channels, and with
+    /// [`LedAdapterError::BufferSizeMismatch`] unless the buffer is sized for
+    /// exactly the number of LEDs. The adapter is left unchanged on failure.
+    pub fn set_strip_config(&mut self, config: &StripConfig) -> Result<(), LedAdapterError> {
+        if config.chip.channels() != Chip::CHANNELS {
+            return Err(LedAdapterError::ChannelMismatch {
+                expected: Chip::CHANNELS,
+                actual: config.chip.channels(),
+            });
+        }
+        self.set_led_count(Some(config.led_count()))?;
+
+        self.set_timing(config.chip.timing());
+        self.set_color_order(Some(config.color_order()));
+        self.set_color_correction(config.correction);
+
+        Ok(())
+    }
+
+    /// Limit the rate at which frames are sent, where 0 disables limiting.
+    ///
+    /// Writes wait until the interval between frames has passed since the
+    /// previous frame was started. See [`FrameLimiter`].
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.limiter.set_target_fps(fps);
+    }
+
+    /// Statistics of the frames sent in the last complete window.
+    ///
+    /// See [`stats`](crate::stats) for what is measured.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.stats.stats()
+    }
+
+    /// Set the length of the window statistics are collected over, in
+    /// milliseconds.
+    #[cfg(feature = "stats")]
+    pub fn set_stats_window(&mut self, window_ms: u32) {
+        self.stats.set_window(window_ms);
+    }
+
+    /// Problems with the signal since the adapter was created, or the
+    /// counters were reset.
+    ///
+    /// See [`stats`](crate::stats) for what is counted.
+    #[cfg(feature = "stats")]
+    pub fn signal_health(&self) -> SignalHealth {
+        self.stats.health()
+    }
+
+    /// Reset the counters of problems with the signal.
+    #[cfg(feature = "stats")]
+    pub fn reset_signal_health(&mut self) {
+        self.stats.reset_health();
+    }
+
+    /// Send frames which don't fit into the buffer in several transmissions.
+    ///
+    /// In streaming mode, the buffer only needs to hold a part of the frame:
+    /// whenever it is full, its contents are transmitted and encoding continues
+    /// at its start. This makes it possible to drive long strips with a buffer
+    /// of a few LEDs instead of one sized for the whole strip.
+    ///
+    /// The RMT driver does not allow refilling the buffer during a
+    /// transmission, so the line is idle while the next part of the frame is
+    /// encoded and its transmission started. The LEDs see this as a longer low
+    /// pulse of the last bit, and will latch early if it exceeds their reset
+    /// time. Keep the parts large enough that this happens rarely, and avoid
+    /// interrupts which could stretch the gap, or use a chip with a long reset
+    /// time.
+    pub fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    /// Guarantee that no gap longer than the low pulse of a bit occurs within
+    /// a frame.
+    ///
+    /// Gaps come from two sources: the idle line between the parts of a frame
+    /// in streaming mode, and refills of the memory of the channel which are
+    /// delayed by interrupts. In gapless mode, the whole frame is encoded
+    /// before its transmission starts, and frames which would have to be sent
+    /// in parts are rejected with [`LedAdapterError::GapUnavoidable`] instead,
+    /// even if streaming mode is enabled. Frames longer than the memory of the
+    /// channel are sent with interrupts disabled, so nothing can delay the
+    /// refills.
+    ///
+    /// Interrupts stay disabled for the duration of such a frame, about 30µs
+    /// per LED, which other drivers have to tolerate.
+    ///
+    /// [`SmartLedsAdapterAsync`] only sends frames which fit into the memory of
+    /// the channel, so its frames never have gaps.
+    pub fn set_gapless(&mut self, gapless: bool) {
+        self.gapless = gapless;
+    }
+
+    /// Set a function which is called regularly during long writes, for
+    /// example to feed a watchdog, or remove it with `None`.
+    ///
+    /// The RMT driver refills the memory of the channel without offering a
+    /// hook, so the function is called between the parts of a frame in
+    /// streaming mode, see [`SmartLedsAdapter::set_streaming`], and while
+    /// waiting for a frame started with [`SmartLedsAdapter::start_write`].
+    /// Between two parts, the line is idle while the function runs, so it has
+    /// to return well within the reset time of the LEDs. Smaller buffers call
+    /// it more often.
+    pub fn set_yield_hook(&mut self, hook: Option<fn()>) {
+        self.yield_hook = hook;
+    }
+
+    /// Set a function which is called when a frame has been sent, including
+    /// the reset time, or remove it with `None`.
+    ///
+    /// This lets work which needs a quiet bus, such as sampling a sensor, be
+    /// chained to the frames. The function is called before a write returns,
+    /// and for a frame started with [`SmartLedsAdapter::start_write`], by the
+    /// call to [`SmartLedsAdapter::poll_done`] or
+    /// [`SmartLedsAdapter::flush`] which finds it sent. It is not called for
+    /// frames which fail.
+    pub fn set_done_hook(&mut self, hook: Option<fn()>) {
+        self.done_hook = hook;
+    }
+
+    /// Number of LEDs which fit into the buffer along with the end delimiter.
+    ///
+    /// Longer frames result in a [`LedAdapterError::BufferSizeExceeded`]
+    /// error, unless streaming mode is enabled.
+    pub fn capacity_leds(&self) -> usize {
+        BUFFER_SIZE.saturating_sub(1) / Chip::BITS_PER_LED
+    }
+
+    /// Write a frame of colors from a slice.
+    ///
+    /// This does the same as [`SmartLedsWrite::write`], but checks the size of
+    /// the frame up front and encodes straight from the slice, which is
+    /// considerably faster for large frames.
+    pub fn write_slice(&mut self, colors: &[Chip::Color]) -> Result<(), LedAdapterError> {
+        self.write_items(colors, 1, PulseEncoder::encode_slice)
+    }
+
+    /// Write a frame of colors packed into words, such as `0x00RRGGBB` for
+    /// RGB LEDs or `0xWWRRGGBB` for RGBW LEDs.
+    ///
+    /// Like [`SmartLedsAdapter::write_slice`], this encodes straight from the
+    /// slice, without converting the frame first.
+    pub fn write_packed(&mut self, packed: &[u32]) -> Result<(), LedAdapterError>
+    where
+        Chip::Color: PackedColor,
+    {
+        self.write_items(packed, 1, PulseEncoder::encode_packed)
+    }
+
+    /// Write a frame of raw bytes, which are already in the order the chip
+    /// expects, with `Chip::CHANNELS` bytes per LED.
+    ///
+    /// The bytes are sent as they are, without the gamma curve, color
+    /// correction or any other adjustment, which suits frames prepared by a
+    /// host, such as those of an [`AdalightParser`](adalight::AdalightParser).
+    /// Bytes of an incomplete LED at the end of the frame are ignored.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), LedAdapterError> {
+        self.write_items(bytes, Chip::CHANNELS, PulseEncoder::encode_raw)
+    }
+
+    /// Write a frame from a slice of `per_led` items per LED, which `encode`
+    /// encodes into the buffer.
+    fn write_items<T>(
+        &mut self,
+        colors: &[T],
+        per_led: usize,
+        encode: impl Fn(&PulseEncoder<Chip>, &mut [u32], &[T]),
+    ) -> Result<(), LedAdapterError> {
+        let leds = match self.count {
+            Some(count) => count.check(colors.len() / per_led)?,
+            None => colors.len() / per_led,
+        };
+        let colors = &colors[..leds * per_led];
+
+        self.flush()?;
+        self.limiter.wait();
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let capacity = self.capacity_leds();
+        if leds > capacity && self.streaming && self.gapless {
+            return Err(LedAdapterError::gap_unavoidable::<Chip>(leds, BUFFER_SIZE));
+        }
+        if leds > capacity && !(self.streaming && capacity > 0) {
+            return Err(LedAdapterError::buffer_size_exceeded::<Chip>(
+                capacity,
+                leds,
+                BUFFER_SIZE,
+            ));
+        }
+
+        if colors.is_empty() {
+            *self
+                .rmt_buffer
+                .first_mut()
+                .ok_or(LedAdapterError::buffer_size_exceeded::<Chip>(
+                    0,
+                    0,
+                    BUFFER_SIZE,
+                ))? = self.encoder.end();
+            self.transmit(1)?;
+            self.frame_done();
+            return Ok(());
+        }
+
+        let mut chunks = colors.chunks(capacity * per_led).peekable();
+        while let Some(chunk) = chunks.next() {
+            encode(&self.encoder, &mut self.rmt_buffer, chunk);
+
+            // Only the last part of the frame ends with the reset pulse
+            let len = chunk.len() / per_led * Chip::BITS_PER_LED;
+            self.rmt_buffer[len] = match chunks.peek() {
+                Some(_) => 0,
+                None => self.encoder.end(),
+            };
+            self.transmit(len + 1)?;
+        }
+        self.frame_done();
+
+        #[cfg(feature = "stats")]
+        self.stats.finish_frame_since(start);
+
+        Ok(())
+    }
+
+    /// Start sending a frame of a single LED, or two RGB LEDs on the ESP32
+    /// and ESP32-S2, without waiting for the transmission to finish.
+    ///
+    /// This lets a main loop keep working while the frame is sent. Call
+    /// [`SmartLedsAdapter::poll_done`] to find out when it has been sent;
+    /// other writes wait for it to finish first.
+    ///
+    /// The RMT driver only refills the memory of the channel while waiting for
+    /// a transmission, so the encoded frame must fit into that memory, like
+    /// with [`SmartLedsAdapterAsync`]. A channel has a single block of 48
+    /// pulse codes (64 on the ESP32 and ESP32-S2). Larger frames result in a
+    /// [`LedAdapterError::BufferSizeExceeded`] error, and need to be sent with
+    /// [`SmartLedsWrite::write`].
+    pub fn start_write<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        self.flush()?;
+        self.limiter.wait();
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let available = BUFFER_SIZE.min(CHANNEL_RAM_SIZE);
+        let mut iterator = Counted::new(iterator.into_iter(), self.count);
+        let len = self
+            .encoder
+            .encode(&mut self.rmt_buffer[..available], &mut iterator)?;
+        iterator.finish()?;
+
+        // The whole frame is copied into channel memory when the transmission
+        // starts, so the transaction is not needed to finish it. The channel is
+        // handed back by `poll_done`.
+        let channel = self.channel.take().unwrap();
+        match channel.transmit(&self.rmt_buffer[..len]) {
+            Ok(transaction) => mem::forget(transaction),
+            Err(e) => {
+                // The channel is consumed by the failed transmission, so
+                // recreate it to keep the adapter usable
+                self.channel = Some(<TX as TxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        }
+
+        #[cfg(feature = "stats")]
+        self.stats.finish_frame_since(start);
+
+        Ok(())
+    }
+
+    /// Check whether the frame started with [`SmartLedsAdapter::start_write`]
+    /// has been sent, including the reset time.
+    ///
+    /// Returns `true` if no frame is in flight.
+    pub fn poll_done(&mut self) -> Result<bool, LedAdapterError> {
+        if self.channel.is_some() {
+            return Ok(true);
+        }
+
+        let error = <TX as TxChannelInternal<Blocking>>::is_error();
+        if !error && !<TX as TxChannelInternal<Blocking>>::is_done() {
+            return Ok(false);
+        }
+
+        <TX as TxChannelInternal<Blocking>>::clear_interrupts();
+        self.channel = Some(<TX as TxChannelInternal<Blocking>>::new());
+
+        if error {
+            self.latch.finish(self.encoder.reset_us() as u64);
+            #[cfg(feature = "stats")]
+            self.stats.count_error(&RmtError::TransmissionError);
+            return Err(LedAdapterError::TransmissionError(
+                RmtError::TransmissionError,
+            ));
+        }
+        self.frame_done();
+
+        Ok(true)
+    }
+
+    /// Wait until the LEDs are ready for the next frame.
+    ///
+    /// Writes block until their transmission, including the reset time, has
+    /// finished, so this only waits for a frame started with
+    /// [`SmartLedsAdapter::start_write`], and for the part of the reset time
+    /// which the end of the last frame could not hold, see
+    /// [`LatchGuard`]. Every write calls this first.
+    pub fn flush(&mut self) -> Result<(), LedAdapterError> {
+        self.encoder.check_resolution()?;
+        while !self.poll_done()? {
+            self.yield_now();
+        }
+        self.latch.wait();
+
+        Ok(())
+    }
+
+    /// Stop any transmission and make the channel usable again.
+    ///
+    /// If the channel hangs, for example because a frame started with
+    /// [`SmartLedsAdapter::start_write`] never finishes, every write waits for
+    /// it forever. Since the channel has been consumed by the adapter, it
+    /// cannot be reconfigured; instead, this stops the transmission, clears the
+    /// state of the channel and holds the line low for the reset time, so the
+    /// LEDs latch whatever they have received.
+    pub fn recover(&mut self) -> Result<(), LedAdapterError> {
+        <TX as TxChannelInternal<Blocking>>::stop();
+        <TX as TxChannelInternal<Blocking>>::clear_interrupts();
+        if self.channel.is_none() {
+            self.channel = Some(<TX as TxChannelInternal<Blocking>>::new());
+        }
+
+        *self
+            .rmt_buffer
+            .first_mut()
+            .ok_or(LedAdapterError::buffer_size_exceeded::<Chip>(
+                0,
+                0,
+                BUFFER_SIZE,
+            ))? = self.encoder.end();
+        self.transmit(1)?;
+        self.latch.finish(self.encoder.latch_gap_us());
+
+        Ok(())
+    }
+
+    /// Call the yield hook, if any.
+    fn yield_now(&self) {
+        if let Some(hook) = self.yield_hook {
+            hook();
+        }
+    }
+
+    /// Mark the end of a frame for the latch time, and call the done hook, if
+    /// any.
+    fn frame_done(&mut self) {
+        self.latch.finish(self.encoder.latch_gap_us());
+        if let Some(hook) = self.done_hook {
+            hook();
+        }
+    }
+
+    /// Transmit the first `len` pulse codes of the buffer and wait for the
+    /// transmission to finish.
+    fn transmit(&mut self, len: usize) -> Result<(), LedAdapterError> {
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        trace!("transmitting {} pulse codes", len);
+
+        // Perform the actual RMT operation. We use the u32 values here right away.
+        // In gapless mode, frames which need refills are sent with interrupts
+        // disabled so the refills are never delayed.
+        let channel = self.channel.take().unwrap();
+        let buffer = &self.rmt_buffer[..len];
+        let result = match self.gapless && len > CHANNEL_RAM_SIZE {
+            true => critical_section::with(|_| channel.transmit(buffer).map(|t| t.wait())),
+            false => channel.transmit(buffer).map(|t| t.wait()),
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                // The channel is consumed by the failed transmission
+                self.channel = Some(<TX as TxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        };
+        let result = match result {
+            Ok(chan) => {
+                self.channel = Some(chan);
+                Ok(())
+            }
+            Err((e, chan)) => {
+                warn!("transmission failed: {}", e);
+                self.channel = Some(chan);
+                // The frame was cut off, so the line has not been held for
+                // the reset time
+                self.latch.finish(self.encoder.reset_us() as u64);
                 Err(LedAdapterError::TransmissionError(e))
             }
+        };
+
+        trace!("transmission done");
+
+        #[cfg(feature = "stats")]
+        {
+            let transmit_us = elapsed_us(start);
+            self.stats.add_transmit(transmit_us);
+
+            // Transmissions which fit into the memory of the channel are never
+            // refilled, so only longer ones can be late
+            if let Err(LedAdapterError::TransmissionError(e)) = &result {
+                self.stats.count_error(e);
+            } else if len > CHANNEL_RAM_SIZE
+                && transmit_us > self.encoder.max_duration_us(len) + LATE_REFILL_SLACK_US
+            {
+                self.stats.count_late_refill();
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsWrite for SmartLedsAdapter<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Convert all color items of the iterator to the RMT format and
+    /// add them to internal buffer, then start a singular RMT operation
+    /// based on that buffer.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.flush()?;
+        self.limiter.wait();
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let bits = Chip::BITS_PER_LED;
+
+        // We always start from the beginning of the buffer
+        let mut position = 0;
+        let mut iterator = Counted::new(iterator.into_iter(), self.count);
+        let mut overflow = None;
+        let mut leds = 0;
+
+        // Add all converted iterator items to the buffer.
+        // This will result in an `BufferSizeExceeded` error in case
+        // the iterator provides more elements than the buffer can take.
+        for (index, item) in iterator.by_ref().enumerate() {
+            // In streaming mode, send what has been encoded so far if there is
+            // no room for another LED and the end delimiter.
+            if self.streaming && !self.gapless && position > 0 && position + bits >= BUFFER_SIZE {
+                trace!("buffer full after {} LEDs, sending part of the frame", leds);
+                self.rmt_buffer[position] = 0;
+                self.transmit(position + 1)?;
+                self.yield_now();
+                position = 0;
+            }
+
+            if position + bits >= BUFFER_SIZE {
+                overflow = Some(index);
+                break;
+            }
+
+            self.encoder
+                .encode_led(item.into(), &mut self.rmt_buffer[position..position + bits]);
+            position += bits;
+            leds += 1;
+        }
+
+        if let Some(led) = overflow {
+            // Count the remaining LEDs to report the size the frame needs
+            let leds = led + 1 + iterator.count();
+            return Err(match self.streaming && self.gapless {
+                true => LedAdapterError::gap_unavoidable::<Chip>(leds, BUFFER_SIZE),
+                false => LedAdapterError::buffer_size_exceeded::<Chip>(led, leds, BUFFER_SIZE),
+            });
+        }
+
+        iterator.finish()?;
+        trace!("encoded {} LEDs", leds);
+
+        // Finally, add an end element, which also holds the line low for the
+        // reset duration.
+        *self.rmt_buffer.get_mut(position).ok_or(
+            LedAdapterError::buffer_size_exceeded::<Chip>(leds, leds, BUFFER_SIZE),
+        )? = self.encoder.end();
+
+        self.transmit(position + 1)?;
+        self.frame_done();
+
+        #[cfg(feature = "stats")]
+        self.stats.finish_frame_since(start);
+
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "esp-hal", feature = "dyn-write"))]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapter<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip,
+{
+    /// Write a frame from a trait object.
+    ///
+    /// [`SmartLedsWrite::write`] is generic over the iterator, so every type of
+    /// iterator written to the adapter gets its own copy of the encoder. Frames
+    /// written through this method share a single copy, at the cost of a
+    /// dynamic call per LED.
+    pub fn write_dyn(
+        &mut self,
+        iterator: &mut dyn Iterator<Item = Chip::Color>,
+    ) -> Result<(), LedAdapterError> {
+        self.write(iterator)
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapter<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip<Color = RGB8>,
+{
+    /// Write a frame of HSV colors.
+    ///
+    /// The colors are converted to RGB while encoding, without an intermediate
+    /// frame. Gamma and color correction are applied as with
+    /// [`SmartLedsWrite::write`].
+    pub fn write_hsv<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Hsv>,
+    {
+        self.write(iterator.into_iter().map(|item| hsv2rgb(item.into())))
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapter<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannel,
+    Chip: LedChip<Color = RGBW<u8>>,
+{
+    /// Write a frame of RGB colors to RGBW LEDs.
+    ///
+    /// The colors are sent with the white channel off, unless a
+    /// [`WhiteExtraction`] is set, which computes it while encoding.
+    pub fn write_rgb<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        self.write(iterator.into_iter().map(|item| rgbw(item.into())))
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<const CHANNEL: u8, const BUFFER_SIZE: usize, Chip>
+    SmartLedsAdapter<Channel<Blocking, CHANNEL>, BUFFER_SIZE, Chip>
+where
+    Channel<Blocking, CHANNEL>: TxChannel,
+    Channel<Async, CHANNEL>: TxChannelAsync,
+    Chip: LedChip,
+{
+    /// Turn the adapter into one for async mode on the same channel, keeping
+    /// its buffer and settings.
+    ///
+    /// This lets an adapter which was created early in boot be moved into an
+    /// Embassy task later. A frame started with
+    /// [`SmartLedsAdapter::start_write`] is waited for first, and an error of
+    /// its transmission is discarded. Streaming mode, gapless mode and the
+    /// yield hook have no counterpart in async mode, and are dropped.
+    ///
+    /// Async transmissions are woken by the interrupt handler which
+    /// `Rmt::into_async` binds, and which cannot be bound afterwards, so only
+    /// adapters on a peripheral set up in async mode can be converted. Start
+    /// with an async adapter, and convert it with
+    /// [`SmartLedsAdapterAsync::into_blocking`] for the blocking part:
+    ///
+    /// ```rust,ignore
+    /// let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap().into_async();
+    /// let led = SmartLedsAdapterAsync::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), buffer);
+    ///
+    /// let mut led = led.into_blocking();
+    /// led.write(boot_animation)?;
+    ///
+    /// let Ok(mut led) = led.into_async() else {
+    ///     unreachable!("created in async mode")
+    /// };
+    /// ```
+    ///
+    /// The adapter is given back unchanged if it was created on a peripheral
+    /// in blocking mode, where async writes would never finish.
+    // The error is the adapter itself, so it is not lost
+    #[allow(clippy::result_large_err)]
+    pub fn into_async(
+        mut self,
+    ) -> Result<SmartLedsAdapterAsync<Channel<Async, CHANNEL>, BUFFER_SIZE, Chip>, Self> {
+        if !self.interrupt_bound {
+            return Err(self);
+        }
+
+        while let Ok(false) = self.poll_done() {}
+
+        Ok(SmartLedsAdapterAsync {
+            channel: <Channel<Async, CHANNEL> as TxChannelInternal<Async>>::new(),
+            rmt_buffers: [[0; CHANNEL_RAM_SIZE]; 2],
+            lengths: [0; 2],
+            front: 0,
+            prepared: false,
+            aborted: false,
+            encoder: self.encoder,
+            count: self.count,
+            pin: self.pin,
+            output: self.output,
+            done_hook: self.done_hook,
+            #[cfg(feature = "embassy-time")]
+            limiter: self.limiter,
+            latch: self.latch,
+            #[cfg(feature = "stats")]
+            stats: self.stats,
+            chip: PhantomData,
+        })
+    }
+}
+
+/// Adapter taking an RMT channel in async mode and a specific pin and
+/// providing RGB LED interaction functionality using the `smart-leds` crate
+///
+/// In async mode, the RMT driver sends a frame from the memory of the channel
+/// without refilling it, so the encoded frame must fit into that memory. A
+/// channel has a single block of 48 pulse codes (64 on the ESP32 and
+/// ESP32-S2), so the adapter drives one RGB or RGBW LED, or two RGB LEDs on
+/// the ESP32 and ESP32-S2, regardless of `BUFFER_SIZE`.
+///
+/// The adapter keeps two pulse buffers the size of that block, so the next
+/// frame can be encoded with [`SmartLedsAdapterAsync::prepare`] while the
+/// previous one is still being sent, and then be sent with
+/// [`SmartLedsAdapterAsync::commit`].
+///
+/// Like [`SmartLedsAdapter`], this adapter owns the channel and its buffers,
+/// so it can be moved into an Embassy task.
+///
+/// All writes are cancellation safe: if a write future is dropped before the
+/// frame has been sent, for example in a `select`, the transmission is stopped
+/// and the channel can be used right away. See
+/// [`SmartLedsAdapterAsync::flush`] for what happens to the LEDs.
+#[cfg(feature = "esp-hal")]
+pub struct SmartLedsAdapterAsync<TX, const BUFFER_SIZE: usize, Chip = Sk68xx>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip,
+{
+    channel: TX,
+    // Only the first `AVAILABLE` pulse codes are used
+    rmt_buffers: [[u32; CHANNEL_RAM_SIZE]; 2],
+    lengths: [usize; 2],
+    front: usize,
+    prepared: bool,
+    aborted: bool,
+    encoder: PulseEncoder<Chip>,
+    count: Option<LedCount>,
+    // Pin the signal is sent on, and how it is driven
+    pin: AnyPin,
+    output: OutputConfig,
+    done_hook: Option<fn()>,
+    #[cfg(feature = "embassy-time")]
+    limiter: FrameLimiter,
+    latch: LatchGuard,
+    #[cfg(feature = "stats")]
+    stats: StatsRecorder,
+    chip: PhantomData<Chip>,
+}
+
+#[cfg(feature = "esp-hal")]
+impl<'d, TX, const BUFFER_SIZE: usize> SmartLedsAdapterAsync<TX, BUFFER_SIZE>
+where
+    TX: TxChannelAsync,
+{
+    /// Create a new adapter object that drives the pin using the RMT channel.
+    pub fn new<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::new_with_timing(channel, pin, frequency, rmt_buffer, Timing::default())
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// with internal buffers of `BUFFER_SIZE` pulse codes.
+    pub fn new_owned<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::new(channel, pin, frequency, [0; BUFFER_SIZE])
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// using the given timing profile.
+    pub fn new_with_timing<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        timing: Timing,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            timing,
+            channel_config(false),
+            OutputConfig::new(),
+        )
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<'d, TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip,
+{
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip.
+    ///
+    /// ```rust,ignore
+    /// let led = SmartLedsAdapterAsync::new_with_chip(
+    ///     rmt.channel0,
+    ///     peripherals.GPIO2,
+    ///     80.MHz(),
+    ///     rmt_buffer,
+    ///     Ws2812b,
+    /// );
+    /// ```
+    pub fn new_with_chip<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        _chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            channel_config(false),
+            OutputConfig::new(),
+        )
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, with an internal buffer of `BUFFER_SIZE`
+    /// pulse codes.
+    pub fn new_owned_with_chip<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::new_with_chip(channel, pin, frequency, [0; BUFFER_SIZE], chip)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for `LEDS` LEDs of the given type, with an internal buffer sized for
+    /// exactly that many LEDs.
+    ///
+    /// `BUFFER_SIZE` is checked against the number of LEDs at compile time, so
+    /// the two can never disagree. [`smart_leds_adapter_async!`] fills it in:
+    ///
+    /// ```rust,ignore
+    /// let led = smart_leds_adapter_async!(rmt.channel0, peripherals.GPIO2, 80.MHz(), NUM_LEDS, Ws2812b);
+    /// ```
+    pub fn new_for_leds<const LEDS: usize, C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        chip: Chip,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        const {
+            assert!(
+                BUFFER_SIZE == buffer_size_for::<Chip>(LEDS),
+                "the buffer size does not match the number of LEDs"
+            )
+        };
+
+        Self::new_owned_with_chip(channel, pin, frequency, chip)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for a strip of the given type of LED chip with `count` LEDs.
+    ///
+    /// Fails with [`LedAdapterError::BufferSizeMismatch`] unless the buffer is
+    /// sized for exactly that many LEDs. Every write is then checked against
+    /// the count, see [`LedCount`](count::LedCount).
+    pub fn new_with_led_count<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        chip: Chip,
+        count: LedCount,
+    ) -> Result<Self, LedAdapterError>
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        let mut adapter = Self::new_with_chip(channel, pin, frequency, rmt_buffer, chip);
+        adapter.set_led_count(Some(count))?;
+
+        Ok(adapter)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for a strip with the given settings.
+    ///
+    /// `chip` is the type of chip the adapter is created for, which must have
+    /// as many channels as the chip of the config. See
+    /// [`set_strip_config`](Self::set_strip_config).
+    pub fn new_with_strip_config<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        chip: Chip,
+        config: &StripConfig,
+    ) -> Result<Self, LedAdapterError>
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        let mut adapter = Self::new_with_chip(channel, pin, frequency, rmt_buffer, chip);
+        adapter.set_strip_config(config)?;
+
+        Ok(adapter)
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, with the given electrical configuration
+    /// of the pin.
+    pub fn new_with_output<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        _chip: Chip,
+        output: OutputConfig,
+    ) -> Self
+    where
+        O: OutputPin + Peripheral<P = O> + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        // SAFETY: the copy of the pin is only used to change its pad settings
+        // once it has been connected to the channel
+        let pad = unsafe { pin.clone_unchecked() };
+        let adapter = Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            output.channel_config(),
+            output,
+        );
+        output.apply(pad, <TX as TxChannelInternal<Async>>::output_signal());
+
+        adapter
+    }
+
+    /// Create a new adapter object that drives the pin using the RMT channel,
+    /// for the given type of LED chip, letting `adjust` change the
+    /// configuration of the channel first.
+    ///
+    /// This gives access to settings of the RMT driver the adapter does not
+    /// cover. The signal is inverted if `idle_output_level` is set, and the
+    /// pulses are encoded for the tick rate resulting from `clk_divider`,
+    /// which divides `frequency`, the one passed to `Rmt::new`. It should
+    /// divide it evenly. Enabling the carrier or disabling the idle output
+    /// will garble the signal for most LEDs.
+    ///
+    /// ```rust,ignore
+    /// let led = SmartLedsAdapterAsync::new_with_config(
+    ///     rmt.channel0,
+    ///     peripherals.GPIO2,
+    ///     80.MHz(),
+    ///     rmt_buffer,
+    ///     Ws2812b,
+    ///     |config| config.clk_divider = 2,
+    /// );
+    /// ```
+    pub fn new_with_config<C, O, F>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+        _chip: Chip,
+        adjust: F,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+        F: FnOnce(&mut TxChannelConfig),
+    {
+        let mut config = channel_config(false);
+        adjust(&mut config);
+
+        Self::configure(
+            channel,
+            pin,
+            frequency,
+            rmt_buffer,
+            Chip::TIMING,
+            config,
+            OutputConfig::new().with_inverted(config.idle_output_level),
+        )
+    }
+
+    /// Configure the channel to drive the pin with the given timing profile,
+    /// channel configuration and electrical configuration of the pin.
+    ///
+    /// The buffer only sets `BUFFER_SIZE`: frames are encoded into buffers the
+    /// size of the channel memory.
+    fn configure<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        _rmt_buffer: [u32; BUFFER_SIZE],
+        timing: Timing,
+        config: TxChannelConfig,
+        output: OutputConfig,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        // SAFETY: the copy of the pin is only used to move the signal to
+        // another pin, once the channel has been configured
+        let pad = unsafe { pin.clone_unchecked() }.into();
+        let channel = channel.configure(pin, config).unwrap();
+
+        let src_clock = frequency.to_MHz() / config.clk_divider.max(1) as u32;
+
+        Self {
+            channel,
+            rmt_buffers: [[0; CHANNEL_RAM_SIZE]; 2],
+            lengths: [0; 2],
+            front: 0,
+            prepared: false,
+            aborted: false,
+            encoder: PulseEncoder::new(timing, src_clock, output.inverted),
+            count: None,
+            pin: pad,
+            output,
+            done_hook: None,
+            chip: PhantomData,
+            #[cfg(feature = "embassy-time")]
+            limiter: FrameLimiter::default(),
+            latch: LatchGuard::new(),
+            #[cfg(feature = "stats")]
+            stats: StatsRecorder::default(),
+        }
+    }
+
+    /// Set the priority of the RMT interrupt which refills the channel and
+    /// wakes up pending writes.
+    ///
+    /// `Rmt::into_async` installs the interrupt at the highest priority. If
+    /// other interrupts are more latency sensitive, lower it; if the LEDs
+    /// glitch while other interrupts are busy, keep it above them. The
+    /// interrupt is shared by all channels of the RMT peripheral, so this
+    /// applies to every async channel, and it is enabled on the current core.
+    ///
+    /// `Priority::None` would disable the interrupt and stall all writes, so
+    /// it is rejected with `InvalidInterruptPriority`.
+    pub fn set_interrupt_priority(&mut self, priority: Priority) -> Result<(), interrupt::Error> {
+        if priority == Priority::None {
+            return Err(interrupt::Error::InvalidInterruptPriority);
+        }
+
+        interrupt::enable(Interrupt::RMT, priority)
+    }
+
+    /// Set a function which is called when a frame has been sent, including
+    /// the reset time, or remove it with `None`.
+    ///
+    /// This lets work which needs a quiet bus, such as sampling a sensor, be
+    /// chained to the frames. The function is called by
+    /// [`SmartLedsAdapterAsync::commit`] and
+    /// [`SmartLedsAdapterAsync::commit_and_prepare`] as soon as they are woken
+    /// by the end of the transmission, before they return. It is not called for frames which
+    /// fail or are cancelled.
+    pub fn set_done_hook(&mut self, hook: Option<fn()>) {
+        self.done_hook = hook;
+    }
+
+    /// Send the signal on another pin, and return the pin it was sent on.
+    ///
+    /// This waits for the LEDs like [`SmartLedsAdapterAsync::flush`], and
+    /// otherwise works like [`SmartLedsAdapter::swap_pin`]. A prepared frame
+    /// is kept, and sent on the new pin when it is committed.
+    pub async fn swap_pin<O>(&mut self, pin: O) -> Result<AnyPin, LedAdapterError>
+    where
+        O: OutputPin,
+    {
+        self.flush().await?;
+
+        let mut pin = pin.into();
+        self.output.reroute(
+            &mut self.pin,
+            &mut pin,
+            <TX as TxChannelInternal<Async>>::output_signal(),
+        );
+
+        Ok(mem::replace(&mut self.pin, pin))
+    }
+
+    /// Change the timing profile, for example to drive a different type of
+    /// strip from the same channel.
+    ///
+    /// Frames which have already been prepared are sent with the timings they
+    /// were prepared with.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.encoder.set_timing(timing);
+    }
+
+    /// Set corrections of the pulses for this board, which are added to the
+    /// pulses of the timing profile.
+    ///
+    /// The offsets are kept when the timing profile is changed.
+    pub fn set_timing_offsets(&mut self, offsets: TimingOffsets) {
+        self.encoder.set_timing_offsets(offsets);
+    }
+
+    /// Set how long the line is held low after every frame, in microseconds.
+    ///
+    /// This overrides the reset duration of the timing profile.
+    pub fn set_reset_us(&mut self, reset_us: u32) {
+        self.encoder.set_reset_us(reset_us);
+    }
+
+    /// Set the color correction applied to every LED while encoding.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.encoder.set_color_correction(correction);
+    }
+
+    /// Set the gamma curve applied to every LED while encoding, before the
+    /// color correction.
+    ///
+    /// This replaces per-channel curves set with `set_channel_gamma`.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.encoder.set_gamma(gamma);
+    }
+
+    /// Set a gamma curve for every channel, applied to every LED while
+    /// encoding in place of the curve set with `set_gamma`.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.encoder.set_channel_gamma(gamma);
+    }
+
+    /// Set how the white channel of RGBW LEDs is computed while encoding,
+    /// after the color correction.
+    ///
+    /// This has no effect on LEDs without a white channel.
+    pub fn set_white_extraction(&mut self, extraction: Option<WhiteExtraction>) {
+        self.encoder.set_white_extraction(extraction);
+    }
+
+    /// Set the calibration of the strip, which scales every channel after all
+    /// other adjustments.
+    ///
+    /// This matches strips from different batches, so they show the same
+    /// colors.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.encoder.set_calibration(calibration);
+    }
+
+    /// Override the order in which the chip expects the color channels, or
+    /// restore it with `None`.
+    pub fn set_color_order(&mut self, order: Option<ColorOrder>) {
+        self.encoder.set_color_order(order);
+    }
+
+    /// The encoder turning colors into the pulse codes of this adapter
+    pub fn encoder(&self) -> &PulseEncoder<Chip> {
+        &self.encoder
+    }
+
+    /// Declare the number of LEDs on the strip, which every write is checked
+    /// against, or remove it with `None`.
+    ///
+    /// Fails with [`LedAdapterError::BufferSizeMismatch`] unless the buffer is
+    /// sized for exactly that many LEDs.
+    pub fn set_led_count(&mut self, count: Option<LedCount>) -> Result<(), LedAdapterError> {
+        if let Some(count) = count {
+            let required = buffer_size_for::<Chip>(count.leds());
+            if required != BUFFER_SIZE {
+                return Err(LedAdapterError::BufferSizeMismatch {
+                    required,
+                    available: BUFFER_SIZE,
+                });
+            }
+        }
+
+        self.count = count;
+        Ok(())
+    }
+
+    /// Apply the settings of a strip: its number of LEDs, the timing profile
+    /// and color order of its chip, and its color correction.
+    ///
+    /// This replaces the reset duration set with
+    /// [`set_reset_us`](Self::set_reset_us). The brightness is not applied,
+    /// see [`StripConfig::pipeline`](config::StripConfig::pipeline). Fails
+    /// with [`LedAdapterError::ChannelMismatch`] if the chip of the config has
+    /// a different number of channels, and with
+    /// [`LedAdapterError::BufferSizeMismatch`] unless the buffer is sized for
+    /// exactly the number of LEDs. The adapter is left unchanged on failure.
+    pub fn set_strip_config(&mut self, config: &StripConfig) -> Result<(), LedAdapterError> {
+        if config.chip.channels() != Chip::CHANNELS {
+            return Err(LedAdapterError::ChannelMismatch {
+                expected: Chip::CHANNELS,
+                actual: config.chip.channels(),
+            });
+        }
+        self.set_led_count(Some(config.led_count()))?;
+
+        self.set_timing(config.chip.timing());
+        self.set_color_order(Some(config.color_order()));
+        self.set_color_correction(config.correction);
+
+        Ok(())
+    }
+
+    /// Limit the rate at which frames are sent, where 0 disables limiting.
+    ///
+    /// Writes wait until the interval between frames has passed since the
+    /// previous frame was started. See [`FrameLimiter`].
+    #[cfg(feature = "embassy-time")]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.limiter.set_target_fps(fps);
+    }
+
+    /// Statistics of the frames sent in the last complete window.
+    ///
+    /// See [`stats`](crate::stats) for what is measured.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.stats.stats()
+    }
+
+    /// Set the length of the window statistics are collected over, in
+    /// milliseconds.
+    #[cfg(feature = "stats")]
+    pub fn set_stats_window(&mut self, window_ms: u32) {
+        self.stats.set_window(window_ms);
+    }
+
+    /// Problems with the signal since the adapter was created, or the
+    /// counters were reset.
+    ///
+    /// See [`stats`](crate::stats) for what is counted.
+    #[cfg(feature = "stats")]
+    pub fn signal_health(&self) -> SignalHealth {
+        self.stats.health()
+    }
+
+    /// Reset the counters of problems with the signal.
+    #[cfg(feature = "stats")]
+    pub fn reset_signal_health(&mut self) {
+        self.stats.reset_health();
+    }
+
+    /// Number of pulse codes a frame can use, limited by the buffer and the
+    /// memory of the channel
+    const AVAILABLE: usize = if BUFFER_SIZE < CHANNEL_RAM_SIZE {
+        BUFFER_SIZE
+    } else {
+        CHANNEL_RAM_SIZE
+    };
+
+    /// Number of LEDs which fit into the buffer and the memory of the channel
+    /// along with the end delimiter.
+    ///
+    /// This is at most one RGB or RGBW LED, or two RGB LEDs on the ESP32 and
+    /// ESP32-S2.
+    ///
+    /// Longer frames result in a [`LedAdapterError::BufferSizeExceeded`]
+    /// error.
+    pub fn capacity_leds(&self) -> usize {
+        Self::AVAILABLE.saturating_sub(1) / Chip::BITS_PER_LED
+    }
+
+    /// Encode a frame into the back buffer, to be sent by the next call to
+    /// [`SmartLedsAdapterAsync::commit`].
+    ///
+    /// Preparing another frame before committing replaces the prepared one.
+    pub fn prepare<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let back = 1 - self.front;
+        // Invalidate the back buffer until it has been encoded successfully
+        self.prepared = false;
+        let mut iterator = Counted::new(iterator.into_iter(), self.count);
+        self.lengths[back] = self.encoder.encode(
+            &mut self.rmt_buffers[back][..Self::AVAILABLE],
+            &mut iterator,
+        )?;
+        iterator.finish()?;
+        self.prepared = true;
+        trace!("prepared {} pulse codes", self.lengths[back]);
+
+        #[cfg(feature = "stats")]
+        self.stats.set_encode(elapsed_us(start));
+
+        Ok(())
+    }
+
+    /// Encode a frame from a slice into the back buffer, to be sent by the next
+    /// call to [`SmartLedsAdapterAsync::commit`].
+    ///
+    /// This does the same as [`SmartLedsAdapterAsync::prepare`], but checks
+    /// the size of the frame up front and encodes straight from the slice.
+    pub fn prepare_slice(&mut self, colors: &[Chip::Color]) -> Result<(), LedAdapterError> {
+        self.prepare_items(colors, 1, PulseEncoder::encode_slice)
+    }
+
+    /// Encode a frame of colors packed into words, such as `0x00RRGGBB` for
+    /// RGB LEDs or `0xWWRRGGBB` for RGBW LEDs, into the back buffer.
+    ///
+    /// Like [`SmartLedsAdapterAsync::prepare_slice`], this encodes straight
+    /// from the slice, without converting the frame first.
+    pub fn prepare_packed(&mut self, packed: &[u32]) -> Result<(), LedAdapterError>
+    where
+        Chip::Color: PackedColor,
+    {
+        self.prepare_items(packed, 1, PulseEncoder::encode_packed)
+    }
+
+    /// Encode a frame of raw bytes, which are already in the order the chip
+    /// expects, into the back buffer.
+    ///
+    /// Like [`SmartLedsAdapter::write_raw`], the bytes are encoded without any
+    /// adjustment, and bytes of an incomplete LED at the end are ignored.
+    pub fn prepare_raw(&mut self, bytes: &[u8]) -> Result<(), LedAdapterError> {
+        self.prepare_items(bytes, Chip::CHANNELS, PulseEncoder::encode_raw)
+    }
+
+    /// Encode a frame from a slice of `per_led` items per LED into the back
+    /// buffer, which `encode` encodes.
+    fn prepare_items<T>(
+        &mut self,
+        colors: &[T],
+        per_led: usize,
+        encode: impl Fn(&PulseEncoder<Chip>, &mut [u32], &[T]),
+    ) -> Result<(), LedAdapterError> {
+        let leds = match self.count {
+            Some(count) => count.check(colors.len() / per_led)?,
+            None => colors.len() / per_led,
+        };
+        let colors = &colors[..leds * per_led];
+        let len = leds * Chip::BITS_PER_LED + 1;
+        if len > Self::AVAILABLE {
+            return Err(LedAdapterError::buffer_size_exceeded::<Chip>(
+                self.capacity_leds(),
+                leds,
+                Self::AVAILABLE,
+            ));
+        }
+
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let back = 1 - self.front;
+        let buffer = &mut self.rmt_buffers[back];
+        encode(&self.encoder, buffer, colors);
+        buffer[len - 1] = self.encoder.end();
+
+        self.lengths[back] = len;
+        self.prepared = true;
+        trace!("prepared {} pulse codes", len);
+
+        #[cfg(feature = "stats")]
+        self.stats.set_encode(elapsed_us(start));
+
+        Ok(())
+    }
+
+    /// Write a frame of colors from a slice.
+    ///
+    /// This does the same as [`SmartLedsWriteAsync::write`], but encodes
+    /// straight from the slice, which is considerably faster for large frames.
+    pub async fn write_slice(&mut self, colors: &[Chip::Color]) -> Result<(), LedAdapterError> {
+        self.prepare_slice(colors)?;
+        self.commit().await
+    }
+
+    /// Write a frame of colors packed into words, see
+    /// [`SmartLedsAdapterAsync::prepare_packed`].
+    pub async fn write_packed(&mut self, packed: &[u32]) -> Result<(), LedAdapterError>
+    where
+        Chip::Color: PackedColor,
+    {
+        self.prepare_packed(packed)?;
+        self.commit().await
+    }
+
+    /// Write a frame of raw bytes, see [`SmartLedsAdapterAsync::prepare_raw`].
+    pub async fn write_raw(&mut self, bytes: &[u8]) -> Result<(), LedAdapterError> {
+        self.prepare_raw(bytes)?;
+        self.commit().await
+    }
+
+    /// Write a frame, giving up if it has not been sent within `timeout`.
+    ///
+    /// This keeps a fault of the RMT peripheral from hanging the task
+    /// forever. If the frame is not sent in time, the transmission is stopped
+    /// like that of a cancelled write, and [`LedAdapterError::Timeout`] is
+    /// returned. The timeout includes the time waiting for the frame rate
+    /// limit. If writes keep timing out, [`SmartLedsAdapterAsync::recover`]
+    /// resets the channel.
+    #[cfg(feature = "embassy-time")]
+    pub async fn write_with_timeout<T, I>(
+        &mut self,
+        iterator: T,
+        timeout: embassy_time::Duration,
+    ) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        match embassy_time::with_timeout(timeout, SmartLedsWriteAsync::write(self, iterator)).await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("the frame was not sent within {} ms", timeout.as_millis());
+                Err(LedAdapterError::Timeout)
+            }
+        }
+    }
+
+    /// Send the prepared frame and wait for the transmission to finish.
+    ///
+    /// If no new frame has been prepared since the last commit, the previous
+    /// frame is sent again.
+    pub async fn commit(&mut self) -> Result<(), LedAdapterError> {
+        self.flush().await?;
+        #[cfg(feature = "embassy-time")]
+        self.limiter.wait_async().await;
+        self.swap();
+
+        let len = self.lengths[self.front];
+        if len == 0 {
+            return Ok(());
+        }
+
+        trace!("transmitting {} pulse codes", len);
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let result = transmit_or_stop(
+            &mut self.channel,
+            &self.rmt_buffers[self.front][..len],
+            &mut self.aborted,
+        )
+        .await;
+        #[cfg(feature = "stats")]
+        if let Err(e) = &result {
+            self.stats.count_error(e);
+        }
+        result?;
+        self.frame_done();
+
+        #[cfg(feature = "stats")]
+        {
+            self.stats.add_transmit(elapsed_us(start));
+            self.stats.finish_frame();
+        }
+
+        Ok(())
+    }
+
+    /// Wait until the LEDs are ready for the next frame.
+    ///
+    /// Every write waits for its transmission, including the reset time, so
+    /// this only has to do something after a write has been cancelled or
+    /// failed. The transmission is stopped right away in that case, which
+    /// leaves the LEDs with a partial frame. This holds the line low for the
+    /// reset time, so the LEDs latch what they have received instead of
+    /// appending the next frame to it. It also waits for the part of the
+    /// reset time which the end of the last frame could not hold, see
+    /// [`LatchGuard`].
+    ///
+    /// Writes call this before sending, so it only needs to be called
+    /// explicitly to make sure the LEDs are settled, for example before
+    /// powering them down.
+    pub async fn flush(&mut self) -> Result<(), LedAdapterError> {
+        self.encoder.check_resolution()?;
+        if self.aborted {
+            trace!("previous transmission was aborted, holding the line for the reset");
+            transmit_or_stop(&mut self.channel, &[self.encoder.end()], &mut self.aborted).await?;
+            self.latch.finish(self.encoder.latch_gap_us());
+        }
+        #[cfg(feature = "embassy-time")]
+        self.latch.wait_async().await;
+        #[cfg(not(feature = "embassy-time"))]
+        self.latch.wait();
+
+        Ok(())
+    }
+
+    /// Stop any transmission and make the channel usable again.
+    ///
+    /// Cancelled and failed writes already leave the channel usable, so this
+    /// is only needed if the channel hangs, for example after an error of the
+    /// RMT peripheral. Since the channel has been consumed by the adapter, it
+    /// cannot be reconfigured; instead, this stops the transmission, clears the
+    /// state of the channel and holds the line low for the reset time, like
+    /// [`SmartLedsAdapterAsync::flush`]. A prepared frame is kept.
+    pub async fn recover(&mut self) -> Result<(), LedAdapterError> {
+        <TX as TxChannelInternal<Async>>::stop();
+        <TX as TxChannelInternal<Async>>::clear_interrupts();
+        self.aborted = true;
+
+        self.flush().await
+    }
+
+    /// Send the prepared frame, and prepare the next one while the
+    /// transmission is running.
+    ///
+    /// This overlaps encoding with the transmission, which is useful when
+    /// frames are sent back to back. Encoding errors of the next frame are only
+    /// reported once the transmission has finished.
+    pub async fn commit_and_prepare<T, I>(&mut self, next: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        self.flush().await?;
+        #[cfg(feature = "embassy-time")]
+        self.limiter.wait_async().await;
+        self.swap();
+
+        let front = self.front;
+        let len = self.lengths[front];
+        let [first, second] = &mut self.rmt_buffers;
+        let (front_buffer, back_buffer) = if front == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        #[cfg(feature = "stats")]
+        let start = now();
+
+        let mut transmission = pin!(transmit_or_stop(
+            &mut self.channel,
+            &front_buffer[..len],
+            &mut self.aborted
+        ));
+
+        // The transmission is started when the future is first polled, so poll
+        // it once before encoding the next frame.
+        let started = if len == 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            poll_fn(|cx| Poll::Ready(transmission.as_mut().poll(cx))).await
+        };
+
+        #[cfg(feature = "stats")]
+        let encode_start = now();
+
+        let mut next = Counted::new(next.into_iter(), self.count);
+        let prepared = self
+            .encoder
+            .encode(&mut back_buffer[..Self::AVAILABLE], &mut next)
+            .and_then(|len| next.finish().map(|_| len));
+
+        #[cfg(feature = "stats")]
+        let encode_us = elapsed_us(encode_start);
+
+        let sent = match started {
+            Poll::Ready(result) => result,
+            Poll::Pending => transmission.await,
+        };
+
+        // The next frame was encoded while the current one was sent
+        #[cfg(feature = "stats")]
+        {
+            if let Err(e) = &sent {
+                self.stats.count_error(e);
+            }
+            if len > 0 && sent.is_ok() {
+                self.stats.add_transmit(elapsed_us(start));
+                self.stats.finish_frame();
+            }
+            self.stats.set_encode(encode_us);
+        }
+
+        self.prepared = prepared.is_ok();
+        self.lengths[1 - front] = *prepared.as_ref().unwrap_or(&0);
+
+        sent?;
+        // The transmission still borrows the buffers, so the frame is marked
+        // done through the fields
+        if len > 0 {
+            self.latch.finish(self.encoder.latch_gap_us());
+            if let Some(hook) = self.done_hook {
+                hook();
+            }
+        }
+        prepared.map(|_| ())
+    }
+
+    /// Mark the end of a frame for the latch time, and call the done hook, if
+    /// any.
+    fn frame_done(&mut self) {
+        self.latch.finish(self.encoder.latch_gap_us());
+        if let Some(hook) = self.done_hook {
+            hook();
+        }
+    }
+
+    /// Make the prepared frame, if any, the front buffer.
+    fn swap(&mut self) {
+        if self.prepared {
+            self.front = 1 - self.front;
+            self.prepared = false;
+        }
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsWriteAsync
+    for SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Convert all color items of the iterator to the RMT format and
+    /// add them to internal buffer, then start a singular RMT operation
+    /// based on that buffer.
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.prepare(iterator)?;
+        self.commit().await
+    }
+}
+
+#[cfg(all(feature = "esp-hal", feature = "dyn-write"))]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip,
+{
+    /// Write a frame from a trait object.
+    ///
+    /// Like [`SmartLedsAdapter::write_dyn`], all frames written through this
+    /// method share a single copy of the encoder.
+    pub async fn write_dyn(
+        &mut self,
+        iterator: &mut dyn Iterator<Item = Chip::Color>,
+    ) -> Result<(), LedAdapterError> {
+        self.prepare(iterator)?;
+        self.commit().await
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip<Color = RGB8>,
+{
+    /// Write a frame of HSV colors.
+    ///
+    /// The colors are converted to RGB while encoding, without an intermediate
+    /// frame. Gamma and color correction are applied as with
+    /// [`SmartLedsWriteAsync::write`].
+    pub async fn write_hsv<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Hsv>,
+    {
+        self.write(iterator.into_iter().map(|item| hsv2rgb(item.into())))
+            .await
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<TX, const BUFFER_SIZE: usize, Chip> SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    Chip: LedChip<Color = RGBW<u8>>,
+{
+    /// Write a frame of RGB colors to RGBW LEDs.
+    ///
+    /// The colors are sent with the white channel off, unless a
+    /// [`WhiteExtraction`] is set, which computes it while encoding.
+    pub async fn write_rgb<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        self.write(iterator.into_iter().map(|item| rgbw(item.into())))
+            .await
+    }
+}
+
+#[cfg(feature = "esp-hal")]
+impl<const CHANNEL: u8, const BUFFER_SIZE: usize, Chip>
+    SmartLedsAdapterAsync<Channel<Async, CHANNEL>, BUFFER_SIZE, Chip>
+where
+    Channel<Async, CHANNEL>: TxChannelAsync,
+    Channel<Blocking, CHANNEL>: TxChannel,
+    Chip: LedChip,
+{
+    /// Turn the adapter into one for blocking mode on the same channel,
+    /// keeping its buffer and settings.
+    ///
+    /// A frame which has been prepared but not committed is dropped. If the
+    /// last transmission was cancelled, the channel is stopped first, and the
+    /// next frame is sent as usual.
+    pub fn into_blocking(
+        mut self,
+    ) -> SmartLedsAdapter<Channel<Blocking, CHANNEL>, BUFFER_SIZE, Chip> {
+        if self.aborted {
+            <Channel<Async, CHANNEL> as TxChannelInternal<Async>>::stop();
+            <Channel<Async, CHANNEL> as TxChannelInternal<Async>>::clear_interrupts();
+            self.latch.finish(self.encoder.reset_us() as u64);
+        }
+
+        SmartLedsAdapter {
+            channel: Some(<Channel<Blocking, CHANNEL> as TxChannelInternal<
+                Blocking,
+            >>::new()),
+            rmt_buffer: [0; BUFFER_SIZE],
+            encoder: self.encoder,
+            count: self.count,
+            pin: self.pin,
+            output: self.output,
+            streaming: false,
+            gapless: false,
+            yield_hook: None,
+            done_hook: self.done_hook,
+            #[cfg(feature = "embassy-time")]
+            limiter: self.limiter,
+            #[cfg(not(feature = "embassy-time"))]
+            limiter: FrameLimiter::default(),
+            latch: self.latch,
+            interrupt_bound: true,
+            #[cfg(feature = "stats")]
+            stats: self.stats,
+            chip: PhantomData,
+        }
+    }
+}
+
+/// Stops the transmission of a channel in async mode when dropped.
+#[cfg(feature = "esp-hal")]
+struct StopOnDrop<TX>(PhantomData<TX>)
+where
+    TX: TxChannelInternal<Async>;
+
+#[cfg(feature = "esp-hal")]
+impl<TX> Drop for StopOnDrop<TX>
+where
+    TX: TxChannelInternal<Async>,
+{
+    fn drop(&mut self) {
+        TX::stop();
+        TX::clear_interrupts();
+    }
+}
+
+/// Transmit `data` and wait for the transmission to finish, stopping it if the
+/// future is dropped before.
+///
+/// `aborted` is set while the transmission is running, and stays set if it
+/// was cancelled or failed.
+#[cfg(feature = "esp-hal")]
+async fn transmit_or_stop<TX>(
+    channel: &mut TX,
+    data: &[u32],
+    aborted: &mut bool,
+) -> Result<(), RmtError>
+where
+    TX: TxChannelAsync,
+{
+    *aborted = true;
+    let guard = StopOnDrop::<TX>(PhantomData);
+
+    let result = channel.transmit(data).await;
+
+    mem::forget(guard);
+    if let Err(_e) = &result {
+        warn!("transmission failed: {}", _e);
+    }
+    *aborted = result.is_err();
+
+    result
+}
+
+/// Configuration of the channel of an adapter, optionally inverting the signal
+#[cfg(feature = "esp-hal")]
+fn channel_config(inverted: bool) -> TxChannelConfig {
+    TxChannelConfig {
+        clk_divider: 1,
+        idle_output_level: inverted,
+        carrier_modulation: false,
+        idle_output: true,
+
+        ..TxChannelConfig::default()
+    }
+}
+
+/// Pulse codes the adapters encode LEDs with
+#[cfg(not(feature = "lut"))]
+pub(crate) type Pulses = (u32, u32);
+
+/// Pulse codes the adapters encode LEDs with
+#[cfg(feature = "lut")]
+pub(crate) type Pulses = lut::PulseLut;
+
+/// Convert bytes to 8 pulse codes each, most significant bit first.
+#[cfg(any(feature = "esp-hal", not(feature = "lut")))]
+pub(crate) fn convert_bytes_to_pulses(bytes: &[u8], buffer: &mut [u32], pulses: (u32, u32)) {
+    for (byte, codes) in bytes.iter().zip(buffer.chunks_exact_mut(8)) {
+        for (position, pulse) in codes.iter_mut().enumerate() {
+            *pulse = match byte & (0x80 >> position) {
+                0 => pulses.0,
+                _ => pulses.1,
+            };
         }
     }
 }
@@ -0,0 +1,376 @@
+//! On-target verification of the generated signal.
+//!
+//! The timings the LEDs see depend on the clock setup of the chip, and on
+//! whether the RMT peripheral is refilled fast enough to send a frame without
+//! gaps. A [`Loopback`] receives the signal of an adapter with an RMT receive
+//! channel, so hardware-in-the-loop tests can check it without a logic
+//! analyzer: connect the data pin of the adapter to the pin of the receive
+//! channel with a jumper wire, and send a frame with
+//! [`Loopback::write_and_verify`].
+//!
+//! Every pulse is checked against the [`Timing`] of the adapter, so both
+//! wrong bit values and pulses out of tolerance are reported, including a low
+//! pulse which is stretched by a gap in the transmission.
+//!
+//! The receive channel stores the pulses in its channel memory, so frames can
+//! only be as long as fits into it: a single block holds 48 pulse codes (64 on
+//! the ESP32 and ESP32-S2), which is enough for two RGB LEDs.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap();
+//!
+//! let mut led = SmartLedsAdapter::new(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//!     smart_led_buffer!(2),
+//! );
+//! let mut loopback = Loopback::new(rmt.channel2, peripherals.GPIO3, 80.MHz());
+//!
+//! let mut buffer = [0u32; 48];
+//! loopback
+//!     .write_and_verify(&mut led, &[RGB8::new(1, 2, 3), RGB8::new(255, 0, 128)], &mut buffer)
+//!     .unwrap();
+//! ```
+
+use core::fmt::{self, Display};
+
+use esp_hal::{
+    gpio::InputPin,
+    peripheral::Peripheral,
+    rmt::{
+        Error as RmtError, PulseCode, RxChannel, RxChannelConfig, RxChannelCreator,
+        RxChannelInternal, TxChannel,
+    },
+    Blocking,
+};
+use fugit::HertzU32;
+use smart_leds_trait::SmartLedsWrite;
+
+use crate::{chip::LedChip, timing::Timing, LedAdapterError, SmartLedsAdapter};
+
+/// Default tolerance of every pulse, in nanoseconds
+const DEFAULT_TOLERANCE_NS: u32 = 150;
+
+/// Time without an edge after which the receive channel stops, in
+/// microseconds
+///
+/// This is longer than any pulse of a bit, and shorter than any reset time.
+const IDLE_THRESHOLD_US: u32 = 20;
+
+/// Errors found when verifying a received frame
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LoopbackError {
+    /// The adapter failed to send the frame
+    Write(LedAdapterError),
+    /// The receive channel failed, usually because the frame did not fit into
+    /// its memory
+    Receive(RmtError),
+    /// A different number of bits than expected was received
+    Length {
+        /// Number of bits sent
+        expected: usize,
+        /// Number of bits received
+        received: usize,
+    },
+    /// A bit was received with the wrong value
+    Bit {
+        /// Index of the bit in the frame
+        bit: usize,
+        /// Value which was sent
+        expected: bool,
+    },
+    /// A pulse was out of tolerance of the timing profile
+    Timing {
+        /// Index of the bit in the frame
+        bit: usize,
+        /// Measured duration of the high pulse, in nanoseconds
+        high_ns: u32,
+        /// Measured duration of the low pulse, in nanoseconds, which is 0 for
+        /// the last bit since it merges into the reset
+        low_ns: u32,
+    },
+}
+
+impl Display for LoopbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopbackError::Write(e) => write!(f, "writing the frame failed: {e}"),
+            LoopbackError::Receive(e) => write!(f, "receiving the frame failed: {e:?}"),
+            LoopbackError::Length { expected, received } => {
+                write!(f, "received {received} bits, but {expected} were sent")
+            }
+            LoopbackError::Bit { bit, expected } => {
+                write!(f, "bit {bit} should be {}", *expected as u8)
+            }
+            LoopbackError::Timing {
+                bit,
+                high_ns,
+                low_ns,
+            } => write!(
+                f,
+                "bit {bit} is out of tolerance: {high_ns}ns high, {low_ns}ns low"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for LoopbackError {}
+
+impl From<LedAdapterError> for LoopbackError {
+    fn from(e: LedAdapterError) -> Self {
+        LoopbackError::Write(e)
+    }
+}
+
+impl From<RmtError> for LoopbackError {
+    fn from(e: RmtError) -> Self {
+        LoopbackError::Receive(e)
+    }
+}
+
+/// Receives the signal of an adapter on an RMT receive channel and checks it
+pub struct Loopback<RX>
+where
+    RX: RxChannel,
+{
+    channel: Option<RX>,
+    src_clock: u32,
+    tolerance_ns: u32,
+}
+
+impl<'d, RX> Loopback<RX>
+where
+    RX: RxChannel,
+{
+    /// Create a new loopback receiving on the pin with the RMT channel.
+    ///
+    /// `frequency` is the one the RMT peripheral has been set up with in
+    /// `Rmt::new`.
+    pub fn new<C, P>(channel: C, pin: impl Peripheral<P = P> + 'd, frequency: HertzU32) -> Self
+    where
+        P: InputPin + 'd,
+        C: RxChannelCreator<'d, RX, P>,
+    {
+        let src_clock = frequency.to_MHz();
+
+        let config = RxChannelConfig {
+            clk_divider: 1,
+            idle_threshold: (IDLE_THRESHOLD_US * src_clock) as u16,
+
+            ..RxChannelConfig::default()
+        };
+
+        let channel = channel.configure(pin, config).unwrap();
+
+        Self {
+            channel: Some(channel),
+            src_clock,
+            tolerance_ns: DEFAULT_TOLERANCE_NS,
+        }
+    }
+
+    /// Set how far every pulse may be off its nominal duration, in
+    /// nanoseconds.
+    ///
+    /// The default of 150ns is the tolerance of the WS2812 datasheet.
+    pub fn set_tolerance_ns(&mut self, tolerance_ns: u32) {
+        self.tolerance_ns = tolerance_ns;
+    }
+
+    /// Write a frame with the adapter while receiving it into `buffer`, and
+    /// check the received signal against the frame.
+    ///
+    /// The frame is encoded with the color adjustments of the adapter.
+    pub fn write_and_verify<TX, const BUFFER_SIZE: usize, Chip>(
+        &mut self,
+        adapter: &mut SmartLedsAdapter<TX, BUFFER_SIZE, Chip>,
+        frame: &[Chip::Color],
+        buffer: &mut [u32],
+    ) -> Result<(), LoopbackError>
+    where
+        TX: TxChannel,
+        Chip: LedChip,
+    {
+        buffer.fill(0);
+
+        let channel = self.channel.take().unwrap();
+        let transaction = match channel.receive(buffer) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                self.channel = Some(<RX as RxChannelInternal<Blocking>>::new());
+                return Err(e.into());
+            }
+        };
+
+        let written = adapter.write(frame.iter().copied());
+        let received = transaction.wait();
+        let received = match received {
+            Ok(channel) => {
+                self.channel = Some(channel);
+                Ok(())
+            }
+            Err((e, channel)) => {
+                self.channel = Some(channel);
+                Err(e)
+            }
+        };
+        written?;
+        received?;
+
+        let expected = frame.iter().flat_map(|color| {
+            let bytes = Chip::bytes(adapter.encoder.adjustments().apply(*color));
+            (0..Chip::CHANNELS * 8).map(move |bit| bytes[bit / 8] & (0x80 >> (bit % 8)) != 0)
+        });
+
+        verify(
+            buffer,
+            expected,
+            &adapter.encoder.timing(),
+            adapter.encoder.inverted(),
+            self.src_clock,
+            self.tolerance_ns,
+        )
+    }
+}
+
+/// Check received pulse codes against the bits which were sent.
+///
+/// `src_clock` is the tick rate of the receive channel in MHz, and `inverted`
+/// whether the signal idles high.
+pub fn verify<I>(
+    codes: &[u32],
+    expected: I,
+    timing: &Timing,
+    inverted: bool,
+    src_clock: u32,
+    tolerance_ns: u32,
+) -> Result<(), LoopbackError>
+where
+    I: IntoIterator<Item = bool>,
+{
+    let within = |measured: u32, nominal: u32| measured.abs_diff(nominal) <= tolerance_ns;
+
+    let mut pulses = Pulses::new(codes, inverted, src_clock);
+    let mut expected = expected.into_iter();
+    let mut bit = 0;
+
+    while let Some(high_ns) = pulses.next_active() {
+        let low_ns = pulses.next_idle().unwrap_or(0);
+        let last = pulses.is_done();
+
+        let value = high_ns.abs_diff(timing.t1h_ns) < high_ns.abs_diff(timing.t0h_ns);
+        let (nominal_high, nominal_low) = match value {
+            true => (timing.t1h_ns, timing.t1l_ns),
+            false => (timing.t0h_ns, timing.t0l_ns),
+        };
+
+        // The low pulse of the last bit merges into the reset
+        if !within(high_ns, nominal_high) || !(last || within(low_ns, nominal_low)) {
+            return Err(LoopbackError::Timing {
+                bit,
+                high_ns,
+                low_ns: if last { 0 } else { low_ns },
+            });
+        }
+
+        match expected.next() {
+            Some(expected) if expected != value => {
+                return Err(LoopbackError::Bit { bit, expected });
+            }
+            Some(_) => {}
+            None => {
+                return Err(LoopbackError::Length {
+                    expected: bit,
+                    received: bit + 1 + pulses.count_active(),
+                })
+            }
+        }
+
+        bit += 1;
+    }
+
+    let missing = expected.count();
+    if missing > 0 {
+        return Err(LoopbackError::Length {
+            expected: bit + missing,
+            received: bit,
+        });
+    }
+
+    Ok(())
+}
+
+/// Iterator over the pulses of received codes, in nanoseconds
+struct Pulses<'a> {
+    codes: &'a [u32],
+    index: usize,
+    active: bool,
+    src_clock: u32,
+}
+
+impl<'a> Pulses<'a> {
+    fn new(codes: &'a [u32], inverted: bool, src_clock: u32) -> Self {
+        Self {
+            codes,
+            index: 0,
+            active: !inverted,
+            src_clock,
+        }
+    }
+
+    /// The half of a code at `index`, as its level and length in ticks
+    fn half(&self, index: usize) -> Option<(bool, u16)> {
+        let code = self.codes.get(index / 2)?;
+        let (level, length) = match index % 2 {
+            0 => (code.level1(), code.length1()),
+            _ => (code.level2(), code.length2()),
+        };
+
+        // A length of 0 marks the end of the received data
+        (length > 0).then_some((level, length))
+    }
+
+    /// Duration of the next pulse at `level`, merging pulses which were split
+    /// over several codes
+    fn next_level(&mut self, level: bool) -> Option<u32> {
+        let mut ticks = 0u32;
+        while let Some((l, length)) = self.half(self.index) {
+            if l != level {
+                break;
+            }
+            ticks += length as u32;
+            self.index += 1;
+        }
+
+        (ticks > 0).then(|| ticks * 1000 / self.src_clock)
+    }
+
+    fn next_active(&mut self) -> Option<u32> {
+        // Skip anything received before the first edge
+        if self.index == 0 {
+            self.next_level(!self.active);
+        }
+        self.next_level(self.active)
+    }
+
+    fn next_idle(&mut self) -> Option<u32> {
+        self.next_level(!self.active)
+    }
+
+    fn is_done(&self) -> bool {
+        self.half(self.index).is_none()
+    }
+
+    fn count_active(&mut self) -> usize {
+        let mut count = 0;
+        while self.next_active().is_some() {
+            self.next_idle();
+            count += 1;
+        }
+        count
+    }
+}
@@ -0,0 +1,37 @@
+//! Lookup table for encoding bytes into pulse codes.
+//!
+//! With the `lut` feature, the adapters encode every byte by copying its 8
+//! pulse codes from a table of all 256 byte values, instead of testing each
+//! bit. This makes encoding long frames considerably faster, at the cost of
+//! 8 KiB of memory per adapter for the table, which is rebuilt whenever the
+//! timing profile changes.
+
+/// Pulse codes of every byte value, most significant bit first
+#[derive(Clone)]
+pub(crate) struct PulseLut {
+    codes: [[u32; 8]; 256],
+}
+
+impl PulseLut {
+    /// Build the table from the pulse codes of a 0 and a 1 bit.
+    pub(crate) fn new(pulses: (u32, u32)) -> Self {
+        let mut codes = [[0; 8]; 256];
+        for (byte, byte_codes) in codes.iter_mut().enumerate() {
+            for (position, code) in byte_codes.iter_mut().enumerate() {
+                *code = match byte & (0x80 >> position) {
+                    0 => pulses.0,
+                    _ => pulses.1,
+                };
+            }
+        }
+
+        Self { codes }
+    }
+
+    /// Convert bytes to 8 pulse codes each.
+    pub(crate) fn encode(&self, bytes: &[u8], buffer: &mut [u32]) {
+        for (byte, codes) in bytes.iter().zip(buffer.chunks_exact_mut(8)) {
+            codes.copy_from_slice(&self.codes[*byte as usize]);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Brightness caps for parts of a strip.
+//!
+//! Some LEDs of an installation need to stay dimmer than the rest: those
+//! behind a thin spot of a diffuser show up as hotspots, and those near a
+//! camera flare into the picture. A [`BrightnessMask`] caps the brightness of
+//! such LEDs, either with a cap for every LED or with caps for ranges of LEDs
+//! given as [`MaskZone`]s, without changing the colors the application
+//! renders. A cap of 255 leaves a LED unchanged, and a cap of 0 turns it off.
+//!
+//! A mask can be applied to a frame on its own with
+//! [`BrightnessMask::apply`], or as a step of an
+//! [`LedPipeline`](crate::pipeline::LedPipeline), where it is applied after the
+//! brightness and before the power limit, so the power limit accounts for it.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! // Keep the LEDs next to the camera at a quarter of their brightness
+//! static ZONES: [MaskZone; 1] = [MaskZone::new(40, 8, 64)];
+//!
+//! let pipeline = LedPipeline::new()
+//!     .with_brightness(200)
+//!     .with_mask(BrightnessMask::Zones(&ZONES));
+//!
+//! led.write(pipeline.process(frame.iter().copied())).unwrap();
+//! ```
+
+use smart_leds_trait::RGB8;
+
+use crate::color::scale;
+
+/// Cap of the brightness of a range of LEDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MaskZone {
+    /// Index of the first LED of the zone
+    pub start: usize,
+    /// Number of LEDs in the zone
+    pub len: usize,
+    /// Maximum brightness of the LEDs in the zone, where 255 is full
+    /// brightness
+    pub max: u8,
+}
+
+impl MaskZone {
+    /// Create a new zone of `len` LEDs starting at `start`, capped at `max`.
+    pub const fn new(start: usize, len: usize, max: u8) -> Self {
+        Self { start, len, max }
+    }
+
+    /// Whether the LED at `index` is in the zone
+    pub const fn contains(&self, index: usize) -> bool {
+        index >= self.start && index - self.start < self.len
+    }
+}
+
+/// Brightness caps of the LEDs of a strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BrightnessMask {
+    /// A cap for every LED, starting with the first one
+    ///
+    /// LEDs past the end of the slice are not capped.
+    PerLed(&'static [u8]),
+    /// Caps of ranges of LEDs
+    ///
+    /// LEDs outside of every zone are not capped. Where zones overlap, the
+    /// lowest cap applies.
+    Zones(&'static [MaskZone]),
+}
+
+impl BrightnessMask {
+    /// The maximum brightness of the LED at `index`
+    pub fn cap(&self, index: usize) -> u8 {
+        match self {
+            BrightnessMask::PerLed(caps) => caps.get(index).copied().unwrap_or(255),
+            BrightnessMask::Zones(zones) => zones
+                .iter()
+                .filter(|zone| zone.contains(index))
+                .map(|zone| zone.max)
+                .min()
+                .unwrap_or(255),
+        }
+    }
+
+    /// Scale `color` of the LED at `index` down to its cap.
+    pub fn apply_at(&self, index: usize, color: RGB8) -> RGB8 {
+        let cap = self.cap(index);
+        RGB8::new(
+            scale(color.r, cap),
+            scale(color.g, cap),
+            scale(color.b, cap),
+        )
+    }
+
+    /// Scale every color of a frame down to the cap of its LED.
+    pub fn apply<I>(&self, iterator: I) -> impl Iterator<Item = RGB8>
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        let mask = *self;
+        iterator
+            .into_iter()
+            .enumerate()
+            .map(move |(index, color)| mask.apply_at(index, color))
+    }
+}
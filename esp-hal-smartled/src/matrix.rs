@@ -0,0 +1,563 @@
+//! Support for two-dimensional LED matrices.
+//!
+//! Most LED panels are a single strip of LEDs folded into a grid. A
+//! [`LedMatrix`] keeps a frame buffer for such a panel and uses a [`Layout`]
+//! to translate the `(x, y)` coordinate of a pixel into the index of the LED on
+//! the strip. Once a frame has been drawn, [`LedMatrix::flush`] writes it out
+//! through the wrapped adapter.
+//!
+//! For panels whose layout is known at compile time, the
+//! [`led_matrix!`](crate::led_matrix) macro computes a [`MappedLayout`] table
+//! and a buffer sized for the panel, and fails the build if the dimensions do
+//! not match the number of LEDs.
+//!
+//! Displays made of several panels, such as a 2x2 arrangement of 16x16
+//! panels chained into one strip, are described by a [`TiledLayout`], which
+//! combines the layouts of the individual panels into one canvas.
+//!
+//! With the `embedded-graphics` feature enabled, [`LedMatrix`] implements
+//! `DrawTarget`, so text, shapes and images can be rendered using the
+//! [`embedded-graphics`](https://crates.io/crates/embedded-graphics) crate.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let led = SmartLedsAdapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), smartLedBuffer!(64));
+//! let mut matrix: LedMatrix<_, _, 64> = LedMatrix::new(led, MatrixLayout::new(8, 8).serpentine());
+//!
+//! Circle::new(Point::new(1, 1), 6)
+//!     .into_styled(PrimitiveStyle::with_stroke(Rgb888::RED, 1))
+//!     .draw(&mut matrix)
+//!     .unwrap();
+//!
+//! matrix.flush().unwrap();
+//! ```
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+/// Mapping of two-dimensional matrix coordinates to LED indices on a strip
+pub trait Layout {
+    /// Width of the matrix in pixels
+    fn width(&self) -> usize;
+
+    /// Height of the matrix in pixels
+    fn height(&self) -> usize;
+
+    /// Index on the strip of the LED at the given coordinate, or `None` if the
+    /// coordinate is outside of the matrix.
+    fn index(&self, x: usize, y: usize) -> Option<usize>;
+}
+
+/// Direction in which the LEDs of a panel are chained together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Order {
+    /// The strip runs along the rows, starting with the top row
+    RowMajor,
+    /// The strip runs along the columns, starting with the leftmost column
+    ColumnMajor,
+}
+
+/// Clockwise rotation of a panel relative to how it is viewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rotation {
+    /// Not rotated
+    Deg0,
+    /// Rotated by 90 degrees clockwise
+    Deg90,
+    /// Rotated by 180 degrees
+    Deg180,
+    /// Rotated by 270 degrees clockwise
+    Deg270,
+}
+
+/// Configurable mapping of matrix coordinates to LED indices
+///
+/// The layout is described by the physical panel: its width and height as
+/// wired, the direction the strip runs in, and whether every other row (or
+/// column) runs backwards, which is commonly called a serpentine or zig-zag
+/// layout. A rotation and flips can then be applied to match how the panel is
+/// mounted; the coordinates passed to [`Layout::index`] are always those of the
+/// image as seen by the viewer.
+///
+/// ```rust,ignore
+/// // A 16x16 panel where the second row runs from right to left, mounted upside
+/// // down.
+/// let layout = MatrixLayout::new(16, 16)
+///     .serpentine()
+///     .rotate(Rotation::Deg180);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MatrixLayout {
+    width: usize,
+    height: usize,
+    order: Order,
+    serpentine: bool,
+    rotation: Rotation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+}
+
+impl MatrixLayout {
+    /// Create a new row-major layout for a panel with the given dimensions.
+    pub const fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            order: Order::RowMajor,
+            serpentine: false,
+            rotation: Rotation::Deg0,
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+
+    /// Set the direction in which the strip runs.
+    pub const fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Make every other row (or column) run in the opposite direction.
+    pub const fn serpentine(mut self) -> Self {
+        self.serpentine = true;
+        self
+    }
+
+    /// Rotate the panel clockwise.
+    pub const fn rotate(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirror the image along the vertical axis.
+    pub const fn flip_horizontal(mut self) -> Self {
+        self.flip_horizontal = !self.flip_horizontal;
+        self
+    }
+
+    /// Mirror the image along the horizontal axis.
+    pub const fn flip_vertical(mut self) -> Self {
+        self.flip_vertical = !self.flip_vertical;
+        self
+    }
+
+    /// Width and height of the image as viewed, after the rotation, usable in
+    /// `const` contexts
+    pub const fn viewed_size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (self.width, self.height),
+            Rotation::Deg90 | Rotation::Deg270 => (self.height, self.width),
+        }
+    }
+
+    /// Index of the LED at the given coordinate, usable in `const` contexts.
+    pub const fn index_of(&self, x: usize, y: usize) -> Option<usize> {
+        let (width, height) = self.viewed_size();
+
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let x = if self.flip_horizontal {
+            width - 1 - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical {
+            height - 1 - y
+        } else {
+            y
+        };
+
+        // Translate the viewed coordinate to a coordinate on the panel
+        let (x, y) = match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (y, self.height - 1 - x),
+            Rotation::Deg180 => (self.width - 1 - x, self.height - 1 - y),
+            Rotation::Deg270 => (self.width - 1 - y, x),
+        };
+
+        let index = match self.order {
+            Order::RowMajor => {
+                let x = if self.serpentine && y % 2 == 1 {
+                    self.width - 1 - x
+                } else {
+                    x
+                };
+                y * self.width + x
+            }
+            Order::ColumnMajor => {
+                let y = if self.serpentine && x % 2 == 1 {
+                    self.height - 1 - y
+                } else {
+                    y
+                };
+                x * self.height + y
+            }
+        };
+
+        Some(index)
+    }
+}
+
+impl Layout for MatrixLayout {
+    fn width(&self) -> usize {
+        self.viewed_size().0
+    }
+
+    fn height(&self) -> usize {
+        self.viewed_size().1
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        self.index_of(x, y)
+    }
+}
+
+/// Mapping of matrix coordinates to LED indices, stored as a lookup table
+///
+/// The table is computed from a [`MatrixLayout`] in `const` contexts, so it
+/// can be placed in flash, and looking up a pixel does not repeat the
+/// arithmetic of the layout. `WIDTH` and `HEIGHT` are the dimensions of the
+/// image as viewed. See [`led_matrix!`](crate::led_matrix) for creating one
+/// together with a buffer of the right size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedLayout<const WIDTH: usize, const HEIGHT: usize> {
+    table: [[u16; WIDTH]; HEIGHT],
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> MappedLayout<WIDTH, HEIGHT> {
+    /// Compute the table of the layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layout does not have the dimensions of the table as
+    /// viewed, or has more LEDs than fit into a `u16`. In `const` contexts,
+    /// this fails the build.
+    pub const fn new(layout: MatrixLayout) -> Self {
+        let (width, height) = layout.viewed_size();
+        assert!(
+            width == WIDTH && height == HEIGHT,
+            "the layout does not have the dimensions of the matrix"
+        );
+        assert!(
+            WIDTH * HEIGHT <= u16::MAX as usize + 1,
+            "the matrix has too many LEDs"
+        );
+
+        let mut table = [[0; WIDTH]; HEIGHT];
+        let mut y = 0;
+        while y < HEIGHT {
+            let mut x = 0;
+            while x < WIDTH {
+                table[y][x] = match layout.index_of(x, y) {
+                    Some(index) => index as u16,
+                    None => unreachable!(),
+                };
+                x += 1;
+            }
+            y += 1;
+        }
+
+        Self { table }
+    }
+
+    /// The index of the LED of every pixel, by row and then column
+    pub const fn table(&self) -> &[[u16; WIDTH]; HEIGHT] {
+        &self.table
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Layout for MappedLayout<WIDTH, HEIGHT> {
+    fn width(&self) -> usize {
+        WIDTH
+    }
+
+    fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        self.table
+            .get(y)
+            .and_then(|row| row.get(x))
+            .map(|&index| index as usize)
+    }
+}
+
+/// Mapping of a canvas made of several panels chained into one strip
+///
+/// The canvas is a grid of `TILES` panels with `columns` panels per row. Each
+/// panel has its own [`Layout`], so panels can be mounted in different
+/// orientations, but all of them must have the same dimensions as viewed.
+/// `tiles` lists the panels in the order they are chained, and the position of
+/// the panels in the grid follows the tile order, in the same way as the LEDs
+/// of a [`MatrixLayout`].
+///
+/// ```rust,ignore
+/// // Four 16x16 panels in a 2x2 arrangement, where the chain runs through the
+/// // top row from left to right and through the bottom row from right to left.
+/// let panel = MatrixLayout::new(16, 16).serpentine();
+/// let layout = TiledLayout::new([panel; 4], 2).serpentine();
+///
+/// let mut matrix: LedMatrix<_, _, 1024> = LedMatrix::new(led, layout);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TiledLayout<L, const TILES: usize> {
+    tiles: [L; TILES],
+    columns: usize,
+    order: Order,
+    serpentine: bool,
+}
+
+impl<L, const TILES: usize> TiledLayout<L, TILES>
+where
+    L: Layout,
+{
+    /// Create a new layout of the given panels, in the order they are chained,
+    /// with `columns` panels per row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the panels do not fill a whole number of rows, or if they
+    /// have different dimensions.
+    pub fn new(tiles: [L; TILES], columns: usize) -> Self {
+        assert!(columns > 0 && TILES % columns == 0);
+        if let Some(first) = tiles.first() {
+            assert!(tiles
+                .iter()
+                .all(|tile| tile.width() == first.width() && tile.height() == first.height()));
+        }
+
+        Self {
+            tiles,
+            columns,
+            order: Order::RowMajor,
+            serpentine: false,
+        }
+    }
+
+    /// Set the direction in which the chain runs through the grid of panels.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Make every other row (or column) of panels run in the opposite
+    /// direction.
+    pub fn serpentine(mut self) -> Self {
+        self.serpentine = true;
+        self
+    }
+
+    /// The panels, in the order they are chained
+    pub fn tiles(&self) -> &[L; TILES] {
+        &self.tiles
+    }
+
+    /// Number of rows of panels
+    fn rows(&self) -> usize {
+        TILES / self.columns
+    }
+
+    /// Width and height of a panel as viewed
+    fn tile_size(&self) -> (usize, usize) {
+        self.tiles
+            .first()
+            .map_or((0, 0), |tile| (tile.width(), tile.height()))
+    }
+}
+
+impl<L, const TILES: usize> Layout for TiledLayout<L, TILES>
+where
+    L: Layout,
+{
+    fn width(&self) -> usize {
+        self.tile_size().0 * self.columns
+    }
+
+    fn height(&self) -> usize {
+        self.tile_size().1 * self.rows()
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        let (tile_width, tile_height) = self.tile_size();
+        if tile_width == 0 || tile_height == 0 {
+            return None;
+        }
+
+        let (column, row) = (x / tile_width, y / tile_height);
+        let (columns, rows) = (self.columns, self.rows());
+        if column >= columns || row >= rows {
+            return None;
+        }
+
+        // Position of the panel along the chain
+        let tile = match self.order {
+            Order::RowMajor => {
+                let column = if self.serpentine && row % 2 == 1 {
+                    columns - 1 - column
+                } else {
+                    column
+                };
+                row * columns + column
+            }
+            Order::ColumnMajor => {
+                let row = if self.serpentine && column % 2 == 1 {
+                    rows - 1 - row
+                } else {
+                    row
+                };
+                column * rows + row
+            }
+        };
+
+        let offset = tile * tile_width * tile_height;
+        self.tiles[tile]
+            .index(x % tile_width, y % tile_height)
+            .map(|index| offset + index)
+    }
+}
+
+/// Frame buffer for an LED matrix driven by a `smart-leds` adapter
+///
+/// `LEDS` is the number of LEDs on the strip, which must be at least the number
+/// of pixels of the layout.
+pub struct LedMatrix<A, L, const LEDS: usize> {
+    adapter: A,
+    layout: L,
+    frame: [RGB8; LEDS],
+}
+
+impl<A, L, const LEDS: usize> LedMatrix<A, L, LEDS>
+where
+    L: Layout,
+{
+    /// Create a new matrix writing to the given adapter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layout contains more pixels than there are LEDs.
+    pub fn new(adapter: A, layout: L) -> Self {
+        assert!(layout.width() * layout.height() <= LEDS);
+
+        Self {
+            adapter,
+            layout,
+            frame: [RGB8::default(); LEDS],
+        }
+    }
+
+    /// The layout used to map coordinates to LEDs
+    pub fn layout(&self) -> &L {
+        &self.layout
+    }
+
+    /// Set the color of the pixel at the given coordinate.
+    ///
+    /// Coordinates outside of the matrix are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: RGB8) {
+        if let Some(led) = self.layout.index(x, y).and_then(|i| self.frame.get_mut(i)) {
+            *led = color;
+        }
+    }
+
+    /// The color of the pixel at the given coordinate, or `None` if the
+    /// coordinate is outside of the matrix.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<RGB8> {
+        self.layout
+            .index(x, y)
+            .and_then(|i| self.frame.get(i))
+            .copied()
+    }
+
+    /// Set all LEDs to the given color.
+    pub fn fill(&mut self, color: RGB8) {
+        self.frame.fill(color);
+    }
+
+    /// Turn all LEDs off.
+    pub fn clear(&mut self) {
+        self.fill(RGB8::default());
+    }
+
+    /// The frame buffer, in strip order
+    pub fn frame(&self) -> &[RGB8; LEDS] {
+        &self.frame
+    }
+
+    /// Mutable access to the frame buffer, in strip order
+    pub fn frame_mut(&mut self) -> &mut [RGB8; LEDS] {
+        &mut self.frame
+    }
+
+    /// Give back the wrapped adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
+
+impl<A, L, const LEDS: usize> LedMatrix<A, L, LEDS>
+where
+    A: SmartLedsWrite<Color = RGB8>,
+{
+    /// Write the current frame to the LEDs.
+    pub fn flush(&mut self) -> Result<(), A::Error> {
+        self.adapter.write(self.frame.iter().copied())
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+mod graphics {
+    use core::convert::Infallible;
+
+    use embedded_graphics_core::{
+        draw_target::DrawTarget,
+        geometry::{OriginDimensions, Size},
+        pixelcolor::{Rgb888, RgbColor},
+        Pixel,
+    };
+    use smart_leds_trait::RGB8;
+
+    use super::{Layout, LedMatrix};
+
+    impl<A, L, const LEDS: usize> OriginDimensions for LedMatrix<A, L, LEDS>
+    where
+        L: Layout,
+    {
+        fn size(&self) -> Size {
+            Size::new(self.layout.width() as u32, self.layout.height() as u32)
+        }
+    }
+
+    impl<A, L, const LEDS: usize> DrawTarget for LedMatrix<A, L, LEDS>
+    where
+        L: Layout,
+    {
+        type Color = Rgb888;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) {
+                    self.set_pixel(x, y, RGB8::new(color.r(), color.g(), color.b()));
+                }
+            }
+
+            Ok(())
+        }
+
+        fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+            self.fill(RGB8::new(color.r(), color.g(), color.b()));
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Sending the same frames to a second pin.
+//!
+//! Symmetric installations, such as a left and a right strip showing the same
+//! content, would otherwise need two adapters encoding the same frames. A
+//! [`MirroredAdapterAsync`] encodes every frame once and sends it on two RMT
+//! channels, which are started in the same poll so both strips update
+//! together.
+//!
+//! The RMT driver of `esp-hal` connects every channel to exactly one pin and
+//! offers no way to route the signal of a configured channel to further pins,
+//! so the mirror uses a channel of its own. Only the async adapter is
+//! supported: the blocking driver refills the memory of one channel at a time
+//! while waiting for a transmission, so it cannot run two channels in
+//! parallel.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap().into_async();
+//!
+//! let led = SmartLedsAdapterAsync::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), smart_led_buffer!(1));
+//! let mut led = MirroredAdapterAsync::new(led, rmt.channel1, peripherals.GPIO3);
+//!
+//! led.write(data.iter().cloned()).await.unwrap();
+//! ```
+
+use esp_hal::{
+    gpio::OutputPin,
+    peripheral::Peripheral,
+    rmt::{TxChannelAsync, TxChannelConfig, TxChannelCreatorAsync},
+};
+use smart_leds_trait::SmartLedsWriteAsync;
+
+use crate::{
+    chip::{LedChip, Sk68xx},
+    latch::Join,
+    transmit_or_stop, LedAdapterError, SmartLedsAdapterAsync,
+};
+
+/// Adapter sending the frames of a [`SmartLedsAdapterAsync`] on a second RMT
+/// channel as well
+pub struct MirroredAdapterAsync<TX, MX, const BUFFER_SIZE: usize, Chip = Sk68xx>
+where
+    TX: TxChannelAsync,
+    MX: TxChannelAsync,
+    Chip: LedChip,
+{
+    adapter: SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>,
+    mirror: MX,
+    aborted: bool,
+}
+
+impl<'d, TX, MX, const BUFFER_SIZE: usize, Chip> MirroredAdapterAsync<TX, MX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    MX: TxChannelAsync,
+    Chip: LedChip,
+{
+    /// Mirror the frames of `adapter` on the pin, using the given channel.
+    ///
+    /// The mirror pin idles at the level of the reset of the adapter.
+    pub fn new<C, O>(
+        adapter: SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>,
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, MX, O>,
+    {
+        let config = TxChannelConfig {
+            clk_divider: 1,
+            idle_output_level: adapter.encoder.inverted(),
+            carrier_modulation: false,
+            idle_output: true,
+
+            ..TxChannelConfig::default()
+        };
+
+        let mirror = channel.configure(pin, config).unwrap();
+
+        Self {
+            adapter,
+            mirror,
+            aborted: false,
+        }
+    }
+
+    /// The wrapped adapter, to prepare frames or change its settings
+    pub fn adapter(&mut self) -> &mut SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip> {
+        &mut self.adapter
+    }
+
+    /// Send the prepared frame on both channels and wait for both
+    /// transmissions to finish.
+    ///
+    /// See [`SmartLedsAdapterAsync::commit`].
+    pub async fn commit(&mut self) -> Result<(), LedAdapterError> {
+        self.flush().await?;
+        #[cfg(feature = "embassy-time")]
+        self.adapter.limiter.wait_async().await;
+        self.adapter.swap();
+
+        let adapter = &mut self.adapter;
+        let len = adapter.lengths[adapter.front];
+        if len == 0 {
+            return Ok(());
+        }
+
+        let data = &adapter.rmt_buffers[adapter.front][..len];
+        Join::new(
+            async {
+                transmit_or_stop(&mut adapter.channel, data, &mut adapter.aborted).await?;
+                Ok::<_, LedAdapterError>(())
+            },
+            async {
+                transmit_or_stop(&mut self.mirror, data, &mut self.aborted).await?;
+                Ok::<_, LedAdapterError>(())
+            },
+        )
+        .await
+    }
+
+    /// Wait until the LEDs on both pins are ready for the next frame.
+    ///
+    /// See [`SmartLedsAdapterAsync::flush`].
+    pub async fn flush(&mut self) -> Result<(), LedAdapterError> {
+        self.adapter.flush().await?;
+        if self.aborted {
+            transmit_or_stop(
+                &mut self.mirror,
+                &[self.adapter.encoder.end()],
+                &mut self.aborted,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Give back the wrapped adapter and the channel of the mirror.
+    pub fn release(self) -> (SmartLedsAdapterAsync<TX, BUFFER_SIZE, Chip>, MX) {
+        (self.adapter, self.mirror)
+    }
+}
+
+impl<TX, MX, const BUFFER_SIZE: usize, Chip> SmartLedsWriteAsync
+    for MirroredAdapterAsync<TX, MX, BUFFER_SIZE, Chip>
+where
+    TX: TxChannelAsync,
+    MX: TxChannelAsync,
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Encode all color items of the iterator once, then send them on both
+    /// channels.
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.adapter.prepare(iterator)?;
+        self.commit().await
+    }
+}
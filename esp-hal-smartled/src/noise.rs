@@ -0,0 +1,175 @@
+//! Noise for organic effects.
+//!
+//! Fire, lava and clouds look natural when their colors follow smooth,
+//! random-looking noise instead of a formula. The functions here compute
+//! Perlin noise in fixed point, with integer arithmetic only, so they are
+//! cheap enough to evaluate for every LED of every frame on chips without a
+//! floating point unit.
+//!
+//! Coordinates are in 8.8 fixed point: the upper byte selects a cell of the
+//! noise lattice, and the lower byte the position within it. Stepping a
+//! coordinate by 256 moves to the next cell, so smaller steps give smoother
+//! noise. All functions return a value from 0 to 255, with 128 on average.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut frame = [RGB8::default(); NUM_LEDS];
+//!
+//! // Lava flowing slowly along the strip
+//! fill_noise(&mut frame, &Palette16::LAVA, 0, time * 8, 60);
+//! time += 1;
+//!
+//! led.write(frame.iter().copied()).unwrap();
+//! ```
+
+use smart_leds_trait::RGB8;
+
+use crate::palette::{color_from_palette, Blending, Palette16};
+
+/// Permutation of Ken Perlin's reference implementation
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+/// Offset between the corners of a cell, in the 7-bit positions within it
+const CELL: i16 = 128;
+
+/// 1D Perlin noise at `x`, from 0 to 255.
+pub fn noise1(x: u16) -> u8 {
+    let cell = (x >> 8) as u8;
+    let a = perm(perm(cell));
+    let b = perm(perm(cell.wrapping_add(1)));
+
+    let fraction = x as u8;
+    let u = fade(fraction);
+    let x = (fraction >> 1) as i16;
+
+    stretch(lerp(grad1(a, x), grad1(b, x - CELL), u))
+}
+
+/// 2D Perlin noise at `(x, y)`, from 0 to 255.
+pub fn noise2(x: u16, y: u16) -> u8 {
+    let (cell_x, cell_y) = ((x >> 8) as u8, (y >> 8) as u8);
+    let a = perm(cell_x).wrapping_add(cell_y);
+    let b = perm(cell_x.wrapping_add(1)).wrapping_add(cell_y);
+    let (aa, ab) = (perm(a), perm(a.wrapping_add(1)));
+    let (ba, bb) = (perm(b), perm(b.wrapping_add(1)));
+
+    let (fraction_x, fraction_y) = (x as u8, y as u8);
+    let (u, v) = (fade(fraction_x), fade(fraction_y));
+    let (x, y) = ((fraction_x >> 1) as i16, (fraction_y >> 1) as i16);
+
+    let bottom = lerp(grad2(perm(aa), x, y), grad2(perm(ba), x - CELL, y), u);
+    let top = lerp(
+        grad2(perm(ab), x, y - CELL),
+        grad2(perm(bb), x - CELL, y - CELL),
+        u,
+    );
+
+    stretch(lerp(bottom, top, v))
+}
+
+/// 2D Perlin noise at `(x, y)` with `octaves` layers of finer detail, from 0
+/// to 255.
+///
+/// Every octave doubles the frequency and halves the amplitude of the one
+/// before, which gives the noise the rough edges of flames and clouds. One
+/// octave is the same as [`noise2`].
+pub fn fractal2(x: u16, y: u16, octaves: u8) -> u8 {
+    let mut sum = 0u32;
+    let mut total = 0u32;
+    let mut amplitude = 128u32;
+    let (mut x, mut y) = (x, y);
+
+    for _ in 0..octaves.max(1) {
+        sum += noise2(x, y) as u32 * amplitude;
+        total += amplitude;
+
+        amplitude /= 2;
+        if amplitude == 0 {
+            break;
+        }
+        // Offset every octave, so the lattice points do not line up
+        x = x.wrapping_mul(2).wrapping_add(0x3a7d);
+        y = y.wrapping_mul(2).wrapping_add(0x91c5);
+    }
+
+    (sum / total) as u8
+}
+
+/// Fill a frame with colors of the palette picked by 2D noise.
+///
+/// The LEDs are placed at `x` along the first axis, starting at `x` and
+/// `step` apart, and at `y` along the second one. Advancing `y` over time
+/// makes the pattern flow.
+pub fn fill_noise(frame: &mut [RGB8], palette: &Palette16, x: u16, y: u16, step: u16) {
+    let mut position = x;
+    for led in frame {
+        let index = noise2(position, y);
+        *led = color_from_palette(palette, index, 255, Blending::Linear);
+        position = position.wrapping_add(step);
+    }
+}
+
+/// Entry of the permutation
+fn perm(index: u8) -> u8 {
+    PERMUTATION[index as usize]
+}
+
+/// Smooth the position within a cell, so the noise has no creases at the
+/// corners.
+const fn fade(t: u8) -> u8 {
+    let t = t as u32;
+    // 3t² - 2t³, scaled to 0..=255
+    ((t * t * (3 * 255 - 2 * t)) / (255 * 255)) as u8
+}
+
+/// Interpolate between `a` and `b` by `fraction / 256`.
+const fn lerp(a: i16, b: i16, fraction: u8) -> i16 {
+    a + (((b - a) as i32 * fraction as i32) >> 8) as i16
+}
+
+/// Gradient of a 1D lattice point, from -128 to 128
+const fn grad1(hash: u8, x: i16) -> i16 {
+    match hash & 8 {
+        0 => x,
+        _ => -x,
+    }
+}
+
+/// Gradient of a 2D lattice point, from -128 to 127
+const fn grad2(hash: u8, x: i16, y: i16) -> i16 {
+    let (u, v) = match hash & 4 {
+        0 => (x, y),
+        _ => (y, x),
+    };
+    let u = if hash & 1 != 0 { -u } else { u };
+    let v = if hash & 2 != 0 { -v } else { v };
+
+    (u + v) >> 1
+}
+
+/// Map raw noise, which mostly stays between -64 and 64, onto 0 to 255.
+const fn stretch(noise: i16) -> u8 {
+    let value = (noise + 64) * 2;
+    if value < 0 {
+        0
+    } else if value > 255 {
+        255
+    } else {
+        value as u8
+    }
+}
@@ -0,0 +1,185 @@
+//! Electrical configuration of the data pin.
+//!
+//! By default the RMT driver configures the data pin as a push-pull output
+//! with the default drive strength. Level shifters built around a single
+//! transistor or an open-collector buffer instead need the pin to only pull
+//! the line low, with an external pull-up to the supply of the LEDs. Long or
+//! heavily loaded data lines may need a stronger or weaker drive. Level
+//! shifters made of a single inverting transistor need the signal to be
+//! inverted, which flips the level of every pulse and the idle level.
+//!
+//! Between frames, the line is held at the level of the reset by default.
+//! Some opto-isolated or inverting level shifters need a different level while
+//! the line is idle to avoid a spurious first bit, which an [`IdleLevel`]
+//! selects. The RMT peripheral cannot release the pin, so to leave the line
+//! floating, for example to an external pull-up, combine
+//! [`IdleLevel::High`] with an open-drain output.
+//!
+//! An [`OutputConfig`] is passed to the `new_with_output` constructors of the
+//! adapters, and applied after the channel has been configured, since
+//! configuring the channel resets the pin to push-pull. The adapters apply it
+//! again to the pin they move the signal to with `swap_pin`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let output = OutputConfig::new()
+//!     .with_open_drain(true)
+//!     .with_drive_strength(DriveStrength::I40mA)
+//!     .with_idle_level(IdleLevel::High);
+//!
+//! let led = SmartLedsAdapter::new_with_output(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//!     rmt_buffer,
+//!     Sk68xx,
+//!     output,
+//! );
+//! ```
+
+use esp_hal::{
+    gpio::{AnyPin, DriveStrength, Flex, Level, OutputPin, OutputSignal, Pull},
+    peripheral::Peripheral,
+    rmt::TxChannelConfig,
+};
+
+use crate::channel_config;
+
+/// Level of the line between frames
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IdleLevel {
+    /// The level of the reset, which is low, or high if the signal is
+    /// inverted
+    #[default]
+    Reset,
+    /// Drive the line low
+    Low,
+    /// Drive the line high, which releases it in open-drain mode
+    High,
+}
+
+/// Electrical configuration of the data pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutputConfig {
+    /// Only drive the line low, relying on an external pull-up
+    pub open_drain: bool,
+    /// Drive strength of the pin
+    pub drive_strength: DriveStrength,
+    /// Invert the signal, so the line idles high and every bit starts with a
+    /// low pulse
+    pub inverted: bool,
+    /// Level of the line between frames
+    pub idle_level: IdleLevel,
+}
+
+impl OutputConfig {
+    /// A push-pull output with a drive strength of 20 mA, which is what the
+    /// RMT driver sets up by default.
+    pub const fn new() -> Self {
+        Self {
+            open_drain: false,
+            drive_strength: DriveStrength::I20mA,
+            inverted: false,
+            idle_level: IdleLevel::Reset,
+        }
+    }
+
+    /// Enable or disable open-drain mode.
+    pub const fn with_open_drain(mut self, open_drain: bool) -> Self {
+        self.open_drain = open_drain;
+        self
+    }
+
+    /// Set the drive strength of the pin.
+    pub const fn with_drive_strength(mut self, drive_strength: DriveStrength) -> Self {
+        self.drive_strength = drive_strength;
+        self
+    }
+
+    /// Enable or disable inverting the signal.
+    ///
+    /// This is needed when the level shifter is a single inverting
+    /// transistor.
+    pub const fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Set the level of the line between frames.
+    ///
+    /// The line is still held at the level of the reset for the reset time
+    /// after every frame, so the LEDs latch the new colors.
+    pub const fn with_idle_level(mut self, idle_level: IdleLevel) -> Self {
+        self.idle_level = idle_level;
+        self
+    }
+
+    /// The configuration of the RMT channel for this output.
+    pub(crate) fn channel_config(&self) -> TxChannelConfig {
+        let mut config = channel_config(self.inverted);
+        config.idle_output_level = match self.idle_level {
+            IdleLevel::Reset => self.inverted,
+            IdleLevel::Low => false,
+            IdleLevel::High => true,
+        };
+
+        config
+    }
+
+    /// Apply the configuration to a pin which is already connected to the
+    /// output `signal` of an RMT channel.
+    ///
+    /// Switching the pin to open-drain routes it back to the GPIO output, so
+    /// the signal is connected to it again afterwards.
+    pub(crate) fn apply<O>(&self, pin: O, signal: OutputSignal)
+    where
+        O: OutputPin + Peripheral<P = O>,
+    {
+        let mut pin = Flex::new(pin);
+
+        if self.open_drain {
+            pin.set_as_open_drain(Pull::None);
+        }
+        pin.set_drive_strength(self.drive_strength);
+
+        if self.open_drain {
+            signal.connect_to(pin);
+        }
+    }
+
+    /// Move the output `signal` of an RMT channel from the pin `old` to the
+    /// pin `new`.
+    ///
+    /// Both pins are set to the idle level before they change over, so
+    /// neither line sees a spurious pulse. The old pin is routed back to the
+    /// GPIO output, which keeps holding the line at that level.
+    pub(crate) fn reroute(&self, old: &mut AnyPin, new: &mut AnyPin, signal: OutputSignal) {
+        let level = Level::from(self.channel_config().idle_output_level);
+
+        let mut pin = Flex::new(&mut *old);
+        pin.set_level(level);
+        if self.open_drain {
+            pin.set_as_open_drain(Pull::None);
+        } else {
+            pin.set_as_output();
+        }
+
+        let mut pin = Flex::new(&mut *new);
+        pin.set_level(level);
+        pin.set_as_output();
+        signal.connect_to(&mut *new);
+
+        // SAFETY: the copy of the pin is only used to change its pad settings
+        // once it has been connected to the channel
+        self.apply(unsafe { new.clone_unchecked() }, signal);
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,100 @@
+//! Frame rate limiting.
+//!
+//! Sending frames as fast as the strip allows wastes CPU time, and makes the
+//! speed of animations depend on the number of LEDs. A [`FrameLimiter`] spaces
+//! frames evenly by waiting until the frame interval has passed since the
+//! previous frame was started. Both adapters contain one, which is configured
+//! with `set_target_fps`, but it can also be used on its own, for example with
+//! the adapters in [`crate::clocked`].
+//!
+//! Paced frames always leave the LEDs enough time to latch, as the adapters
+//! wait for the [`LatchGuard`](crate::latch::LatchGuard) of the previous frame
+//! before every write.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut limiter = FrameLimiter::new(60);
+//!
+//! loop {
+//!     limiter.wait();
+//!     led.write(next_frame()).unwrap();
+//! }
+//! ```
+
+use esp_hal::time::{now, Instant};
+
+/// Waits between frames to keep a target frame rate
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimiter {
+    interval_us: u64,
+    last_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Create a new limiter for the given frame rate.
+    ///
+    /// A frame rate of 0 disables limiting.
+    pub const fn new(fps: u32) -> Self {
+        Self {
+            interval_us: interval_us(fps),
+            last_frame: None,
+        }
+    }
+
+    /// Change the target frame rate, where 0 disables limiting.
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.interval_us = interval_us(fps);
+    }
+
+    /// Change the interval between the starts of frames, in microseconds,
+    /// where 0 disables limiting.
+    pub fn set_interval_us(&mut self, interval_us: u64) {
+        self.interval_us = interval_us;
+    }
+
+    /// Wait until the next frame is due, and mark it as started.
+    pub fn wait(&mut self) {
+        while self.remaining_us() > 0 {}
+
+        self.last_frame = Some(now());
+    }
+
+    /// Wait until the next frame is due without blocking the executor, and
+    /// mark it as started.
+    #[cfg(feature = "embassy-time")]
+    pub async fn wait_async(&mut self) {
+        let remaining = self.remaining_us();
+        if remaining > 0 {
+            embassy_time::Timer::after_micros(remaining).await;
+        }
+
+        self.last_frame = Some(now());
+    }
+
+    /// Time until the next frame is due, in microseconds
+    fn remaining_us(&self) -> u64 {
+        let elapsed = match self.last_frame {
+            Some(last) if self.interval_us > 0 => now().checked_duration_since(last),
+            _ => return 0,
+        };
+
+        elapsed.map_or(0, |elapsed| {
+            self.interval_us.saturating_sub(elapsed.to_micros())
+        })
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Time between frames at `fps`, in microseconds
+const fn interval_us(fps: u32) -> u64 {
+    match fps {
+        0 => 0,
+        fps => 1_000_000 / fps as u64,
+    }
+}
@@ -0,0 +1,171 @@
+//! Color palettes.
+//!
+//! Many effects pick their colors from a small palette instead of computing
+//! them, so the same effect can look like fire, water or a rainbow. A
+//! [`Palette16`] holds 16 colors, spread evenly over the indices 0 to 255,
+//! and [`color_from_palette`] looks up an index, blending linearly between
+//! the two nearest colors, like `ColorFromPalette` of FastLED.
+//!
+//! A few palettes are built in, with the colors of their FastLED
+//! counterparts: [`Palette16::RAINBOW`], [`Palette16::HEAT`],
+//! [`Palette16::OCEAN`], [`Palette16::LAVA`], [`Palette16::CLOUD`] and
+//! [`Palette16::FOREST`]. Others are created with [`Palette16::new`], or from
+//! hex codes with [`Palette16::from_hex`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut frame = [RGB8::default(); NUM_LEDS];
+//!
+//! // Scroll a rainbow along the strip
+//! fill_palette(&mut frame, offset, 4, &Palette16::RAINBOW, 255, Blending::Linear);
+//! offset = offset.wrapping_add(1);
+//!
+//! led.write(frame.iter().copied()).unwrap();
+//! ```
+
+use smart_leds_trait::RGB8;
+
+use crate::color::scale;
+
+/// How colors between two entries of a palette are computed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Blending {
+    /// Blend linearly between the two nearest entries
+    #[default]
+    Linear,
+    /// Use the entry at or below the index
+    None,
+}
+
+/// Palette of 16 colors, spread evenly over the indices 0 to 255
+///
+/// The palette wraps around: indices past the last entry blend back towards
+/// the first one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette16 {
+    entries: [RGB8; 16],
+}
+
+impl Palette16 {
+    /// Hues of the color wheel
+    pub const RAINBOW: Self = Self::from_hex([
+        0xFF0000, 0xD52A00, 0xAB5500, 0xAB7F00, 0xABAB00, 0x56D500, 0x00FF00, 0x00D52A, 0x00AB55,
+        0x0056AA, 0x0000FF, 0x2A00D5, 0x5500AB, 0x7F0081, 0xAB0055, 0xD5002B,
+    ]);
+
+    /// Black through red and yellow to white, for fire
+    pub const HEAT: Self = Self::from_hex([
+        0x000000, 0x330000, 0x660000, 0x990000, 0xCC0000, 0xFF0000, 0xFF3300, 0xFF6600, 0xFF9900,
+        0xFFCC00, 0xFFFF00, 0xFFFF33, 0xFFFF66, 0xFFFF99, 0xFFFFCC, 0xFFFFFF,
+    ]);
+
+    /// Blues and greens of water
+    pub const OCEAN: Self = Self::from_hex([
+        0x191970, 0x00008B, 0x191970, 0x000080, 0x00008B, 0x0000CD, 0x2E8B57, 0x008080, 0x5F9EA0,
+        0x0000FF, 0x008B8B, 0x6495ED, 0x7FFFD4, 0x2E8B57, 0x00FFFF, 0x87CEFA,
+    ]);
+
+    /// Dark reds with flashes of orange and white
+    pub const LAVA: Self = Self::from_hex([
+        0x000000, 0x800000, 0x000000, 0x800000, 0x8B0000, 0x8B0000, 0x800000, 0x8B0000, 0x8B0000,
+        0x8B0000, 0xFF0000, 0xFFA500, 0xFFFFFF, 0xFFA500, 0xFF0000, 0x8B0000,
+    ]);
+
+    /// Dark blues with white clouds
+    pub const CLOUD: Self = Self::from_hex([
+        0x0000FF, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x0000FF,
+        0x00008B, 0x87CEEB, 0x87CEEB, 0xADD8E6, 0xFFFFFF, 0xADD8E6, 0x87CEEB,
+    ]);
+
+    /// Greens of foliage
+    pub const FOREST: Self = Self::from_hex([
+        0x006400, 0x006400, 0x556B2F, 0x006400, 0x008000, 0x228B22, 0x6B8E23, 0x008000, 0x2E8B57,
+        0x66CDAA, 0x32CD32, 0x9ACD32, 0x90EE90, 0x7CFC00, 0x66CDAA, 0x228B22,
+    ]);
+
+    /// Create a new palette from its colors.
+    pub const fn new(entries: [RGB8; 16]) -> Self {
+        Self { entries }
+    }
+
+    /// Create a new palette from colors in the `0xRRGGBB` format.
+    pub const fn from_hex(hex: [u32; 16]) -> Self {
+        let mut entries = [RGB8::new(0, 0, 0); 16];
+        let mut i = 0;
+        while i < 16 {
+            entries[i] = RGB8::new((hex[i] >> 16) as u8, (hex[i] >> 8) as u8, hex[i] as u8);
+            i += 1;
+        }
+
+        Self { entries }
+    }
+
+    /// The colors of the palette
+    pub const fn entries(&self) -> &[RGB8; 16] {
+        &self.entries
+    }
+
+    /// Mutable access to the colors of the palette
+    pub fn entries_mut(&mut self) -> &mut [RGB8; 16] {
+        &mut self.entries
+    }
+}
+
+impl Default for Palette16 {
+    fn default() -> Self {
+        Self::RAINBOW
+    }
+}
+
+/// The color of the palette at `index`, scaled by `brightness / 255`.
+///
+/// The upper 4 bits of the index select an entry, and with
+/// [`Blending::Linear`], the lower 4 bits blend towards the next one.
+pub const fn color_from_palette(
+    palette: &Palette16,
+    index: u8,
+    brightness: u8,
+    blending: Blending,
+) -> RGB8 {
+    let entry = (index >> 4) as usize;
+    let fraction = (index & 0x0f) << 4;
+    let color = palette.entries[entry];
+
+    let color = match blending {
+        Blending::Linear if fraction > 0 => {
+            let next = palette.entries[(entry + 1) % 16];
+            let rest = 255 - fraction;
+            RGB8::new(
+                scale(color.r, rest) + scale(next.r, fraction),
+                scale(color.g, rest) + scale(next.g, fraction),
+                scale(color.b, rest) + scale(next.b, fraction),
+            )
+        }
+        _ => color,
+    };
+
+    RGB8::new(
+        scale(color.r, brightness),
+        scale(color.g, brightness),
+        scale(color.b, brightness),
+    )
+}
+
+/// Fill a frame with colors of the palette, starting at index `start` for
+/// the first LED and advancing by `step` for every following LED.
+pub fn fill_palette(
+    frame: &mut [RGB8],
+    start: u8,
+    step: u8,
+    palette: &Palette16,
+    brightness: u8,
+    blending: Blending,
+) {
+    let mut index = start;
+    for led in frame {
+        *led = color_from_palette(palette, index, brightness, blending);
+        index = index.wrapping_add(step);
+    }
+}
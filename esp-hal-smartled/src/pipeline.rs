@@ -0,0 +1,243 @@
+//! A single pass over a frame for all color processing.
+//!
+//! Applications usually run a frame through several steps before writing it:
+//! a gamma curve, a color correction, a global brightness and a power limit.
+//! Chaining the iterator adaptors of each step is verbose, and gets the order
+//! wrong easily. An [`LedPipeline`] holds all of these steps, and
+//! [`LedPipeline::process`] applies them in a fixed order to every LED as the
+//! adapter encodes it:
+//!
+//! 1. the decoding of sRGB colors into linear values,
+//! 2. the gamma curve, or a curve for every channel,
+//! 3. the color correction,
+//! 4. the brightness,
+//! 5. the brightness mask,
+//! 6. the power limit.
+//!
+//! The power limit depends on the whole frame, so the frame is walked twice:
+//! once to estimate the current the processed frame draws, and once to
+//! produce the colors. Nothing is buffered in between.
+//!
+//! The brightness and the power limit scale the light of the LEDs, so they are
+//! only right for linear values. Colors picked on a screen or taken from an
+//! image are usually encoded in sRGB instead, and scaling them directly dims
+//! and mixes them wrongly, and underestimates the current they draw. Declare
+//! such colors with [`LedPipeline::with_color_space`], and the pipeline
+//! decodes them before all other steps.
+//!
+//! The steps are applied before the colors reach the adapter, so the gamma
+//! curve and color correction of the adapter should be left unset when using
+//! a pipeline.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let pipeline = LedPipeline::new()
+//!     .with_color_space(ColorSpace::Srgb)
+//!     .with_color_correction(ColorCorrection::TYPICAL_SMD5050)
+//!     .with_brightness(128)
+//!     .with_mask(BrightnessMask::PerLed(&DIFFUSER_CAPS))
+//!     .with_power_limit(PowerLimit::new(500));
+//!
+//! led.write(pipeline.process(frame.iter().copied())).unwrap();
+//! ```
+
+use smart_leds_trait::RGB8;
+
+use crate::{
+    color::{scale, ChannelGamma, ColorCorrection, ColorSpace, Gamma, LedColor},
+    mask::BrightnessMask,
+    power::PowerLimit,
+};
+
+/// Color processing steps applied to every LED of a frame in one pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LedPipeline {
+    decode: Option<Gamma>,
+    gamma: Option<Gamma>,
+    channel_gamma: Option<ChannelGamma>,
+    correction: ColorCorrection,
+    brightness: u8,
+    mask: Option<BrightnessMask>,
+    power_limit: Option<PowerLimit>,
+}
+
+impl LedPipeline {
+    /// Create a new pipeline which passes colors through unchanged.
+    pub const fn new() -> Self {
+        Self {
+            decode: None,
+            gamma: None,
+            channel_gamma: None,
+            correction: ColorCorrection::UNCORRECTED,
+            brightness: 255,
+            mask: None,
+            power_limit: None,
+        }
+    }
+
+    /// Declare how the colors handed to the pipeline are encoded, and decode
+    /// sRGB colors into linear values first.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.set_color_space(color_space);
+        self
+    }
+
+    /// Apply the gamma curve after decoding the colors.
+    ///
+    /// This replaces the curves of [`LedPipeline::with_channel_gamma`].
+    pub fn with_gamma(mut self, gamma: Gamma) -> Self {
+        self.set_gamma(Some(gamma));
+        self
+    }
+
+    /// Apply a gamma curve for every channel after decoding the colors, in
+    /// place of the curve of [`LedPipeline::with_gamma`].
+    pub fn with_channel_gamma(mut self, gamma: ChannelGamma) -> Self {
+        self.set_channel_gamma(Some(gamma));
+        self
+    }
+
+    /// Apply the color correction after the gamma curve.
+    pub const fn with_color_correction(mut self, correction: ColorCorrection) -> Self {
+        self.correction = correction;
+        self
+    }
+
+    /// Scale every color by `brightness / 255` after the color correction.
+    pub const fn with_brightness(mut self, brightness: u8) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Cap the brightness of individual LEDs after the brightness.
+    pub const fn with_mask(mut self, mask: BrightnessMask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Scale the whole frame down to stay within the power limit, after all
+    /// other steps.
+    pub const fn with_power_limit(mut self, power_limit: PowerLimit) -> Self {
+        self.power_limit = Some(power_limit);
+        self
+    }
+
+    /// Change how the colors handed to the pipeline are encoded.
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.decode = match color_space {
+            ColorSpace::Linear => None,
+            ColorSpace::Srgb => Some(Gamma::srgb()),
+        };
+    }
+
+    /// Change the gamma curve, and remove the curves of every channel.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.gamma = gamma;
+        self.channel_gamma = None;
+    }
+
+    /// Change the curves of every channel, and remove the gamma curve.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.channel_gamma = gamma;
+        self.gamma = None;
+    }
+
+    /// Change the color correction.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.correction = correction;
+    }
+
+    /// Change the brightness, for example from a dimmer.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+
+    /// Change the brightness mask.
+    pub fn set_mask(&mut self, mask: Option<BrightnessMask>) {
+        self.mask = mask;
+    }
+
+    /// Change the power limit.
+    pub fn set_power_limit(&mut self, power_limit: Option<PowerLimit>) {
+        self.power_limit = power_limit;
+    }
+
+    /// The brightness
+    pub const fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// How the colors handed to the pipeline are encoded
+    pub const fn color_space(&self) -> ColorSpace {
+        match self.decode {
+            Some(_) => ColorSpace::Srgb,
+            None => ColorSpace::Linear,
+        }
+    }
+
+    /// Apply all steps except the brightness mask and the power limit to a
+    /// single color.
+    pub fn apply(&self, color: RGB8) -> RGB8 {
+        let color = match &self.decode {
+            Some(decode) => color.with_gamma(decode),
+            None => color,
+        };
+        let color = match (&self.gamma, &self.channel_gamma) {
+            (Some(gamma), _) => color.with_gamma(gamma),
+            (None, Some(gamma)) => color.with_channel_gamma(gamma),
+            (None, None) => color,
+        };
+        let color = self.correction.apply(color);
+
+        scale_color(color, self.brightness)
+    }
+
+    /// Apply all steps except the power limit to the color of the LED at
+    /// `index`.
+    pub fn apply_at(&self, index: usize, color: RGB8) -> RGB8 {
+        let color = self.apply(color);
+        match &self.mask {
+            Some(mask) => mask.apply_at(index, color),
+            None => color,
+        }
+    }
+
+    /// Apply all steps to every color of a frame.
+    ///
+    /// The iterator is walked twice if a power limit is set, once to estimate
+    /// the current and once to produce the processed colors.
+    pub fn process<'a, I>(&'a self, iterator: I) -> impl Iterator<Item = RGB8> + 'a
+    where
+        I: IntoIterator<Item = RGB8>,
+        I::IntoIter: Clone + 'a,
+    {
+        let iterator = iterator.into_iter().enumerate();
+        let limit = match &self.power_limit {
+            Some(power_limit) => power_limit.max_brightness(
+                iterator
+                    .clone()
+                    .map(|(index, color)| self.apply_at(index, color)),
+            ),
+            None => 255,
+        };
+
+        iterator.map(move |(index, color)| scale_color(self.apply_at(index, color), limit))
+    }
+}
+
+impl Default for LedPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scale every channel of `color` by `factor / 255`.
+const fn scale_color(color: RGB8, factor: u8) -> RGB8 {
+    RGB8::new(
+        scale(color.r, factor),
+        scale(color.g, factor),
+        scale(color.b, factor),
+    )
+}
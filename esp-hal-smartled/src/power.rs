@@ -0,0 +1,327 @@
+//! Power and current limiting.
+//!
+//! A strip of addressable LEDs can easily draw more current than a USB port or
+//! small power supply can deliver: a single WS2812 at full white draws around
+//! 60 mA, so 100 of them need 6 A. A [`PowerLimit`] estimates the current a
+//! frame will draw and scales the brightness of the whole frame down just
+//! enough to stay within a configured budget.
+//!
+//! Even within budget, a large strip lighting up at once after boot causes an
+//! inrush current which can brown out a weak USB supply. A [`SoftStart`]
+//! ramps the brightness from 0 up to the target over a configurable time,
+//! starting with the first frame.
+//!
+//! Densely packed fixtures heat up when they run bright for long, which
+//! shortens the life of the LEDs and can end in thermal runaway. A
+//! [`ThermalDerating`] takes readings of an external temperature sensor and
+//! lowers the brightness above configurable thresholds.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! // Stay below 500 mA on USB power
+//! let limit = PowerLimit::new(500);
+//!
+//! led.write(limit.limit(data.iter().copied())).unwrap();
+//!
+//! // Fade in over the first second
+//! let mut soft_start = SoftStart::new(1000);
+//!
+//! loop {
+//!     pipeline.set_brightness(soft_start.brightness(128));
+//!     led.write(pipeline.process(data.iter().copied())).unwrap();
+//! }
+//!
+//! // Dim above 60°C, down to a quarter at 80°C, and turn off at 90°C
+//! let mut derating = ThermalDerating::new(60.0, 80.0).with_shutdown(90.0);
+//!
+//! loop {
+//!     derating.update(sensor.read_celsius()?);
+//!     pipeline.set_brightness(derating.brightness(128));
+//!     led.write(pipeline.process(data.iter().copied())).unwrap();
+//! }
+//! ```
+
+#[cfg(feature = "esp-hal")]
+use esp_hal::time::{now, Instant};
+use smart_leds_trait::RGB8;
+
+use crate::color::scale;
+
+/// Current drawn by a single WS2812 showing full white, in milliamps
+const DEFAULT_LED_MILLIAMPS: u32 = 60;
+/// Current drawn by a single WS2812 which is turned off, in milliamps
+const DEFAULT_IDLE_MILLIAMPS: u32 = 1;
+
+/// Current budget for a strip of LEDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PowerLimit {
+    max_milliamps: u32,
+    led_milliamps: u32,
+    idle_milliamps: u32,
+}
+
+impl PowerLimit {
+    /// Create a new limit of `max_milliamps`, assuming WS2812 LEDs which draw
+    /// 60 mA at full white and 1 mA when off.
+    pub const fn new(max_milliamps: u32) -> Self {
+        Self {
+            max_milliamps,
+            led_milliamps: DEFAULT_LED_MILLIAMPS,
+            idle_milliamps: DEFAULT_IDLE_MILLIAMPS,
+        }
+    }
+
+    /// Set the current drawn by a single LED showing full white.
+    pub const fn with_led_milliamps(mut self, milliamps: u32) -> Self {
+        self.led_milliamps = milliamps;
+        self
+    }
+
+    /// Set the current drawn by a single LED which is turned off.
+    pub const fn with_idle_milliamps(mut self, milliamps: u32) -> Self {
+        self.idle_milliamps = milliamps;
+        self
+    }
+
+    /// The configured budget in milliamps
+    pub const fn max_milliamps(&self) -> u32 {
+        self.max_milliamps
+    }
+
+    /// Estimate the current drawn by the given frame, in milliamps.
+    pub fn estimate<I>(&self, iterator: I) -> u32
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        let (leds, active) = self.draw(iterator);
+
+        leds * self.idle_milliamps + active / (3 * 255)
+    }
+
+    /// The highest brightness at which the given frame stays within budget.
+    ///
+    /// The result uses the same scale as `smart_leds::brightness`, so 255
+    /// means the frame is shown unchanged.
+    pub fn max_brightness<I>(&self, iterator: I) -> u8
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        let (leds, active) = self.draw(iterator);
+
+        // Scale the budget that is left after the idle current into the
+        // 1..=256 multiplier used when scaling colors
+        let budget = self
+            .max_milliamps
+            .saturating_sub(leds * self.idle_milliamps) as u64;
+        let active = active as u64;
+        if budget * 3 * 255 >= active {
+            return 255;
+        }
+
+        ((budget * 3 * 255 * 256 / active).saturating_sub(1)).min(255) as u8
+    }
+
+    /// Scale the brightness of a frame down so it stays within budget.
+    ///
+    /// Frames which already fit the budget are passed through unchanged. The
+    /// iterator is walked twice, once to estimate the current and once to
+    /// produce the scaled colors.
+    pub fn limit<I>(&self, iterator: I) -> impl Iterator<Item = RGB8>
+    where
+        I: IntoIterator<Item = RGB8>,
+        I::IntoIter: Clone,
+    {
+        let iterator = iterator.into_iter();
+        let scale = self.max_brightness(iterator.clone()) as u16 + 1;
+
+        iterator.map(move |color| {
+            RGB8::new(
+                ((color.r as u16 * scale) >> 8) as u8,
+                ((color.g as u16 * scale) >> 8) as u8,
+                ((color.b as u16 * scale) >> 8) as u8,
+            )
+        })
+    }
+
+    /// Number of LEDs and the summed current of all channels, in units of
+    /// 1 / (3 * 255) milliamps.
+    fn draw<I>(&self, iterator: I) -> (u32, u32)
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        iterator.into_iter().fold((0, 0), |(leds, active), color| {
+            let sum = color.r as u32 + color.g as u32 + color.b as u32;
+            (leds + 1, active + sum * self.led_milliamps)
+        })
+    }
+}
+
+/// Ramps the brightness up from 0 after the first frame
+#[cfg(feature = "esp-hal")]
+#[derive(Debug, Clone, Copy)]
+pub struct SoftStart {
+    ramp_us: u64,
+    started: Option<Instant>,
+}
+
+#[cfg(feature = "esp-hal")]
+impl SoftStart {
+    /// Create a new ramp taking `ramp_ms` milliseconds, which starts with the
+    /// first call to [`SoftStart::brightness`] or [`SoftStart::ramp`].
+    pub const fn new(ramp_ms: u32) -> Self {
+        Self {
+            ramp_us: ramp_ms as u64 * 1000,
+            started: None,
+        }
+    }
+
+    /// The brightness to show the current frame at, on the way to `target`.
+    ///
+    /// The first call starts the ramp and returns 0.
+    pub fn brightness(&mut self, target: u8) -> u8 {
+        let started = *self.started.get_or_insert_with(now);
+        let elapsed = now()
+            .checked_duration_since(started)
+            .map_or(0, |elapsed| elapsed.to_micros());
+
+        if elapsed >= self.ramp_us {
+            return target;
+        }
+
+        (target as u64 * elapsed / self.ramp_us) as u8
+    }
+
+    /// Scale the brightness of a frame by the current step of the ramp.
+    pub fn ramp<I>(&mut self, iterator: I) -> impl Iterator<Item = RGB8>
+    where
+        I: IntoIterator<Item = RGB8>,
+    {
+        let brightness = self.brightness(255);
+
+        iterator.into_iter().map(move |color| {
+            RGB8::new(
+                scale(color.r, brightness),
+                scale(color.g, brightness),
+                scale(color.b, brightness),
+            )
+        })
+    }
+
+    /// Whether the ramp has reached the target brightness.
+    pub fn is_done(&self) -> bool {
+        self.started.is_some_and(|started| {
+            now()
+                .checked_duration_since(started)
+                .is_some_and(|elapsed| elapsed.to_micros() >= self.ramp_us)
+        })
+    }
+
+    /// Start the ramp again with the next frame, for example after turning
+    /// the power supply of the strip back on.
+    pub fn restart(&mut self) {
+        self.started = None;
+    }
+}
+
+/// Lowers the brightness as the temperature of a fixture rises
+///
+/// Up to the start temperature, the brightness is left unchanged. Above it,
+/// the brightness falls linearly until it reaches the floor at the full
+/// temperature, and the LEDs are turned off at the shutdown temperature. Once
+/// derated, the brightness only rises again after the temperature has fallen
+/// by the hysteresis, so a reading which hovers around a threshold does not
+/// make the LEDs flicker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ThermalDerating {
+    start_c: f32,
+    full_c: f32,
+    shutdown_c: Option<f32>,
+    floor: u8,
+    hysteresis_c: f32,
+    factor: u8,
+}
+
+impl ThermalDerating {
+    /// Create a new derating which starts at `start_c` degrees Celsius and
+    /// reaches a quarter of the brightness at `full_c`, with a hysteresis of
+    /// 2 degrees and no shutdown.
+    pub const fn new(start_c: f32, full_c: f32) -> Self {
+        Self {
+            start_c,
+            full_c,
+            shutdown_c: None,
+            floor: 64,
+            hysteresis_c: 2.0,
+            factor: 255,
+        }
+    }
+
+    /// Set the brightness left at the full temperature and above, where 255
+    /// is full brightness.
+    pub const fn with_floor(mut self, floor: u8) -> Self {
+        self.floor = floor;
+        self
+    }
+
+    /// Turn the LEDs off at and above `shutdown_c` degrees Celsius.
+    pub const fn with_shutdown(mut self, shutdown_c: f32) -> Self {
+        self.shutdown_c = Some(shutdown_c);
+        self
+    }
+
+    /// Set how far the temperature has to fall before the brightness rises
+    /// again, in degrees Celsius.
+    pub const fn with_hysteresis(mut self, hysteresis_c: f32) -> Self {
+        self.hysteresis_c = hysteresis_c;
+        self
+    }
+
+    /// Feed a new temperature reading in degrees Celsius, and return the
+    /// factor brightness is scaled by, where 255 leaves it unchanged.
+    pub fn update(&mut self, temperature_c: f32) -> u8 {
+        let falling = self.factor_at(temperature_c);
+        let rising = self.factor_at(temperature_c + self.hysteresis_c);
+
+        if falling < self.factor {
+            self.factor = falling;
+        } else if rising > self.factor {
+            self.factor = rising;
+        }
+
+        self.factor
+    }
+
+    /// The factor brightness is scaled by after the last reading, where 255
+    /// leaves it unchanged
+    pub const fn factor(&self) -> u8 {
+        self.factor
+    }
+
+    /// The brightness to show frames at instead of `target`.
+    pub const fn brightness(&self, target: u8) -> u8 {
+        scale(target, self.factor)
+    }
+
+    /// The factor for a temperature, without hysteresis
+    fn factor_at(&self, temperature_c: f32) -> u8 {
+        if self
+            .shutdown_c
+            .is_some_and(|shutdown_c| temperature_c >= shutdown_c)
+        {
+            return 0;
+        }
+        if temperature_c <= self.start_c {
+            return 255;
+        }
+        if temperature_c >= self.full_c {
+            return self.floor;
+        }
+
+        let position = (temperature_c - self.start_c) / (self.full_c - self.start_c);
+        let range = (255 - self.floor) as f32;
+        (255.0 - libm::roundf(range * position)) as u8
+    }
+}
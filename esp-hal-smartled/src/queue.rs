@@ -0,0 +1,137 @@
+//! A queue of frames between a rendering task and the LED task.
+//!
+//! Rendering work is often bursty: a frame which decodes an image or runs a
+//! simulation step can take several frame intervals, while the frames around
+//! it are cheap. Rendering in the LED task makes the frame rate stutter. With
+//! the `embassy-sync` feature, a [`FrameQueue`] lets one task render frames
+//! ahead of time with a [`FrameProducer`], while the LED task takes them with
+//! a [`FrameConsumer`] and writes them at a steady rate. The jitter is
+//! absorbed as long as the queue does not run empty.
+//!
+//! The frames are stored in a buffer provided by the application, and are
+//! rendered and written in place, so they are never copied.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let frames = mk_static!([[RGB8; 64]; 4], [[RGB8::default(); 64]; 4]);
+//! let queue = mk_static!(FrameQueue<'static, 64>, FrameQueue::new(frames));
+//! let (mut producer, mut consumer) = queue.split();
+//!
+//! #[embassy_executor::task]
+//! async fn render(mut producer: FrameProducer<'static, 64>) {
+//!     loop {
+//!         producer.render(|frame| plasma.render(frame)).await;
+//!     }
+//! }
+//!
+//! #[embassy_executor::task]
+//! async fn leds(mut consumer: FrameConsumer<'static, 64>, mut led: Adapter) {
+//!     let error = consumer.run(&mut led, 60).await;
+//!     panic!("LED task failed: {error:?}");
+//! }
+//! ```
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    zerocopy_channel::{Channel, Receiver, Sender},
+};
+use embassy_time::{Duration, Ticker};
+use smart_leds_trait::{SmartLedsWriteAsync, RGB8};
+
+/// Fixed-capacity queue of frames of `LEDS` LEDs
+pub struct FrameQueue<'a, const LEDS: usize> {
+    channel: Channel<'a, CriticalSectionRawMutex, [RGB8; LEDS]>,
+}
+
+impl<'a, const LEDS: usize> FrameQueue<'a, LEDS> {
+    /// Create a new queue holding up to `frames.len()` frames.
+    pub fn new(frames: &'a mut [[RGB8; LEDS]]) -> Self {
+        Self {
+            channel: Channel::new(frames),
+        }
+    }
+
+    /// Split the queue into its producer and its consumer.
+    pub fn split(&mut self) -> (FrameProducer<'_, LEDS>, FrameConsumer<'_, LEDS>) {
+        let (sender, receiver) = self.channel.split();
+        (FrameProducer { sender }, FrameConsumer { receiver })
+    }
+}
+
+/// Renders frames into a [`FrameQueue`]
+pub struct FrameProducer<'a, const LEDS: usize> {
+    sender: Sender<'a, CriticalSectionRawMutex, [RGB8; LEDS]>,
+}
+
+impl<const LEDS: usize> FrameProducer<'_, LEDS> {
+    /// Wait for a free frame, render it with `render`, and queue it.
+    ///
+    /// The frame holds whatever was rendered into it before, so `render` has
+    /// to set every LED.
+    pub async fn render<F>(&mut self, render: F)
+    where
+        F: FnOnce(&mut [RGB8; LEDS]),
+    {
+        render(self.sender.send().await);
+        self.sender.send_done();
+    }
+
+    /// Render and queue a frame if one is free, and return whether a frame
+    /// was rendered.
+    pub fn try_render<F>(&mut self, render: F) -> bool
+    where
+        F: FnOnce(&mut [RGB8; LEDS]),
+    {
+        let Some(frame) = self.sender.try_send() else {
+            return false;
+        };
+        render(frame);
+        self.sender.send_done();
+
+        true
+    }
+}
+
+/// Takes frames from a [`FrameQueue`] and writes them to the LEDs
+pub struct FrameConsumer<'a, const LEDS: usize> {
+    receiver: Receiver<'a, CriticalSectionRawMutex, [RGB8; LEDS]>,
+}
+
+impl<const LEDS: usize> FrameConsumer<'_, LEDS> {
+    /// Wait for the next frame, and write it to the adapter.
+    ///
+    /// The frame is freed for the producer once it has been written, even if
+    /// writing it failed.
+    pub async fn write_next<A>(&mut self, adapter: &mut A) -> Result<(), A::Error>
+    where
+        A: SmartLedsWriteAsync<Color = RGB8>,
+    {
+        let frame = self.receiver.receive().await;
+        let result = adapter.write(frame.iter().copied()).await;
+        self.receiver.receive_done();
+
+        result
+    }
+
+    /// Write the queued frames at `fps` frames per second, until writing to
+    /// the LEDs fails.
+    ///
+    /// If the queue runs empty, the LEDs keep showing the last frame, and the
+    /// next one is written as soon as it is queued. Returns the error of the
+    /// failed write.
+    pub async fn run<A>(&mut self, adapter: &mut A, fps: u32) -> A::Error
+    where
+        A: SmartLedsWriteAsync<Color = RGB8>,
+    {
+        let mut ticker = Ticker::every(Duration::from_micros(1_000_000 / fps.max(1) as u64));
+
+        loop {
+            if let Err(error) = self.write_next(adapter).await {
+                return error;
+            }
+
+            ticker.next().await;
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! A ready-made LED task for Embassy.
+//!
+//! Most applications with Embassy have a task which owns the LEDs, renders an
+//! effect at a fixed frame rate, and takes requests from the rest of the
+//! application, such as showing a different effect. With the `embassy-sync`
+//! feature, an [`LedRunner`] does all of this: it owns an async adapter and a
+//! frame buffer, renders its [`Effect`] on every tick of an
+//! `embassy_time::Ticker`, and applies the [`Command`]s sent to it over a
+//! [`CommandChannel`].
+//!
+//! Commands are applied before rendering the next frame, so they take effect
+//! within one frame interval.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! static COMMANDS: CommandChannel<Rainbow, 4> = CommandChannel::new();
+//!
+//! #[embassy_executor::task]
+//! async fn leds(led: SmartLedsAdapterAsync<Channel<Async, 0>, { buffer_size_async(1) }>) {
+//!     let mut runner = LedRunner::<_, _, 1>::new(led, Rainbow::default(), 50);
+//!     let error = runner.run(COMMANDS.receiver()).await;
+//!     panic!("LED task failed: {error:?}");
+//! }
+//!
+//! // Anywhere else in the application
+//! COMMANDS.send(Command::SetBrightness(32)).await;
+//! ```
+
+use embassy_sync::{
+    blocking_mutex::raw::{CriticalSectionRawMutex, RawMutex},
+    channel::{Channel, Receiver},
+};
+use embassy_time::{Duration, Ticker};
+use smart_leds_trait::{SmartLedsWriteAsync, RGB8};
+
+use crate::color::scale;
+
+/// Channel to send commands to an [`LedRunner`] from any task or interrupt
+pub type CommandChannel<E, const N: usize> = Channel<CriticalSectionRawMutex, Command<E>, N>;
+
+/// An animated effect rendered by an [`LedRunner`]
+pub trait Effect {
+    /// Render the frame at `tick`, the number of frames since the effect was
+    /// started.
+    fn render(&mut self, frame: &mut [RGB8], tick: u32);
+}
+
+impl<F> Effect for F
+where
+    F: FnMut(&mut [RGB8], u32),
+{
+    fn render(&mut self, frame: &mut [RGB8], tick: u32) {
+        self(frame, tick)
+    }
+}
+
+/// A request to an [`LedRunner`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command<E> {
+    /// Start showing the effect, from its first frame
+    SetEffect(E),
+    /// Show all LEDs in one color, instead of the effect
+    SetColor(RGB8),
+    /// Scale the brightness of every frame by `brightness / 255`
+    SetBrightness(u8),
+    /// Turn all LEDs off, until the next effect or color is set
+    Off,
+}
+
+/// What an [`LedRunner`] currently shows
+enum Mode<E> {
+    Effect(E),
+    Color(RGB8),
+}
+
+/// Renders an effect to a strip of `LEDS` LEDs at a fixed frame rate
+pub struct LedRunner<A, E, const LEDS: usize> {
+    adapter: A,
+    frame: [RGB8; LEDS],
+    mode: Mode<E>,
+    brightness: u8,
+    tick: u32,
+    interval: Duration,
+}
+
+impl<A, E, const LEDS: usize> LedRunner<A, E, LEDS>
+where
+    A: SmartLedsWriteAsync<Color = RGB8>,
+    E: Effect,
+{
+    /// Create a new runner showing the effect at `fps` frames per second.
+    pub fn new(adapter: A, effect: E, fps: u32) -> Self {
+        Self {
+            adapter,
+            frame: [RGB8::default(); LEDS],
+            mode: Mode::Effect(effect),
+            brightness: 255,
+            tick: 0,
+            interval: Duration::from_micros(1_000_000 / fps.max(1) as u64),
+        }
+    }
+
+    /// The brightness every frame is scaled by
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// The frame written last, before scaling it by the brightness
+    pub fn frame(&self) -> &[RGB8; LEDS] {
+        &self.frame
+    }
+
+    /// Apply a command.
+    pub fn apply(&mut self, command: Command<E>) {
+        match command {
+            Command::SetEffect(effect) => {
+                self.mode = Mode::Effect(effect);
+                self.tick = 0;
+            }
+            Command::SetColor(color) => self.mode = Mode::Color(color),
+            Command::SetBrightness(brightness) => self.brightness = brightness,
+            Command::Off => self.mode = Mode::Color(RGB8::default()),
+        }
+    }
+
+    /// Render the next frame and write it to the LEDs.
+    pub async fn step(&mut self) -> Result<(), A::Error> {
+        match &mut self.mode {
+            Mode::Effect(effect) => {
+                effect.render(&mut self.frame, self.tick);
+                self.tick = self.tick.wrapping_add(1);
+            }
+            Mode::Color(color) => self.frame.fill(*color),
+        }
+
+        let brightness = self.brightness;
+        self.adapter
+            .write(self.frame.iter().map(|color| {
+                RGB8::new(
+                    scale(color.r, brightness),
+                    scale(color.g, brightness),
+                    scale(color.b, brightness),
+                )
+            }))
+            .await
+    }
+
+    /// Show the effect, applying the commands received on `commands`, until
+    /// writing to the LEDs fails.
+    ///
+    /// Returns the error of the failed write.
+    pub async fn run<M, const N: usize>(
+        &mut self,
+        commands: Receiver<'_, M, Command<E>, N>,
+    ) -> A::Error
+    where
+        M: RawMutex,
+    {
+        let mut ticker = Ticker::every(self.interval);
+
+        loop {
+            while let Ok(command) = commands.try_receive() {
+                self.apply(command);
+            }
+
+            if let Err(error) = self.step().await {
+                return error;
+            }
+
+            ticker.next().await;
+        }
+    }
+
+    /// Give back the adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
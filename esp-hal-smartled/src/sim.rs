@@ -0,0 +1,261 @@
+//! Simulation of an adapter, for testing without LEDs.
+//!
+//! With the `std` feature, a [`SimAdapter`] implements the same `smart-leds`
+//! traits as the adapters, and encodes frames with the same code, but records
+//! them instead of sending them. This lets the encoding, color order, gamma
+//! curve and color correction, as well as effects built on top of the traits,
+//! be checked in unit tests.
+//!
+//! Every recorded [`SimFrame`] holds the pulse codes which would have been
+//! sent, and decodes them back into the bytes of every LED, in the order they
+//! are sent.
+//!
+//! The simulation does not use any peripheral. Without a chip feature, the
+//! crate does not depend on `esp-hal`, and only builds the encoding and the
+//! simulation, so the tests run on the host with `cargo test --features std`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut led = SimAdapter::new_with_chip(Ws2812b);
+//! led.set_gamma(Some(Gamma::new(2.2)));
+//!
+//! led.write([RGB8::new(255, 0, 0)]).unwrap();
+//!
+//! // Green is sent first
+//! assert_eq!(led.last_frame().unwrap().leds()[0], [0, 255, 0, 0]);
+//! ```
+
+extern crate std;
+
+use std::vec::Vec;
+
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync};
+
+use crate::{
+    chip::{ColorOrder, LedChip, Sk68xx},
+    color::{Calibration, ChannelGamma, ColorCorrection, Gamma, WhiteExtraction},
+    encoder::PulseEncoder,
+    timing::Timing,
+    LedAdapterError,
+};
+
+/// Tick rate pulses are encoded for by default, in MHz
+const DEFAULT_SRC_CLOCK: u32 = 80;
+
+/// Adapter recording the frames written to it
+pub struct SimAdapter<Chip = Sk68xx>
+where
+    Chip: LedChip,
+{
+    frames: Vec<SimFrame>,
+    encoder: PulseEncoder<Chip>,
+}
+
+impl SimAdapter {
+    /// Create a new simulated adapter for [`Sk68xx`] LEDs.
+    pub fn new() -> Self {
+        Self::new_with_chip(Sk68xx)
+    }
+}
+
+impl Default for SimAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Chip> SimAdapter<Chip>
+where
+    Chip: LedChip,
+{
+    /// Create a new simulated adapter for the given type of LED chip.
+    pub fn new_with_chip(_chip: Chip) -> Self {
+        Self {
+            frames: Vec::new(),
+            encoder: PulseEncoder::new(Chip::TIMING, DEFAULT_SRC_CLOCK, false),
+        }
+    }
+
+    /// Change the tick rate pulses are encoded for, in MHz.
+    ///
+    /// The adapters use the frequency passed to their constructor, divided by
+    /// the `clk_divider` of the channel; this defaults to 80 MHz. Rates too low for the timing profile make
+    /// writes fail with [`LedAdapterError::InsufficientResolution`].
+    pub fn set_src_clock(&mut self, src_clock: u32) {
+        self.encoder.set_src_clock(src_clock);
+    }
+
+    /// Change the timing profile.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.encoder.set_timing(timing);
+    }
+
+    /// Set the color correction applied to every LED while encoding.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.encoder.set_color_correction(correction);
+    }
+
+    /// Set the gamma curve applied to every LED while encoding, before the
+    /// color correction.
+    ///
+    /// This replaces per-channel curves set with `set_channel_gamma`.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.encoder.set_gamma(gamma);
+    }
+
+    /// Set a gamma curve for every channel, applied to every LED while
+    /// encoding in place of the curve set with `set_gamma`.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.encoder.set_channel_gamma(gamma);
+    }
+
+    /// Set how the white channel of RGBW LEDs is computed while encoding,
+    /// after the color correction.
+    ///
+    /// This has no effect on LEDs without a white channel.
+    pub fn set_white_extraction(&mut self, extraction: Option<WhiteExtraction>) {
+        self.encoder.set_white_extraction(extraction);
+    }
+
+    /// Set the calibration of the strip, which scales every channel after all
+    /// other adjustments.
+    ///
+    /// This matches strips from different batches, so they show the same
+    /// colors.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.encoder.set_calibration(calibration);
+    }
+
+    /// Override the order in which the chip expects the color channels, or
+    /// restore it with `None`.
+    pub fn set_color_order(&mut self, order: Option<ColorOrder>) {
+        self.encoder.set_color_order(order);
+    }
+
+    /// All frames written so far, oldest first
+    pub fn frames(&self) -> &[SimFrame] {
+        &self.frames
+    }
+
+    /// The frame written last
+    pub fn last_frame(&self) -> Option<&SimFrame> {
+        self.frames.last()
+    }
+
+    /// Forget all recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Encode and record a frame.
+    fn record<T, I>(&mut self, iterator: T) -> Result<(), LedAdapterError>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Chip::Color>,
+    {
+        self.encoder.check_resolution()?;
+
+        let colors: Vec<Chip::Color> = iterator.into_iter().map(Into::into).collect();
+
+        let mut codes = std::vec![0; colors.len() * Chip::BITS_PER_LED + 1];
+        self.encoder.encode(&mut codes, colors)?;
+
+        self.frames.push(SimFrame {
+            codes,
+            one: self
+                .encoder
+                .timing()
+                .pulses(self.encoder.src_clock(), false)
+                .1,
+            channels: Chip::CHANNELS,
+        });
+
+        Ok(())
+    }
+}
+
+impl<Chip> SmartLedsWrite for SimAdapter<Chip>
+where
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Encode all color items of the iterator, and record the frame.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.record(iterator)
+    }
+}
+
+impl<Chip> SmartLedsWriteAsync for SimAdapter<Chip>
+where
+    Chip: LedChip,
+{
+    type Error = LedAdapterError;
+    type Color = Chip::Color;
+
+    /// Encode all color items of the iterator, and record the frame.
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.record(iterator)
+    }
+}
+
+/// A frame recorded by a [`SimAdapter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimFrame {
+    codes: Vec<u32>,
+    // Pulse code of a 1 bit
+    one: u32,
+    channels: usize,
+}
+
+impl SimFrame {
+    /// The pulse codes of the frame, including the end delimiter
+    pub fn codes(&self) -> &[u32] {
+        &self.codes
+    }
+
+    /// Number of LEDs in the frame
+    pub fn len(&self) -> usize {
+        self.codes.len() / (self.channels * 8)
+    }
+
+    /// Whether the frame has no LEDs
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bytes of the frame, decoded from the pulse codes in the order they
+    /// are sent
+    pub fn bytes(&self) -> Vec<u8> {
+        self.codes[..self.codes.len() - 1]
+            .chunks_exact(8)
+            .map(|byte| {
+                byte.iter()
+                    .fold(0, |value, code| value << 1 | (*code == self.one) as u8)
+            })
+            .collect()
+    }
+
+    /// The bytes of every LED, in the order they are sent, with unused
+    /// channels set to 0, like [`LedChip::bytes`]
+    pub fn leds(&self) -> Vec<[u8; 4]> {
+        self.bytes()
+            .chunks_exact(self.channels)
+            .map(|channels| {
+                let mut led = [0; 4];
+                led[..channels.len()].copy_from_slice(channels);
+                led
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,245 @@
+//! Driving single-wire LEDs from the MOSI pin of an SPI peripheral.
+//!
+//! Chips without an RMT peripheral can still drive LEDs of the WS2812 family
+//! by clocking a bit pattern out of an SPI peripheral at 3.2 MHz. Every bit of
+//! a color is sent as 4 SPI bits: `1000` for a `0` and `1110` for a `1`, which
+//! gives high pulses of about 310 ns and 940 ns in a period of 1.25 µs. The
+//! frame is followed by enough zero bytes to hold the line low for the reset
+//! time.
+//!
+//! Unlike the RMT adapters, the pulse timings are fixed by the SPI clock, so
+//! the [`Timing`](crate::timing::Timing) of the chip is not used. The timings
+//! above are within the tolerances of the WS2812, WS2812B, SK6812 and their
+//! clones. Only the MOSI pin is needed.
+//!
+//! The adapter works with every implementation of
+//! [`embedded_hal::spi::SpiBus`], including the SPI driver of `esp-hal`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let config = Config {
+//!     frequency: spi::FREQUENCY.kHz(),
+//!     ..Config::default()
+//! };
+//! let spi = Spi::new_with_config(peripherals.SPI2, config).with_mosi(peripherals.GPIO7);
+//!
+//! let spi_buffer = [0u8; spi::buffer_size(NUM_LEDS)];
+//! let mut led = SpiAdapter::new(spi, spi_buffer);
+//! led.write(data.iter().cloned()).unwrap();
+//! ```
+
+use core::{
+    fmt::{self, Debug, Display},
+    marker::PhantomData,
+};
+
+use embedded_hal::spi::SpiBus;
+use smart_leds_trait::SmartLedsWrite;
+
+use crate::{
+    chip::{LedChip, Sk68xx},
+    color::{Adjustments, ChannelGamma, ColorCorrection, Gamma},
+};
+
+/// Frequency of the SPI clock the adapter expects, in kHz
+pub const FREQUENCY: u32 = 3_200;
+
+/// Number of zero bytes sent after every frame, which hold the line low for
+/// 80 µs
+pub(crate) const RESET_BYTES: usize = 32;
+
+/// SPI bits sent for a `0` and a `1` bit
+const CODES: [u8; 2] = [0b1000, 0b1110];
+
+/// Number of bytes needed to address `num_leds` RGB LEDs
+///
+/// Every LED takes 12 bytes (3 channels of 8 bits, with 4 SPI bits per bit),
+/// and the frame is followed by 32 bytes for the reset.
+pub const fn buffer_size(num_leds: usize) -> usize {
+    num_leds * 12 + RESET_BYTES
+}
+
+/// Number of bytes needed to address `num_leds` RGBW LEDs
+///
+/// Every LED takes 16 bytes (4 channels of 8 bits, with 4 SPI bits per bit),
+/// and the frame is followed by 32 bytes for the reset.
+pub const fn buffer_size_rgbw(num_leds: usize) -> usize {
+    num_leds * 16 + RESET_BYTES
+}
+
+/// All types of errors that can happen during the conversion and transmission
+/// of LED commands with the [`SpiAdapter`]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SpiAdapterError<E> {
+    /// Raised in the event that the provided data container is not large enough
+    ///
+    /// The frame needs a buffer of `required` bytes, but the adapter was
+    /// created with a buffer of only `available` bytes. Nothing has been sent
+    /// to the LEDs. Size the buffer for the number of LEDs on the strip, using
+    /// [`buffer_size`], or write shorter frames.
+    BufferSizeExceeded {
+        /// Index of the first LED which did not fit into the buffer
+        led: usize,
+        /// Number of bytes needed for the whole frame
+        required: usize,
+        /// Number of bytes in the buffer
+        available: usize,
+    },
+    /// Raised if the SPI bus fails to send the frame
+    TransmissionError(E),
+}
+
+impl<E> Display for SpiAdapterError<E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpiAdapterError::BufferSizeExceeded {
+                led,
+                required,
+                available,
+            } => write!(
+                f,
+                "LED {led} does not fit into the buffer: the frame needs {required} bytes, but the \
+                 buffer holds {available}"
+            ),
+            SpiAdapterError::TransmissionError(e) => write!(f, "SPI transmission failed: {e:?}"),
+        }
+    }
+}
+
+impl<E> core::error::Error for SpiAdapterError<E> where E: Debug {}
+
+/// Adapter driving single-wire LEDs from the MOSI pin of an SPI bus
+///
+/// The bus must be clocked at [`FREQUENCY`].
+pub struct SpiAdapter<SPI, const BUFFER_SIZE: usize, Chip = Sk68xx>
+where
+    SPI: SpiBus<u8>,
+    Chip: LedChip,
+{
+    spi: SPI,
+    buffer: [u8; BUFFER_SIZE],
+    adjustments: Adjustments,
+    chip: PhantomData<Chip>,
+}
+
+impl<SPI, const BUFFER_SIZE: usize> SpiAdapter<SPI, BUFFER_SIZE>
+where
+    SPI: SpiBus<u8>,
+{
+    /// Create a new adapter writing to the given SPI bus.
+    pub fn new(spi: SPI, buffer: [u8; BUFFER_SIZE]) -> Self {
+        Self::new_with_chip(spi, buffer, Sk68xx)
+    }
+}
+
+impl<SPI, const BUFFER_SIZE: usize, Chip> SpiAdapter<SPI, BUFFER_SIZE, Chip>
+where
+    SPI: SpiBus<u8>,
+    Chip: LedChip,
+{
+    /// Create a new adapter writing to the given SPI bus, for the given type
+    /// of LED chip.
+    pub fn new_with_chip(spi: SPI, buffer: [u8; BUFFER_SIZE], _chip: Chip) -> Self {
+        Self {
+            spi,
+            buffer,
+            adjustments: Adjustments::default(),
+            chip: PhantomData,
+        }
+    }
+
+    /// Set the color correction applied to every LED while encoding.
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.adjustments.correction = correction;
+    }
+
+    /// Set the gamma curve applied to every LED while encoding, before the
+    /// color correction.
+    ///
+    /// This replaces per-channel curves set with `set_channel_gamma`.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.adjustments.set_gamma(gamma);
+    }
+
+    /// Set a gamma curve for every channel, applied to every LED while
+    /// encoding in place of the curve set with `set_gamma`.
+    pub fn set_channel_gamma(&mut self, gamma: Option<ChannelGamma>) {
+        self.adjustments.set_channel_gamma(gamma);
+    }
+
+    /// Number of LEDs which fit into the buffer along with the reset.
+    pub fn capacity_leds(&self) -> usize {
+        BUFFER_SIZE.saturating_sub(RESET_BYTES) / (Chip::CHANNELS * 4)
+    }
+
+    /// Release the SPI bus.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI, const BUFFER_SIZE: usize, Chip> SmartLedsWrite for SpiAdapter<SPI, BUFFER_SIZE, Chip>
+where
+    SPI: SpiBus<u8>,
+    Chip: LedChip,
+{
+    type Error = SpiAdapterError<SPI::Error>;
+    type Color = Chip::Color;
+
+    /// Convert all color items of the iterator to SPI bit patterns in the
+    /// internal buffer, then send the buffer in a single SPI transfer.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let len = Chip::CHANNELS * 4;
+        let mut iterator = iterator.into_iter();
+        let mut position = 0;
+
+        for (index, item) in iterator.by_ref().enumerate() {
+            // Make sure there is room for the LED and the reset
+            if position + len + RESET_BYTES > BUFFER_SIZE {
+                return Err(SpiAdapterError::BufferSizeExceeded {
+                    led: index,
+                    required: (index + 1 + iterator.count()) * len + RESET_BYTES,
+                    available: BUFFER_SIZE,
+                });
+            }
+
+            let bytes = Chip::bytes(self.adjustments.apply(item.into()));
+            for (byte, out) in bytes[..Chip::CHANNELS]
+                .iter()
+                .zip(self.buffer[position..position + len].chunks_exact_mut(4))
+            {
+                for (pair, code) in out.iter_mut().enumerate() {
+                    let high = (byte >> (7 - 2 * pair)) & 1;
+                    let low = (byte >> (6 - 2 * pair)) & 1;
+                    *code = (CODES[high as usize] << 4) | CODES[low as usize];
+                }
+            }
+            position += len;
+        }
+
+        if position + RESET_BYTES > BUFFER_SIZE {
+            return Err(SpiAdapterError::BufferSizeExceeded {
+                led: 0,
+                required: RESET_BYTES,
+                available: BUFFER_SIZE,
+            });
+        }
+
+        self.buffer[position..position + RESET_BYTES].fill(0);
+        position += RESET_BYTES;
+
+        self.spi
+            .write(&self.buffer[..position])
+            .and_then(|_| self.spi.flush())
+            .map_err(SpiAdapterError::TransmissionError)
+    }
+}
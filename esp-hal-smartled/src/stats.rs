@@ -0,0 +1,223 @@
+//! Throughput and frame rate statistics.
+//!
+//! With the `stats` feature, both adapters measure how long encoding and
+//! transmitting every frame takes, and summarize the measurements over a
+//! window of time, one second by default. The summary of the last complete
+//! window is returned by `stats()`, which is cheap enough to call every frame.
+//!
+//! Encoding includes the color adjustments, and transmitting includes the
+//! reset time at the end of the frame. Time spent waiting for the frame rate
+//! limit is not counted, and neither is the transmission of frames started
+//! with [`SmartLedsAdapter::start_write`](crate::SmartLedsAdapter::start_write),
+//! which runs in the background.
+//!
+//! The adapters also count problems with the signal since they were created,
+//! returned by `signal_health()` as a [`SignalHealth`]: failed transmissions,
+//! underruns, where the channel ran out of pulse codes, and late refills of
+//! the memory of the channel. The RMT driver does not report late refills,
+//! so a transmission is counted as one if it takes more than 20µs longer
+//! than its pulses, which means the line was idle in between. Rising counts
+//! on a device in the field point to interrupts which delay the refills.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! led.set_stats_window(5_000);
+//!
+//! loop {
+//!     led.write(next_frame()).unwrap();
+//!
+//!     let stats = led.stats();
+//!     println!(
+//!         "{} fps, encode {} µs, transmit {} µs",
+//!         stats.fps, stats.encode_us, stats.transmit_us
+//!     );
+//!
+//!     let health = led.signal_health();
+//!     if health.late_refills > 0 {
+//!         println!("{} late refills", health.late_refills);
+//!     }
+//! }
+//! ```
+
+use core::mem;
+
+use esp_hal::{
+    rmt::Error as RmtError,
+    time::{now, Instant},
+};
+
+/// Default length of the window statistics are collected over, in
+/// milliseconds
+const DEFAULT_WINDOW_MS: u32 = 1_000;
+
+/// Statistics of the frames sent in a window of time
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Number of frames sent
+    pub frames: u32,
+    /// Frames sent per second
+    pub fps: f32,
+    /// Average time to encode a frame, in microseconds
+    pub encode_us: u32,
+    /// Longest time to encode a frame, in microseconds
+    pub max_encode_us: u32,
+    /// Average time to transmit a frame, in microseconds
+    pub transmit_us: u32,
+    /// Longest time to transmit a frame, in microseconds
+    pub max_transmit_us: u32,
+}
+
+/// How much longer than its pulses a transmission may take before it counts
+/// as a late refill, in microseconds
+pub(crate) const LATE_REFILL_SLACK_US: u64 = 20;
+
+/// Counters of problems with the signal of an adapter
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SignalHealth {
+    /// Number of transmissions which failed, including underruns
+    pub errors: u32,
+    /// Number of transmissions which failed because the channel ran out of
+    /// pulse codes
+    pub underruns: u32,
+    /// Number of transmissions which took longer than their pulses, because
+    /// the memory of the channel was refilled late
+    pub late_refills: u32,
+}
+
+/// Collects the measurements of an adapter
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StatsRecorder {
+    window_us: u64,
+    window_start: Option<Instant>,
+    frames: u32,
+    encode_us: u64,
+    max_encode_us: u32,
+    transmit_us: u64,
+    max_transmit_us: u32,
+    // Measurements of the frame which is being sent
+    frame_encode_us: u64,
+    frame_transmit_us: u64,
+    stats: Stats,
+    health: SignalHealth,
+}
+
+impl StatsRecorder {
+    /// Change the length of the window, in milliseconds.
+    pub(crate) fn set_window(&mut self, window_ms: u32) {
+        self.window_us = window_ms.max(1) as u64 * 1000;
+    }
+
+    /// Statistics of the last complete window
+    pub(crate) fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Problems with the signal since the counters were reset
+    pub(crate) fn health(&self) -> SignalHealth {
+        self.health
+    }
+
+    /// Reset the counters of problems with the signal.
+    pub(crate) fn reset_health(&mut self) {
+        self.health = SignalHealth::default();
+    }
+
+    /// Count a failed transmission.
+    pub(crate) fn count_error(&mut self, error: &RmtError) {
+        self.health.errors += 1;
+        if matches!(error, RmtError::TransmissionError) {
+            self.health.underruns += 1;
+        }
+    }
+
+    /// Count a transmission which took longer than its pulses.
+    pub(crate) fn count_late_refill(&mut self) {
+        self.health.late_refills += 1;
+    }
+
+    /// Set the encoding time of the next frame, in microseconds.
+    pub(crate) fn set_encode(&mut self, encode_us: u64) {
+        self.frame_encode_us = encode_us;
+    }
+
+    /// Add transmission time to the current frame, in microseconds.
+    pub(crate) fn add_transmit(&mut self, transmit_us: u64) {
+        self.frame_transmit_us += transmit_us;
+    }
+
+    /// Finish a frame which started at `since`, counting everything but the
+    /// transmissions as encoding time.
+    pub(crate) fn finish_frame_since(&mut self, since: Instant) {
+        self.frame_encode_us = elapsed_us(since).saturating_sub(self.frame_transmit_us);
+        self.finish_frame();
+    }
+
+    /// Finish the current frame, and summarize the window once it is over.
+    pub(crate) fn finish_frame(&mut self) {
+        let encode_us = mem::take(&mut self.frame_encode_us);
+        let transmit_us = mem::take(&mut self.frame_transmit_us);
+
+        // The first window starts at the end of the first frame
+        let Some(window_start) = self.window_start else {
+            self.window_start = Some(now());
+            return;
+        };
+
+        self.frames += 1;
+        self.encode_us += encode_us;
+        self.max_encode_us = self.max_encode_us.max(encode_us as u32);
+        self.transmit_us += transmit_us;
+        self.max_transmit_us = self.max_transmit_us.max(transmit_us as u32);
+
+        let window_us = elapsed_us(window_start);
+        if window_us < self.window_us {
+            return;
+        }
+
+        let frames = self.frames;
+        self.stats = Stats {
+            frames,
+            fps: frames as f32 * 1_000_000.0 / window_us as f32,
+            encode_us: (self.encode_us / frames as u64) as u32,
+            max_encode_us: self.max_encode_us,
+            transmit_us: (self.transmit_us / frames as u64) as u32,
+            max_transmit_us: self.max_transmit_us,
+        };
+
+        *self = Self {
+            window_us: self.window_us,
+            window_start: Some(now()),
+            stats: self.stats,
+            health: self.health,
+            ..Self::default()
+        };
+    }
+}
+
+impl Default for StatsRecorder {
+    fn default() -> Self {
+        Self {
+            window_us: DEFAULT_WINDOW_MS as u64 * 1000,
+            window_start: None,
+            frames: 0,
+            encode_us: 0,
+            max_encode_us: 0,
+            transmit_us: 0,
+            max_transmit_us: 0,
+            frame_encode_us: 0,
+            frame_transmit_us: 0,
+            stats: Stats::default(),
+            health: SignalHealth::default(),
+        }
+    }
+}
+
+/// Time since `since`, in microseconds
+pub(crate) fn elapsed_us(since: Instant) -> u64 {
+    now()
+        .checked_duration_since(since)
+        .map_or(0, |elapsed| elapsed.to_micros())
+}
@@ -0,0 +1,366 @@
+//! A single RGB LED used as a status indicator.
+//!
+//! Most development boards have one addressable RGB LED on board, which is
+//! typically used to show the state of the application. A [`StatusLed`] wraps
+//! the adapter of such a LED, and offers setting its color and simple blinking
+//! and breathing effects. [`StatusLedAsync`] does the same for async adapters,
+//! where the effects need the `embassy-time` feature.
+//!
+//! The effects run for one period and then return, so they are called in a
+//! loop to repeat them, and the application stays in control in between.
+//!
+//! Headless devices can report errors with blink codes:
+//! [`StatusLed::blink_code`] flashes the LED as many times as the error
+//! number, followed by a pause, in the timing of a [`BlinkCode`].
+//! [`StatusLed::blink_code_forever`] repeats the code until a write fails, for
+//! example from a panic handler.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt = Rmt::new(peripherals.RMT, 80.MHz()).unwrap();
+//! let mut led = StatusLed::from_channel(rmt.channel0, peripherals.GPIO8, 80.MHz());
+//!
+//! led.set_color(RGB8::new(0, 32, 0)).unwrap();
+//!
+//! loop {
+//!     led.breathe(RGB8::new(0, 0, 255), 2_000).unwrap();
+//! }
+//! ```
+
+use esp_hal::{
+    delay::Delay,
+    gpio::OutputPin,
+    peripheral::Peripheral,
+    rmt::{TxChannel, TxChannelAsync, TxChannelCreator, TxChannelCreatorAsync},
+};
+use fugit::HertzU32;
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync, RGB8};
+
+use crate::{buffer_size, SmartLedsAdapter, SmartLedsAdapterAsync};
+
+/// Interval between the steps of a breathing effect, in milliseconds
+const BREATHE_STEP_MS: u32 = 20;
+
+/// Timing and color of blink codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlinkCode {
+    color: RGB8,
+    on_ms: u32,
+    off_ms: u32,
+    pause_ms: u32,
+}
+
+impl BlinkCode {
+    /// Create new blink codes of red flashes, lit for 200ms with 300ms in
+    /// between, and a pause of 1.5s after every code.
+    pub const fn new() -> Self {
+        Self {
+            color: RGB8::new(255, 0, 0),
+            on_ms: 200,
+            off_ms: 300,
+            pause_ms: 1_500,
+        }
+    }
+
+    /// Set the color of the flashes.
+    pub const fn with_color(mut self, color: RGB8) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set how long every flash is lit, and how long the LED is off between
+    /// two flashes, in milliseconds.
+    pub const fn with_flash(mut self, on_ms: u32, off_ms: u32) -> Self {
+        self.on_ms = on_ms;
+        self.off_ms = off_ms;
+        self
+    }
+
+    /// Set how long the LED is off after every code, in milliseconds.
+    pub const fn with_pause(mut self, pause_ms: u32) -> Self {
+        self.pause_ms = pause_ms;
+        self
+    }
+
+    /// The color of the flashes
+    pub const fn color(&self) -> RGB8 {
+        self.color
+    }
+
+    /// How long the LED stays off after flash `index` of `code` flashes, in
+    /// milliseconds
+    const fn off_after(&self, index: u32, code: u32) -> u32 {
+        match index + 1 == code {
+            true => self.pause_ms,
+            false => self.off_ms,
+        }
+    }
+}
+
+impl Default for BlinkCode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single LED driven by a `smart-leds` adapter
+pub struct StatusLed<A> {
+    adapter: A,
+    color: RGB8,
+}
+
+impl<A> StatusLed<A> {
+    /// Create a new status LED writing to the given adapter.
+    pub fn new(adapter: A) -> Self {
+        Self {
+            adapter,
+            color: RGB8::default(),
+        }
+    }
+
+    /// The color the LED was last set to
+    pub fn color(&self) -> RGB8 {
+        self.color
+    }
+
+    /// Give back the wrapped adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
+
+impl<'d, TX> StatusLed<SmartLedsAdapter<TX, { buffer_size(1) }>>
+where
+    TX: TxChannel,
+{
+    /// Create a new status LED that drives the pin using the RMT channel.
+    ///
+    /// `frequency` is the one the RMT peripheral has been set up with in
+    /// `Rmt::new`.
+    pub fn from_channel<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        Self::new(SmartLedsAdapter::new_owned(channel, pin, frequency))
+    }
+}
+
+impl<A> StatusLed<A>
+where
+    A: SmartLedsWrite<Color = RGB8>,
+{
+    /// Set the color of the LED.
+    pub fn set_color(&mut self, color: RGB8) -> Result<(), A::Error> {
+        self.adapter.write([color])?;
+        self.color = color;
+
+        Ok(())
+    }
+
+    /// Turn the LED off.
+    pub fn off(&mut self) -> Result<(), A::Error> {
+        self.set_color(RGB8::default())
+    }
+
+    /// Show `color` for the first half of `period_ms` milliseconds, and turn
+    /// the LED off for the second half.
+    pub fn blink(&mut self, color: RGB8, period_ms: u32) -> Result<(), A::Error> {
+        let delay = Delay::new();
+        self.set_color(color)?;
+        delay.delay_millis(period_ms / 2);
+        self.off()?;
+        delay.delay_millis(period_ms - period_ms / 2);
+
+        Ok(())
+    }
+
+    /// Fade `color` in and out again over `period_ms` milliseconds, ending
+    /// with the LED off.
+    pub fn breathe(&mut self, color: RGB8, period_ms: u32) -> Result<(), A::Error> {
+        let delay = Delay::new();
+        let steps = breathe_steps(period_ms);
+        for step in 0..=steps {
+            self.set_color(breathe_color(color, step, steps))?;
+            delay.delay_millis(BREATHE_STEP_MS);
+        }
+
+        Ok(())
+    }
+
+    /// Flash the LED `code` times, and keep it off for the pause after the
+    /// last flash.
+    ///
+    /// A code of 0 only shows the pause.
+    pub fn blink_code(&mut self, code: u32, blink: &BlinkCode) -> Result<(), A::Error> {
+        let delay = Delay::new();
+        if code == 0 {
+            self.off()?;
+            delay.delay_millis(blink.pause_ms);
+        }
+
+        for index in 0..code {
+            self.set_color(blink.color)?;
+            delay.delay_millis(blink.on_ms);
+            self.off()?;
+            delay.delay_millis(blink.off_after(index, code));
+        }
+
+        Ok(())
+    }
+
+    /// Repeat the blink code until a write to the LED fails, and return the
+    /// error.
+    pub fn blink_code_forever(&mut self, code: u32, blink: &BlinkCode) -> A::Error {
+        loop {
+            if let Err(error) = self.blink_code(code, blink) {
+                return error;
+            }
+        }
+    }
+}
+
+/// A single LED driven by an async `smart-leds` adapter
+pub struct StatusLedAsync<A> {
+    adapter: A,
+    color: RGB8,
+}
+
+impl<A> StatusLedAsync<A> {
+    /// Create a new status LED writing to the given adapter.
+    pub fn new(adapter: A) -> Self {
+        Self {
+            adapter,
+            color: RGB8::default(),
+        }
+    }
+
+    /// The color the LED was last set to
+    pub fn color(&self) -> RGB8 {
+        self.color
+    }
+
+    /// Give back the wrapped adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
+
+impl<'d, TX> StatusLedAsync<SmartLedsAdapterAsync<TX, { buffer_size(1) }>>
+where
+    TX: TxChannelAsync,
+{
+    /// Create a new status LED that drives the pin using the RMT channel.
+    ///
+    /// `frequency` is the one the RMT peripheral has been set up with in
+    /// `Rmt::new`.
+    pub fn from_channel<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+    ) -> Self
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreatorAsync<'d, TX, O>,
+    {
+        Self::new(SmartLedsAdapterAsync::new_owned(channel, pin, frequency))
+    }
+}
+
+impl<A> StatusLedAsync<A>
+where
+    A: SmartLedsWriteAsync<Color = RGB8>,
+{
+    /// Set the color of the LED.
+    pub async fn set_color(&mut self, color: RGB8) -> Result<(), A::Error> {
+        self.adapter.write([color]).await?;
+        self.color = color;
+
+        Ok(())
+    }
+
+    /// Turn the LED off.
+    pub async fn off(&mut self) -> Result<(), A::Error> {
+        self.set_color(RGB8::default()).await
+    }
+
+    /// Show `color` for the first half of `period_ms` milliseconds, and turn
+    /// the LED off for the second half.
+    #[cfg(feature = "embassy-time")]
+    pub async fn blink(&mut self, color: RGB8, period_ms: u32) -> Result<(), A::Error> {
+        self.set_color(color).await?;
+        embassy_time::Timer::after_millis((period_ms / 2) as u64).await;
+        self.off().await?;
+        embassy_time::Timer::after_millis((period_ms - period_ms / 2) as u64).await;
+
+        Ok(())
+    }
+
+    /// Fade `color` in and out again over `period_ms` milliseconds, ending
+    /// with the LED off.
+    #[cfg(feature = "embassy-time")]
+    pub async fn breathe(&mut self, color: RGB8, period_ms: u32) -> Result<(), A::Error> {
+        let steps = breathe_steps(period_ms);
+        for step in 0..=steps {
+            self.set_color(breathe_color(color, step, steps)).await?;
+            embassy_time::Timer::after_millis(BREATHE_STEP_MS as u64).await;
+        }
+
+        Ok(())
+    }
+
+    /// Flash the LED `code` times, and keep it off for the pause after the
+    /// last flash.
+    ///
+    /// A code of 0 only shows the pause.
+    #[cfg(feature = "embassy-time")]
+    pub async fn blink_code(&mut self, code: u32, blink: &BlinkCode) -> Result<(), A::Error> {
+        if code == 0 {
+            self.off().await?;
+            embassy_time::Timer::after_millis(blink.pause_ms as u64).await;
+        }
+
+        for index in 0..code {
+            self.set_color(blink.color).await?;
+            embassy_time::Timer::after_millis(blink.on_ms as u64).await;
+            self.off().await?;
+            embassy_time::Timer::after_millis(blink.off_after(index, code) as u64).await;
+        }
+
+        Ok(())
+    }
+
+    /// Repeat the blink code until a write to the LED fails, and return the
+    /// error.
+    #[cfg(feature = "embassy-time")]
+    pub async fn blink_code_forever(&mut self, code: u32, blink: &BlinkCode) -> A::Error {
+        loop {
+            if let Err(error) = self.blink_code(code, blink).await {
+                return error;
+            }
+        }
+    }
+}
+
+/// Number of steps of a breathing effect over `period_ms` milliseconds
+fn breathe_steps(period_ms: u32) -> u32 {
+    (period_ms / BREATHE_STEP_MS).max(2)
+}
+
+/// Color at `step` of a breathing effect of `steps` steps.
+///
+/// The brightness rises and falls linearly, and is squared so the fade looks
+/// even to the eye.
+fn breathe_color(color: RGB8, step: u32, steps: u32) -> RGB8 {
+    let rise = (step * 2).min(steps * 2 - step * 2);
+    let level = (rise * 255 / steps).min(255);
+    let level = level * level / 255;
+    let scale = |channel: u8| (channel as u32 * level / 255) as u8;
+
+    RGB8::new(scale(color.r), scale(color.g), scale(color.b))
+}
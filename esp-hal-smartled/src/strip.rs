@@ -0,0 +1,432 @@
+//! Frame buffers for whole strips and their zones.
+//!
+//! Even a trivial application needs an adapter, a frame buffer, the color
+//! processing of an [`LedPipeline`] and the pacing of a [`FrameLimiter`]. A
+//! [`LedStrip`] combines all of them: set the colors of its LEDs with
+//! [`LedStrip::set`] and [`LedStrip::fill`], and write them with
+//! [`LedStrip::show`], or [`LedStrip::show_async`] for the async adapters.
+//!
+//! Many products use one physical strip for several purposes, such as a few
+//! status LEDs at the start of a strip used for ambient lighting.
+//! [`LedStrip::segment`] returns a [`Segment`] view of a range of its LEDs,
+//! which can be filled, shifted and written without touching the rest of the
+//! frame.
+//!
+//! Since a strip can only be updated as a whole, writing a segment sends the
+//! whole frame, with the other zones unchanged.
+//!
+//! With [`LedStrip::set_skip_unchanged`], writing a frame which is the same as
+//! the one written last does nothing, so a mostly static display can simply be
+//! flushed on every iteration of the main loop.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let led = SmartLedsAdapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), smartLedBuffer!(60));
+//! let mut strip = LedStrip::<_, 60>::new(led)
+//!     .with_pipeline(LedPipeline::new().with_gamma(Gamma::new(2.2)).with_brightness(64))
+//!     .with_target_fps(60);
+//!
+//! strip.fill(RGB8::new(0, 0, 255));
+//! strip.set(0, RGB8::new(255, 0, 0));
+//! strip.show().unwrap();
+//!
+//! strip.segment(0..4).fill(RGB8::new(0, 255, 0));
+//!
+//! let mut ambient = strip.segment(4..60);
+//! ambient.shift_forward(1);
+//! ambient.set(0, next_color());
+//! ambient.flush().unwrap();
+//! ```
+
+use core::ops::Range;
+
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync, RGB8};
+
+use crate::{color::scale, pacing::FrameLimiter, pipeline::LedPipeline, power::SoftStart};
+
+/// Frame buffer for a strip driven by a `smart-leds` adapter, with the color
+/// processing and pacing of its frames
+///
+/// `LEDS` is the number of LEDs on the strip.
+pub struct LedStrip<A, const LEDS: usize> {
+    adapter: A,
+    frame: [RGB8; LEDS],
+    pipeline: LedPipeline,
+    limiter: FrameLimiter,
+    soft_start: Option<SoftStart>,
+    skip_unchanged: bool,
+    // Hash of the frame written last, while skipping unchanged frames
+    written: Option<u64>,
+}
+
+impl<A, const LEDS: usize> LedStrip<A, LEDS> {
+    /// Create a new strip writing to the given adapter, with all LEDs off.
+    ///
+    /// Colors are written unchanged, and frames are not paced.
+    pub fn new(adapter: A) -> Self {
+        Self {
+            adapter,
+            frame: [RGB8::default(); LEDS],
+            pipeline: LedPipeline::new(),
+            limiter: FrameLimiter::new(0),
+            soft_start: None,
+            skip_unchanged: false,
+            written: None,
+        }
+    }
+
+    /// Process every frame with the given pipeline before writing it.
+    pub fn with_pipeline(mut self, pipeline: LedPipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Write at most `fps` frames per second, where 0 disables limiting.
+    pub fn with_target_fps(mut self, fps: u32) -> Self {
+        self.limiter.set_target_fps(fps);
+        self
+    }
+
+    /// Ramp the brightness up from 0 over `ramp_ms` milliseconds, starting
+    /// with the first frame shown.
+    pub fn with_soft_start(mut self, ramp_ms: u32) -> Self {
+        self.soft_start = Some(SoftStart::new(ramp_ms));
+        self
+    }
+
+    /// Number of LEDs on the strip
+    pub const fn len(&self) -> usize {
+        LEDS
+    }
+
+    /// Whether the strip has no LEDs
+    pub const fn is_empty(&self) -> bool {
+        LEDS == 0
+    }
+
+    /// Set the color of the LED at `index`.
+    ///
+    /// Indices outside of the strip are ignored.
+    pub fn set(&mut self, index: usize, color: RGB8) {
+        if let Some(led) = self.frame.get_mut(index) {
+            *led = color;
+        }
+    }
+
+    /// The color of the LED at `index`, before processing
+    pub fn get(&self, index: usize) -> Option<RGB8> {
+        self.frame.get(index).copied()
+    }
+
+    /// Set all LEDs to the given color.
+    pub fn fill(&mut self, color: RGB8) {
+        self.frame.fill(color);
+    }
+
+    /// Turn all LEDs off.
+    pub fn clear(&mut self) {
+        self.fill(RGB8::default());
+    }
+
+    /// The pipeline every frame is processed with
+    pub fn pipeline(&self) -> &LedPipeline {
+        &self.pipeline
+    }
+
+    /// Mutable access to the pipeline every frame is processed with
+    pub fn pipeline_mut(&mut self) -> &mut LedPipeline {
+        // The processed frame may change even if the colors do not
+        self.written = None;
+        &mut self.pipeline
+    }
+
+    /// Change the brightness of the pipeline, for example from a dimmer.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.pipeline_mut().set_brightness(brightness);
+    }
+
+    /// Change the maximum frame rate, where 0 disables limiting.
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.limiter.set_target_fps(fps);
+    }
+
+    /// Set whether [`LedStrip::flush`] skips writing a frame which is the same
+    /// as the one written last.
+    ///
+    /// This saves the time for encoding and sending frames of mostly static
+    /// displays, such as status indicators. Frames are compared by a 64-bit
+    /// hash, so only the hash of the frame written last is kept.
+    pub fn set_skip_unchanged(&mut self, skip_unchanged: bool) {
+        self.skip_unchanged = skip_unchanged;
+        self.written = None;
+    }
+
+    /// Make the next [`LedStrip::flush`] write the frame even if it is
+    /// unchanged, for example after the LEDs have been powered up again.
+    pub fn invalidate(&mut self) {
+        self.written = None;
+    }
+
+    /// A view of the LEDs in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds of the strip.
+    pub fn segment(&mut self, range: Range<usize>) -> Segment<'_, A, LEDS> {
+        assert!(range.start <= range.end && range.end <= LEDS);
+
+        Segment { strip: self, range }
+    }
+
+    /// The frame buffer
+    pub fn frame(&self) -> &[RGB8; LEDS] {
+        &self.frame
+    }
+
+    /// Mutable access to the frame buffer
+    pub fn frame_mut(&mut self) -> &mut [RGB8; LEDS] {
+        &mut self.frame
+    }
+
+    /// Give back the wrapped adapter.
+    pub fn release(self) -> A {
+        self.adapter
+    }
+}
+
+impl<A, const LEDS: usize> LedStrip<A, LEDS> {
+    /// Hash of the current frame if it may be skipped when unchanged.
+    ///
+    /// Frames are never skipped while the soft start ramps up.
+    fn skip_hash(&self) -> Option<u64> {
+        let ramping = self
+            .soft_start
+            .as_ref()
+            .is_some_and(|soft_start| !soft_start.is_done());
+
+        (self.skip_unchanged && !ramping).then(|| frame_hash(&self.frame))
+    }
+
+    /// Brightness of the current step of the soft start
+    fn ramp(&mut self) -> u8 {
+        match &mut self.soft_start {
+            Some(soft_start) => soft_start.brightness(255),
+            None => 255,
+        }
+    }
+}
+
+impl<A, const LEDS: usize> LedStrip<A, LEDS>
+where
+    A: SmartLedsWrite<Color = RGB8>,
+{
+    /// Process the current frame with the pipeline and write it to the LEDs,
+    /// after waiting until the next frame is due.
+    ///
+    /// If unchanged frames are skipped, nothing is written when the frame is
+    /// the same as the one written last.
+    pub fn show(&mut self) -> Result<(), A::Error> {
+        let hash = self.skip_hash();
+        if hash.is_some() && self.written == hash {
+            return Ok(());
+        }
+
+        self.limiter.wait();
+        self.written = None;
+        let ramp = self.ramp();
+        self.adapter
+            .write(processed(&self.pipeline, &self.frame, ramp))?;
+        self.written = hash;
+
+        Ok(())
+    }
+
+    /// Write the current frame to the LEDs, like [`LedStrip::show`].
+    pub fn flush(&mut self) -> Result<(), A::Error> {
+        self.show()
+    }
+}
+
+impl<A, const LEDS: usize> LedStrip<A, LEDS>
+where
+    A: SmartLedsWriteAsync<Color = RGB8>,
+{
+    /// Process the current frame with the pipeline and write it to the LEDs,
+    /// like [`LedStrip::show`], without blocking the executor.
+    ///
+    /// Frames are only paced with the `embassy-time` feature.
+    pub async fn show_async(&mut self) -> Result<(), A::Error> {
+        let hash = self.skip_hash();
+        if hash.is_some() && self.written == hash {
+            return Ok(());
+        }
+
+        #[cfg(feature = "embassy-time")]
+        self.limiter.wait_async().await;
+        self.written = None;
+        let ramp = self.ramp();
+        self.adapter
+            .write(processed(&self.pipeline, &self.frame, ramp))
+            .await?;
+        self.written = hash;
+
+        Ok(())
+    }
+}
+
+/// View of a range of LEDs of a [`LedStrip`]
+///
+/// Indices passed to the methods of a segment are relative to its start.
+pub struct Segment<'a, A, const LEDS: usize> {
+    strip: &'a mut LedStrip<A, LEDS>,
+    range: Range<usize>,
+}
+
+impl<A, const LEDS: usize> Segment<'_, A, LEDS> {
+    /// Number of LEDs in the segment
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Whether the segment has no LEDs
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// The range of the segment on the strip
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The colors of the LEDs in the segment
+    pub fn colors(&self) -> &[RGB8] {
+        &self.strip.frame[self.range.clone()]
+    }
+
+    /// Mutable access to the colors of the LEDs in the segment
+    pub fn colors_mut(&mut self) -> &mut [RGB8] {
+        &mut self.strip.frame[self.range.clone()]
+    }
+
+    /// Set the color of the LED at `index`.
+    ///
+    /// Indices outside of the segment are ignored.
+    pub fn set(&mut self, index: usize, color: RGB8) {
+        if let Some(led) = self.colors_mut().get_mut(index) {
+            *led = color;
+        }
+    }
+
+    /// Set all LEDs of the segment to the given color.
+    pub fn fill(&mut self, color: RGB8) {
+        self.colors_mut().fill(color);
+    }
+
+    /// Turn all LEDs of the segment off.
+    pub fn clear(&mut self) {
+        self.fill(RGB8::default());
+    }
+
+    /// Set the LEDs of the segment from an iterator.
+    ///
+    /// Colors beyond the end of the segment are ignored, and LEDs beyond the
+    /// end of the iterator keep their color.
+    pub fn copy_from<T, I>(&mut self, iterator: T)
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        for (led, color) in self.colors_mut().iter_mut().zip(iterator) {
+            *led = color.into();
+        }
+    }
+
+    /// Move the colors `count` LEDs towards the end of the segment, turning
+    /// off the LEDs at its start.
+    pub fn shift_forward(&mut self, count: usize) {
+        let colors = self.colors_mut();
+        let count = count.min(colors.len());
+        colors.rotate_right(count);
+        colors[..count].fill(RGB8::default());
+    }
+
+    /// Move the colors `count` LEDs towards the start of the segment, turning
+    /// off the LEDs at its end.
+    pub fn shift_backward(&mut self, count: usize) {
+        let colors = self.colors_mut();
+        let count = count.min(colors.len());
+        colors.rotate_left(count);
+        let len = colors.len();
+        colors[len - count..].fill(RGB8::default());
+    }
+
+    /// Move the colors `count` LEDs towards the end of the segment, wrapping
+    /// the last ones around to its start.
+    pub fn rotate_forward(&mut self, count: usize) {
+        let colors = self.colors_mut();
+        if !colors.is_empty() {
+            let count = count % colors.len();
+            colors.rotate_right(count);
+        }
+    }
+
+    /// Move the colors `count` LEDs towards the start of the segment, wrapping
+    /// the first ones around to its end.
+    pub fn rotate_backward(&mut self, count: usize) {
+        let colors = self.colors_mut();
+        if !colors.is_empty() {
+            let count = count % colors.len();
+            colors.rotate_left(count);
+        }
+    }
+}
+
+impl<A, const LEDS: usize> Segment<'_, A, LEDS>
+where
+    A: SmartLedsWrite<Color = RGB8>,
+{
+    /// Write the frame of the strip to the LEDs, including the other zones.
+    pub fn flush(&mut self) -> Result<(), A::Error> {
+        self.strip.flush()
+    }
+
+    /// Set the LEDs of the segment from an iterator, like
+    /// [`Segment::copy_from`], and write the frame of the strip to the LEDs.
+    pub fn write<T, I>(&mut self, iterator: T) -> Result<(), A::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        self.copy_from(iterator);
+        self.flush()
+    }
+}
+
+/// Colors of a frame processed with the pipeline, and scaled by `ramp / 255`
+fn processed<'a>(
+    pipeline: &'a LedPipeline,
+    frame: &'a [RGB8],
+    ramp: u8,
+) -> impl Iterator<Item = RGB8> + 'a {
+    pipeline.process(frame.iter().copied()).map(move |color| {
+        RGB8::new(
+            scale(color.r, ramp),
+            scale(color.g, ramp),
+            scale(color.b, ramp),
+        )
+    })
+}
+
+/// FNV-1a hash of the colors of a frame
+pub(crate) fn frame_hash(frame: &[RGB8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    frame
+        .iter()
+        .flat_map(|color| [color.r, color.g, color.b])
+        .fold(OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        })
+}
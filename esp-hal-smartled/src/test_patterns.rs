@@ -0,0 +1,169 @@
+//! Test patterns for bringing up new hardware.
+//!
+//! When a board or strip is wired up for the first time, a few simple
+//! patterns show most problems at a glance: solid red, green and blue reveal
+//! a wrong color order, a chase of a single LED shows where the strip is
+//! broken or the LED count is wrong, 50% gray and white show whether the
+//! supply can carry the load, and a gradient shows whether dimming is even.
+//!
+//! [`TestPattern`] renders each pattern for a strip of any length, and a
+//! [`TestRunner`] shows all of them in turn with a single call.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut led = SmartLedsAdapter::<_, { buffer_size(60) }>::new_owned(
+//!     rmt.channel0,
+//!     peripherals.GPIO2,
+//!     80.MHz(),
+//! );
+//!
+//! TestRunner::new(60).with_brightness(64).run(&mut led).unwrap();
+//! ```
+
+use esp_hal::delay::Delay;
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::color::scale;
+
+/// A test pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TestPattern {
+    /// All LEDs full red
+    Red,
+    /// All LEDs full green
+    Green,
+    /// All LEDs full blue
+    Blue,
+    /// All LEDs full white, which draws the most current
+    White,
+    /// A single white LED at the given index, with every tenth LED before it
+    /// lit dimly red to count along the strip
+    Chase(usize),
+    /// All LEDs at 50% gray
+    Gray,
+    /// Brightness rising from off at the first LED to full white at the last
+    Gradient,
+}
+
+impl TestPattern {
+    /// The patterns shown by a [`TestRunner`], in order
+    pub const ALL: [TestPattern; 7] = [
+        TestPattern::Red,
+        TestPattern::Green,
+        TestPattern::Blue,
+        TestPattern::Chase(0),
+        TestPattern::Gray,
+        TestPattern::Gradient,
+        TestPattern::White,
+    ];
+
+    /// The color of the LED at `index` of a strip of `leds` LEDs
+    pub fn color(&self, index: usize, leds: usize) -> RGB8 {
+        match *self {
+            TestPattern::Red => RGB8::new(255, 0, 0),
+            TestPattern::Green => RGB8::new(0, 255, 0),
+            TestPattern::Blue => RGB8::new(0, 0, 255),
+            TestPattern::White => RGB8::new(255, 255, 255),
+            TestPattern::Chase(position) if index == position => RGB8::new(255, 255, 255),
+            TestPattern::Chase(position) if index < position && index % 10 == 9 => {
+                RGB8::new(32, 0, 0)
+            }
+            TestPattern::Chase(_) => RGB8::default(),
+            TestPattern::Gray => RGB8::new(128, 128, 128),
+            TestPattern::Gradient => {
+                let level = (index * 255 / leds.saturating_sub(1).max(1)) as u8;
+                RGB8::new(level, level, level)
+            }
+        }
+    }
+
+    /// The colors of a strip of `leds` LEDs
+    pub fn frame(&self, leds: usize) -> impl Iterator<Item = RGB8> {
+        let pattern = *self;
+        (0..leds).map(move |index| pattern.color(index, leds))
+    }
+}
+
+/// Shows every [`TestPattern`] on a strip in turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TestRunner {
+    leds: usize,
+    brightness: u8,
+    hold_ms: u32,
+    chase_step_ms: u32,
+}
+
+impl TestRunner {
+    /// Create a new runner for a strip of `leds` LEDs, at full brightness,
+    /// showing every pattern for a second and moving the chase every 50ms.
+    pub const fn new(leds: usize) -> Self {
+        Self {
+            leds,
+            brightness: 255,
+            hold_ms: 1_000,
+            chase_step_ms: 50,
+        }
+    }
+
+    /// Scale every pattern by `brightness / 255`, to keep a large strip
+    /// within the limits of its supply.
+    pub const fn with_brightness(mut self, brightness: u8) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Set how long every pattern is shown, in milliseconds.
+    pub const fn with_hold(mut self, hold_ms: u32) -> Self {
+        self.hold_ms = hold_ms;
+        self
+    }
+
+    /// Set how long the chase stays on every LED, in milliseconds.
+    pub const fn with_chase_step(mut self, chase_step_ms: u32) -> Self {
+        self.chase_step_ms = chase_step_ms;
+        self
+    }
+
+    /// Show a single pattern, and leave it on.
+    pub fn show<A>(&self, adapter: &mut A, pattern: TestPattern) -> Result<(), A::Error>
+    where
+        A: SmartLedsWrite<Color = RGB8>,
+    {
+        let brightness = self.brightness;
+        adapter.write(pattern.frame(self.leds).map(move |color| {
+            RGB8::new(
+                scale(color.r, brightness),
+                scale(color.g, brightness),
+                scale(color.b, brightness),
+            )
+        }))
+    }
+
+    /// Show every pattern in the order of [`TestPattern::ALL`], running the
+    /// chase over the whole strip, and turn the LEDs off at the end.
+    pub fn run<A>(&self, adapter: &mut A) -> Result<(), A::Error>
+    where
+        A: SmartLedsWrite<Color = RGB8>,
+    {
+        let delay = Delay::new();
+        for pattern in TestPattern::ALL {
+            match pattern {
+                TestPattern::Chase(_) => {
+                    for position in 0..self.leds {
+                        self.show(adapter, TestPattern::Chase(position))?;
+                        delay.delay_millis(self.chase_step_ms);
+                    }
+                }
+                pattern => {
+                    self.show(adapter, pattern)?;
+                    delay.delay_millis(self.hold_ms);
+                }
+            }
+        }
+
+        adapter.write((0..self.leds).map(|_| RGB8::default()))
+    }
+}
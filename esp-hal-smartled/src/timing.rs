@@ -0,0 +1,226 @@
+//! Pulse timings of single-wire LED protocols.
+//!
+//! LEDs of the WS2812 family encode every bit as a high pulse followed by a low
+//! pulse, where the length of the high pulse tells a `0` from a `1`. After the
+//! last bit of a frame, the line has to be held low for the reset (or latch)
+//! time before the LEDs show the new colors. The exact values differ between
+//! chips, and clones often need a longer reset time than the original parts.
+//!
+//! Level shifters and long cables can stretch or shorten the high pulses, as
+//! their rise and fall times differ. [`TimingOffsets`] correct the pulses for
+//! a particular board, independently of the timing profile of the LEDs.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! // The level shifter stretches the high pulses by about 60ns
+//! led.set_timing_offsets(TimingOffsets::skew(-60));
+//! ```
+
+/// Longest duration of a single RMT pulse, in ticks
+const MAX_PULSE_TICKS: u32 = 0x7fff;
+
+/// RMT pulse code of two levels held for the given number of ticks each
+///
+/// This has the layout of `PulseCode` of `esp-hal`, which cannot be used
+/// without the `esp-hal` dependency, so pulses can be encoded on the host.
+const fn pulse_code(level1: bool, length1: u16, level2: bool, length2: u16) -> u32 {
+    (level1 as u32) << 15
+        | (length1 as u32 & MAX_PULSE_TICKS)
+        | (level2 as u32) << 31
+        | (length2 as u32 & MAX_PULSE_TICKS) << 16
+}
+
+/// Timing profile of a single-wire LED protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// Duration of the high pulse of a `0` bit, in nanoseconds
+    pub t0h_ns: u32,
+    /// Duration of the low pulse of a `0` bit, in nanoseconds
+    pub t0l_ns: u32,
+    /// Duration of the high pulse of a `1` bit, in nanoseconds
+    pub t1h_ns: u32,
+    /// Duration of the low pulse of a `1` bit, in nanoseconds
+    pub t1l_ns: u32,
+    /// Duration the line is held low after a frame, in microseconds
+    pub reset_us: u32,
+}
+
+impl Timing {
+    /// Timings which work with both the SK6812 and WS2812 families.
+    ///
+    /// T0H is 400ns, which is between the 300ns of the SK6812 datasheet and the
+    /// 400ns of the WS2812 one. Some LEDs require more than 350ns for T0H,
+    /// others less than 500ns. T1H is 850ns, where the SK6812 datasheet
+    /// specifies 900ns; more than 550ns is sometimes enough, but some LEDs
+    /// require T1H to be at least twice T0H, or T1L to be more than 300ns.
+    pub const SK68XX: Self = Self::new(400, 850, 850, 400, 80);
+
+    /// Timings of the original WS2812
+    pub const WS2812: Self = Self::new(350, 800, 700, 600, 50);
+
+    /// Timings of the WS2812B, whose newer revisions need 280µs to latch
+    pub const WS2812B: Self = Self::new(400, 850, 800, 450, 280);
+
+    /// Timings of the WS2811 in its 800 kHz mode
+    pub const WS2811: Self = Self::new(250, 1000, 600, 650, 50);
+
+    /// Timings of the SK6812
+    pub const SK6812: Self = Self::new(300, 900, 600, 600, 80);
+
+    /// Timings of the TM1814, whose signal is inverted, so the "high" pulses
+    /// are sent as low ones
+    pub const TM1814: Self = Self::new(360, 940, 960, 340, 300);
+
+    /// Timings of the UCS1903 in its 800 kHz mode
+    pub const UCS1903: Self = Self::new(250, 1000, 1000, 250, 50);
+
+    /// Timings of the SM16703
+    pub const SM16703: Self = Self::new(300, 900, 900, 300, 80);
+
+    /// Timings of the GS8208
+    pub const GS8208: Self = Self::new(300, 900, 800, 400, 280);
+
+    /// Timings of the WS2818
+    pub const WS2818: Self = Self::new(300, 1000, 1000, 300, 280);
+
+    /// Timings of the WS2813, which accepts long low pulses and needs 300µs
+    /// to latch
+    pub const WS2813: Self = Self::new(350, 800, 800, 450, 300);
+
+    /// Timings of the WS2815, whose low pulse of a `1` bit must stay below
+    /// 420ns
+    pub const WS2815: Self = Self::new(300, 900, 900, 300, 280);
+
+    /// Create a new timing profile.
+    pub const fn new(t0h_ns: u32, t0l_ns: u32, t1h_ns: u32, t1l_ns: u32, reset_us: u32) -> Self {
+        Self {
+            t0h_ns,
+            t0l_ns,
+            t1h_ns,
+            t1l_ns,
+            reset_us,
+        }
+    }
+
+    /// Change the reset duration of this profile.
+    pub const fn with_reset_us(mut self, reset_us: u32) -> Self {
+        self.reset_us = reset_us;
+        self
+    }
+
+    /// Add the offsets to the pulses of this profile.
+    ///
+    /// Pulses which would become negative are 0.
+    pub const fn with_offsets(mut self, offsets: TimingOffsets) -> Self {
+        self.t0h_ns = self.t0h_ns.saturating_add_signed(offsets.t0h_ns);
+        self.t0l_ns = self.t0l_ns.saturating_add_signed(offsets.t0l_ns);
+        self.t1h_ns = self.t1h_ns.saturating_add_signed(offsets.t1h_ns);
+        self.t1l_ns = self.t1l_ns.saturating_add_signed(offsets.t1l_ns);
+        self
+    }
+
+    /// Whether the pulses of this profile can be produced at the given clock
+    /// rate in MHz.
+    ///
+    /// Every pulse has to be at least one tick long, and the high pulses of a
+    /// `0` and a `1` bit have to differ.
+    pub const fn resolves(&self, src_clock: u32) -> bool {
+        let t0h = ticks(self.t0h_ns, src_clock);
+        let t1h = ticks(self.t1h_ns, src_clock);
+
+        t0h > 0
+            && t1h > 0
+            && t0h != t1h
+            && ticks(self.t0l_ns, src_clock) > 0
+            && ticks(self.t1l_ns, src_clock) > 0
+    }
+
+    /// RMT pulse codes for a `0` and a `1` bit at the given clock rate,
+    /// optionally with inverted levels.
+    pub(crate) fn pulses(&self, src_clock: u32, inverted: bool) -> (u32, u32) {
+        (
+            pulse_code(
+                !inverted,
+                ticks(self.t0h_ns, src_clock) as u16,
+                inverted,
+                ticks(self.t0l_ns, src_clock) as u16,
+            ),
+            pulse_code(
+                !inverted,
+                ticks(self.t1h_ns, src_clock) as u16,
+                inverted,
+                ticks(self.t1l_ns, src_clock) as u16,
+            ),
+        )
+    }
+
+    /// Part of the reset duration which the pulse code of [`Timing::end`]
+    /// cannot hold, in microseconds
+    pub(crate) fn end_shortfall_us(&self, src_clock: u32) -> u32 {
+        self.reset_us
+            .saturating_sub(MAX_PULSE_TICKS / src_clock.max(1))
+    }
+
+    /// RMT pulse code which holds the line low, or high if `inverted`, for the
+    /// reset duration and then ends the transmission.
+    ///
+    /// The reset duration is limited to 32767 ticks, which is about 409µs at
+    /// 80MHz.
+    pub(crate) fn end(&self, src_clock: u32, inverted: bool) -> u32 {
+        let ticks = (self.reset_us * src_clock).min(MAX_PULSE_TICKS);
+
+        pulse_code(inverted, ticks as u16, inverted, 0)
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::SK68XX
+    }
+}
+
+/// Signed corrections of the pulses of a [`Timing`], in nanoseconds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimingOffsets {
+    /// Correction of the high pulse of a `0` bit
+    pub t0h_ns: i32,
+    /// Correction of the low pulse of a `0` bit
+    pub t0l_ns: i32,
+    /// Correction of the high pulse of a `1` bit
+    pub t1h_ns: i32,
+    /// Correction of the low pulse of a `1` bit
+    pub t1l_ns: i32,
+}
+
+impl TimingOffsets {
+    /// No correction
+    pub const NONE: Self = Self::new(0, 0, 0, 0);
+
+    /// Create new offsets for every pulse.
+    pub const fn new(t0h_ns: i32, t0l_ns: i32, t1h_ns: i32, t1l_ns: i32) -> Self {
+        Self {
+            t0h_ns,
+            t0l_ns,
+            t1h_ns,
+            t1l_ns,
+        }
+    }
+
+    /// Lengthen the high pulses by `high_ns`, and shorten the low pulses by
+    /// the same amount, so the duration of every bit stays the same.
+    ///
+    /// This compensates a level shifter whose rising and falling edges are
+    /// delayed by different amounts, which shifts the falling edge of every
+    /// bit.
+    pub const fn skew(high_ns: i32) -> Self {
+        Self::new(high_ns, -high_ns, high_ns, -high_ns)
+    }
+}
+
+/// Number of ticks of a pulse of `ns` nanoseconds at `src_clock` MHz
+const fn ticks(ns: u32, src_clock: u32) -> u32 {
+    ns * src_clock / 1000
+}
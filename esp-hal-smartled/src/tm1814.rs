@@ -0,0 +1,214 @@
+//! Adapter for TM1814 RGBW LEDs.
+//!
+//! The TM1814 is found on 12V and 24V RGBW strips. It differs from the WS2812
+//! family in more than its timings:
+//!
+//! - The signal is inverted, so the line idles high and every bit starts with
+//!   a low pulse.
+//! - Every frame starts with a preamble that sets the constant current of each
+//!   channel, followed by its bitwise complement.
+//! - Colors are sent in WRGB order with 32 bits per LED.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let rmt_buffer = [0u32; tm1814::buffer_size(NUM_LEDS)];
+//! let mut led = Tm1814Adapter::new(rmt.channel0, peripherals.GPIO2, 80.MHz(), rmt_buffer);
+//! led.set_current(Current::new(190, 190, 190, 380));
+//!
+//! led.write([RGBW::new_alpha(255, 0, 0, White(0))]).unwrap();
+//! ```
+
+use esp_hal::{
+    gpio::OutputPin,
+    peripheral::Peripheral,
+    rmt::{TxChannel, TxChannelConfig, TxChannelCreator},
+};
+use fugit::HertzU32;
+use smart_leds_trait::{SmartLedsWrite, RGBW};
+
+use crate::{convert_bytes_to_pulses, timing::Timing, LedAdapterError};
+
+/// Number of pulse codes of the current preamble
+const PREAMBLE_LEN: usize = 64;
+
+/// Number of `u32` pulse codes needed to address `num_leds` TM1814 LEDs,
+/// including the current preamble
+pub const fn buffer_size(num_leds: usize) -> usize {
+    PREAMBLE_LEN + num_leds * 32 + 1
+}
+
+/// Constant current of each channel, in units of 0.1 mA
+///
+/// The TM1814 supports currents from 6.5 mA to 38 mA in steps of 0.5 mA.
+/// Values outside that range are clamped, others rounded down to a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Current {
+    /// Current of the red channel
+    pub r: u16,
+    /// Current of the green channel
+    pub g: u16,
+    /// Current of the blue channel
+    pub b: u16,
+    /// Current of the white channel
+    pub w: u16,
+}
+
+impl Current {
+    /// Lowest supported current, 6.5 mA
+    pub const MIN: u16 = 65;
+    /// Highest supported current, 38 mA
+    pub const MAX: u16 = 380;
+
+    /// Create a new current setting, in units of 0.1 mA.
+    pub const fn new(r: u16, g: u16, b: u16, w: u16) -> Self {
+        Self { r, g, b, w }
+    }
+
+    /// The preamble bytes in WRGB order, followed by their complement
+    fn preamble(&self) -> [u8; 8] {
+        let [w, r, g, b] = [self.w, self.r, self.g, self.b]
+            .map(|current| ((current.clamp(Self::MIN, Self::MAX) - Self::MIN) / 5) as u8);
+
+        [w, r, g, b, !w, !r, !g, !b]
+    }
+}
+
+impl Default for Current {
+    /// 19 mA on every channel
+    fn default() -> Self {
+        Self::new(190, 190, 190, 190)
+    }
+}
+
+/// Adapter taking an RMT channel and a specific pin and driving TM1814 LEDs
+pub struct Tm1814Adapter<TX, const BUFFER_SIZE: usize>
+where
+    TX: TxChannel,
+{
+    channel: Option<TX>,
+    rmt_buffer: [u32; BUFFER_SIZE],
+    pulses: (u32, u32),
+    end: u32,
+    current: Current,
+}
+
+impl<'d, TX, const BUFFER_SIZE: usize> Tm1814Adapter<TX, BUFFER_SIZE>
+where
+    TX: TxChannel,
+{
+    /// Create a new adapter object that drives the pin using the RMT channel.
+    ///
+    /// `frequency` is the one the RMT peripheral has been set up with in
+    /// `Rmt::new`.
+    pub fn new<C, O>(
+        channel: C,
+        pin: impl Peripheral<P = O> + 'd,
+        frequency: HertzU32,
+        rmt_buffer: [u32; BUFFER_SIZE],
+    ) -> Tm1814Adapter<TX, BUFFER_SIZE>
+    where
+        O: OutputPin + 'd,
+        C: TxChannelCreator<'d, TX, O>,
+    {
+        let config = TxChannelConfig {
+            clk_divider: 1,
+            idle_output_level: true,
+            carrier_modulation: false,
+            idle_output: true,
+
+            ..TxChannelConfig::default()
+        };
+
+        let channel = channel.configure(pin, config).unwrap();
+
+        let timing = Timing::TM1814;
+
+        Self {
+            channel: Some(channel),
+            rmt_buffer,
+            pulses: timing.pulses(frequency.to_MHz(), true),
+            end: timing.end(frequency.to_MHz(), true),
+            current: Current::default(),
+        }
+    }
+
+    /// Number of LEDs which fit into the buffer along with the preamble and
+    /// the end delimiter.
+    pub fn capacity_leds(&self) -> usize {
+        BUFFER_SIZE.saturating_sub(PREAMBLE_LEN + 1) / 32
+    }
+
+    /// Set the constant current of each channel, which is sent with every
+    /// frame.
+    pub fn set_current(&mut self, current: Current) {
+        self.current = current;
+    }
+}
+
+impl<TX, const BUFFER_SIZE: usize> SmartLedsWrite for Tm1814Adapter<TX, BUFFER_SIZE>
+where
+    TX: TxChannel,
+{
+    type Error = LedAdapterError;
+    type Color = RGBW<u8>;
+
+    /// Convert all RGBW items of the iterator to the RMT format, preceded by
+    /// the current preamble, and send them in a single RMT operation.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let mut iterator = iterator.into_iter();
+
+        if BUFFER_SIZE < buffer_size(0) {
+            return Err(LedAdapterError::BufferSizeExceeded {
+                led: 0,
+                required: buffer_size(iterator.count()),
+                available: BUFFER_SIZE,
+            });
+        }
+
+        convert_bytes_to_pulses(
+            &self.current.preamble(),
+            &mut self.rmt_buffer[..PREAMBLE_LEN],
+            self.pulses,
+        );
+        let mut position = PREAMBLE_LEN;
+
+        for (index, item) in iterator.by_ref().enumerate() {
+            // Make sure there is room for the LED and the end delimiter
+            if position + 32 >= BUFFER_SIZE {
+                return Err(LedAdapterError::BufferSizeExceeded {
+                    led: index,
+                    required: buffer_size(index + 1 + iterator.count()),
+                    available: BUFFER_SIZE,
+                });
+            }
+
+            let color = item.into();
+            convert_bytes_to_pulses(
+                &[color.a.0, color.r, color.g, color.b],
+                &mut self.rmt_buffer[position..position + 32],
+                self.pulses,
+            );
+            position += 32;
+        }
+
+        self.rmt_buffer[position] = self.end;
+
+        let channel = self.channel.take().unwrap();
+        match channel.transmit(&self.rmt_buffer[..position + 1])?.wait() {
+            Ok(chan) => {
+                self.channel = Some(chan);
+                Ok(())
+            }
+            Err((e, chan)) => {
+                self.channel = Some(chan);
+                Err(LedAdapterError::TransmissionError(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Smooth transitions between frames.
+//!
+//! A [`Transition`] fades from the frame currently shown to a target frame
+//! over a duration, following an [`Easing`] curve. It is advanced with
+//! [`Transition::tick`] by the time that has passed, which returns the frame
+//! to show next. Setting a new target while a transition is running starts
+//! the next one from the colors currently shown, so there are no jumps.
+//!
+//! With the `embassy-time` feature, [`Transition::tick_async`] waits for the
+//! frame interval before advancing.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut transition = Transition::<NUM_LEDS>::new();
+//! transition.set_target([RGB8::new(255, 64, 0); NUM_LEDS], 500, Easing::EaseInOut);
+//!
+//! while !transition.is_done() {
+//!     let frame = transition.tick(10);
+//!     led.write(frame.iter().copied()).unwrap();
+//!     delay.delay_millis(10);
+//! }
+//! ```
+
+use smart_leds_trait::RGB8;
+
+/// Fixed-point representation of a progress of 1
+const ONE: u32 = 1 << 16;
+
+/// Curve mapping the elapsed fraction of a transition to its progress
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear,
+    /// Start slowly and speed up, following a quadratic curve
+    EaseIn,
+    /// Start quickly and slow down, following a quadratic curve
+    EaseOut,
+    /// Start and end slowly, following a quadratic curve
+    EaseInOut,
+    /// Start slowly and speed up, following a cubic curve
+    CubicIn,
+    /// Start quickly and slow down, following a cubic curve
+    CubicOut,
+    /// Start and end slowly, following a cubic curve
+    CubicInOut,
+}
+
+impl Easing {
+    /// Progress at the elapsed fraction `t`, both in 16.16 fixed point from 0
+    /// to 1.
+    fn apply(self, t: u32) -> u32 {
+        let t = t.min(ONE) as u64;
+        let one = ONE as u64;
+        let square = |t: u64| t * t / one;
+        let cube = |t: u64| t * t / one * t / one;
+
+        let progress = match self {
+            Easing::Linear => t,
+            Easing::EaseIn => square(t),
+            Easing::EaseOut => one - square(one - t),
+            Easing::EaseInOut if t < one / 2 => 2 * square(t),
+            Easing::EaseInOut => one - 2 * square(one - t),
+            Easing::CubicIn => cube(t),
+            Easing::CubicOut => one - cube(one - t),
+            Easing::CubicInOut if t < one / 2 => 4 * cube(t),
+            Easing::CubicInOut => one - 4 * cube(one - t),
+        };
+
+        progress as u32
+    }
+}
+
+/// Fade between frames of `LEDS` LEDs
+#[derive(Debug, Clone)]
+pub struct Transition<const LEDS: usize> {
+    from: [RGB8; LEDS],
+    to: [RGB8; LEDS],
+    current: [RGB8; LEDS],
+    duration_ms: u32,
+    elapsed_ms: u32,
+    easing: Easing,
+}
+
+impl<const LEDS: usize> Transition<LEDS> {
+    /// Create a new transition with all LEDs off.
+    pub const fn new() -> Self {
+        Self {
+            from: [RGB8::new(0, 0, 0); LEDS],
+            to: [RGB8::new(0, 0, 0); LEDS],
+            current: [RGB8::new(0, 0, 0); LEDS],
+            duration_ms: 0,
+            elapsed_ms: 0,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Start fading from the colors currently shown to `target` over
+    /// `duration_ms` milliseconds.
+    ///
+    /// LEDs beyond the end of `target` fade to off.
+    pub fn set_target<T, I>(&mut self, target: T, duration_ms: u32, easing: Easing)
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        let mut target = target.into_iter();
+        for led in self.to.iter_mut() {
+            *led = target.next().map_or(RGB8::default(), Into::into);
+        }
+
+        self.from = self.current;
+        self.duration_ms = duration_ms;
+        self.elapsed_ms = 0;
+        self.easing = easing;
+
+        if duration_ms == 0 {
+            self.current = self.to;
+        }
+    }
+
+    /// Show `frame` right away, ending any running transition.
+    pub fn jump_to<T, I>(&mut self, frame: T)
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>,
+    {
+        self.set_target(frame, 0, Easing::Linear);
+    }
+
+    /// Advance the transition by `dt_ms` milliseconds, and return the frame to
+    /// show.
+    pub fn tick(&mut self, dt_ms: u32) -> &[RGB8; LEDS] {
+        if self.is_done() {
+            return &self.current;
+        }
+
+        self.elapsed_ms = self.elapsed_ms.saturating_add(dt_ms).min(self.duration_ms);
+        let t = ((self.elapsed_ms as u64) << 16) / self.duration_ms as u64;
+        let progress = self.easing.apply(t as u32);
+
+        for ((current, from), to) in self.current.iter_mut().zip(&self.from).zip(&self.to) {
+            *current = RGB8::new(
+                lerp(from.r, to.r, progress),
+                lerp(from.g, to.g, progress),
+                lerp(from.b, to.b, progress),
+            );
+        }
+
+        &self.current
+    }
+
+    /// Wait for `interval_ms` milliseconds, then advance the transition by
+    /// that time, and return the frame to show.
+    #[cfg(feature = "embassy-time")]
+    pub async fn tick_async(&mut self, interval_ms: u32) -> &[RGB8; LEDS] {
+        embassy_time::Timer::after_millis(interval_ms as u64).await;
+        self.tick(interval_ms)
+    }
+
+    /// Whether the target frame has been reached
+    pub fn is_done(&self) -> bool {
+        self.elapsed_ms >= self.duration_ms
+    }
+
+    /// The frame currently shown
+    pub fn frame(&self) -> &[RGB8; LEDS] {
+        &self.current
+    }
+
+    /// The frame the transition ends at
+    pub fn target(&self) -> &[RGB8; LEDS] {
+        &self.to
+    }
+}
+
+impl<const LEDS: usize> Default for Transition<LEDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Interpolate between two channel values, with `progress` in 16.16 fixed
+/// point.
+fn lerp(from: u8, to: u8, progress: u32) -> u8 {
+    let from = from as i32;
+    let delta = to as i32 - from;
+
+    (from + ((delta * progress as i32 + (ONE as i32 / 2)) >> 16)) as u8
+}
@@ -0,0 +1,118 @@
+//! Tests of temporal and spatial dithering, run on the host with
+//! `cargo test --features std`.
+
+use esp_hal_smartled::{dither::TemporalDither, smart_leds_trait::RGB8};
+
+/// Interval between frames at 200 FPS
+const FAST_US: u64 = 5_000;
+/// Interval between frames at 20 FPS
+const SLOW_US: u64 = 50_000;
+
+/// A color of 3 which is scaled by a brightness of 127 (a factor of 128/256)
+/// to 1.5, halfway between two values
+const HALF: RGB8 = RGB8::new(3, 3, 3);
+
+fn frame<const LEDS: usize>(
+    dither: &mut TemporalDither<LEDS>,
+    colors: &[RGB8],
+    brightness: u8,
+    interval_us: u64,
+) -> Vec<RGB8> {
+    dither
+        .apply_with_interval(colors.iter().copied(), brightness, interval_us)
+        .collect()
+}
+
+#[test]
+fn full_brightness_is_unchanged() {
+    let mut dither = TemporalDither::<3>::new();
+    let colors = [
+        RGB8::new(0, 1, 2),
+        RGB8::new(127, 128, 129),
+        RGB8::new(253, 254, 255),
+    ];
+
+    for _ in 0..4 {
+        assert_eq!(frame(&mut dither, &colors, 255, FAST_US), colors);
+    }
+}
+
+#[test]
+fn alternates_between_values() {
+    let mut dither = TemporalDither::<1>::new();
+
+    let values: Vec<u8> = (0..4)
+        .map(|_| frame(&mut dither, &[HALF], 127, FAST_US)[0].r)
+        .collect();
+
+    assert_eq!(values, [1, 2, 1, 2]);
+}
+
+#[test]
+fn averages_to_the_fraction() {
+    let mut dither = TemporalDither::<1>::new();
+    // 10 scaled by 32/256 is 1.25
+    let color = RGB8::new(10, 0, 0);
+
+    let sum: u32 = (0..8)
+        .map(|_| frame(&mut dither, &[color], 31, FAST_US)[0].r as u32)
+        .sum();
+
+    assert_eq!(sum, 10);
+}
+
+#[test]
+fn rounds_at_low_frame_rates() {
+    let mut dither = TemporalDither::<1>::new();
+
+    for _ in 0..4 {
+        assert_eq!(
+            frame(&mut dither, &[HALF], 127, SLOW_US),
+            [RGB8::new(2, 2, 2)]
+        );
+    }
+}
+
+#[test]
+fn min_frame_rate() {
+    let mut dither = TemporalDither::<1>::new().with_min_frame_rate(10);
+
+    let values: Vec<u8> = (0..2)
+        .map(|_| frame(&mut dither, &[HALF], 127, SLOW_US)[0].r)
+        .collect();
+
+    assert_eq!(values, [1, 2]);
+}
+
+#[test]
+fn spatial_fallback() {
+    let mut dither = TemporalDither::<4>::new().with_spatial_fallback(true);
+
+    let values: Vec<u8> = frame(&mut dither, &[HALF; 4], 127, SLOW_US)
+        .iter()
+        .map(|color| color.r)
+        .collect();
+
+    assert_eq!(values, [1, 2, 1, 2]);
+}
+
+#[test]
+fn leds_beyond_the_state_are_rounded() {
+    let mut dither = TemporalDither::<1>::new();
+
+    for _ in 0..2 {
+        assert_eq!(
+            frame(&mut dither, &[HALF; 2], 127, FAST_US)[1],
+            RGB8::new(2, 2, 2)
+        );
+    }
+}
+
+#[test]
+fn reset_clears_the_error() {
+    let mut dither = TemporalDither::<1>::new();
+
+    assert_eq!(frame(&mut dither, &[HALF], 127, FAST_US)[0].r, 1);
+    dither.reset();
+    assert_eq!(frame(&mut dither, &[HALF], 127, FAST_US)[0].r, 1);
+}
@@ -0,0 +1,135 @@
+//! Tests of the mapping of matrix coordinates to LED indices, run on the host
+//! with `cargo test --features std`.
+
+use esp_hal_smartled::{
+    buffer_size, buffer_size_rgbw, led_matrix,
+    matrix::{Layout, MappedLayout, MatrixLayout, Order, Rotation, TiledLayout},
+};
+
+/// A panel of 3x2 LEDs whose second row runs from right to left:
+///
+/// ```text
+/// 0 1 2
+/// 5 4 3
+/// ```
+const PANEL: MatrixLayout = MatrixLayout::new(3, 2).serpentine();
+
+/// Indices of all pixels of `layout`, by row and then column
+fn indices(layout: &MatrixLayout) -> Vec<Vec<usize>> {
+    let (width, height) = layout.viewed_size();
+    (0..height)
+        .map(|y| (0..width).map(|x| layout.index_of(x, y).unwrap()).collect())
+        .collect()
+}
+
+#[test]
+fn serpentine_rows() {
+    assert_eq!(indices(&PANEL), [[0, 1, 2], [5, 4, 3]]);
+}
+
+#[test]
+fn serpentine_columns() {
+    let layout = MatrixLayout::new(3, 2)
+        .order(Order::ColumnMajor)
+        .serpentine();
+
+    assert_eq!(indices(&layout), [[0, 3, 4], [1, 2, 5]]);
+}
+
+#[test]
+fn rotations() {
+    let cases = [
+        (Rotation::Deg0, (3, 2), vec![vec![0, 1, 2], vec![5, 4, 3]]),
+        (
+            Rotation::Deg90,
+            (2, 3),
+            vec![vec![5, 0], vec![4, 1], vec![3, 2]],
+        ),
+        (Rotation::Deg180, (3, 2), vec![vec![3, 4, 5], vec![2, 1, 0]]),
+        (
+            Rotation::Deg270,
+            (2, 3),
+            vec![vec![2, 3], vec![1, 4], vec![0, 5]],
+        ),
+    ];
+
+    for (rotation, size, expected) in cases {
+        let layout = PANEL.rotate(rotation);
+
+        assert_eq!(layout.viewed_size(), size, "{rotation:?}");
+        assert_eq!(indices(&layout), expected, "{rotation:?}");
+    }
+}
+
+#[test]
+fn flips() {
+    assert_eq!(indices(&PANEL.flip_horizontal()), [[2, 1, 0], [3, 4, 5]]);
+    assert_eq!(indices(&PANEL.flip_vertical()), [[5, 4, 3], [0, 1, 2]]);
+}
+
+#[test]
+fn out_of_range() {
+    for rotation in [
+        Rotation::Deg0,
+        Rotation::Deg90,
+        Rotation::Deg180,
+        Rotation::Deg270,
+    ] {
+        let layout = PANEL.rotate(rotation);
+        let (width, height) = layout.viewed_size();
+
+        assert_eq!(layout.index_of(width, 0), None, "{rotation:?}");
+        assert_eq!(layout.index_of(0, height), None, "{rotation:?}");
+    }
+}
+
+#[test]
+fn tiles() {
+    let layout = TiledLayout::new([PANEL; 4], 2).serpentine();
+
+    assert_eq!((layout.width(), layout.height()), (6, 4));
+    // First pixel of the second panel
+    assert_eq!(layout.index(3, 0), Some(6));
+    // The second row of panels runs from right to left, so its right panel is
+    // the third one
+    assert_eq!(layout.index(3, 2), Some(12));
+    assert_eq!(layout.index(0, 2), Some(18));
+    assert_eq!(layout.index(6, 0), None);
+    assert_eq!(layout.index(0, 4), None);
+}
+
+#[test]
+fn tiles_in_one_row() {
+    let layout = TiledLayout::new([PANEL; 2], 2);
+
+    assert_eq!(layout.index(4, 1), Some(10));
+}
+
+#[test]
+fn mapped_layout() {
+    let mapped = MappedLayout::<2, 3>::new(PANEL.rotate(Rotation::Deg90));
+
+    assert_eq!(mapped.table(), &[[5, 0], [4, 1], [3, 2]]);
+    assert_eq!(mapped.index(1, 2), Some(2));
+    assert_eq!(mapped.index(2, 0), None);
+}
+
+#[test]
+fn led_matrix_macro() {
+    let (layout, buffer) = led_matrix!(3, 2, Serpentine, rotate = Deg180, leds = 6);
+
+    assert_eq!(layout.table(), &[[3, 4, 5], [2, 1, 0]]);
+    assert_eq!(buffer.len(), buffer_size(6));
+
+    let (layout, buffer) = led_matrix!(
+        3,
+        2,
+        Progressive,
+        order = ColumnMajor,
+        flip_horizontal,
+        chip = esp_hal_smartled::chip::Sk6812Rgbw
+    );
+
+    assert_eq!(layout.table(), &[[4, 2, 0], [5, 3, 1]]);
+    assert_eq!(buffer.len(), buffer_size_rgbw(6));
+}
@@ -0,0 +1,92 @@
+//! Tests of palette lookups, run on the host with `cargo test --features std`.
+
+use esp_hal_smartled::{
+    palette::{color_from_palette, fill_palette, Blending, Palette16},
+    smart_leds_trait::RGB8,
+};
+
+const WHITE: RGB8 = RGB8::new(255, 255, 255);
+const BLACK: RGB8 = RGB8::new(0, 0, 0);
+
+/// A palette which is white at the first entry and black everywhere else
+fn white_first() -> Palette16 {
+    let mut entries = [BLACK; 16];
+    entries[0] = WHITE;
+    Palette16::new(entries)
+}
+
+#[test]
+fn from_hex() {
+    let palette = Palette16::from_hex([0x123456; 16]);
+
+    assert_eq!(palette.entries(), &[RGB8::new(0x12, 0x34, 0x56); 16]);
+    assert_eq!(Palette16::HEAT.entries()[15], WHITE);
+    assert_eq!(Palette16::default(), Palette16::RAINBOW);
+}
+
+#[test]
+fn entries_are_exact() {
+    for (entry, &color) in Palette16::RAINBOW.entries().iter().enumerate() {
+        let index = (entry << 4) as u8;
+
+        for blending in [Blending::Linear, Blending::None] {
+            assert_eq!(
+                color_from_palette(&Palette16::RAINBOW, index, 255, blending),
+                color,
+                "{index} {blending:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn linear_blending() {
+    // Halfway between 0xFF0000 and 0xD52A00
+    assert_eq!(
+        color_from_palette(&Palette16::RAINBOW, 0x08, 255, Blending::Linear),
+        RGB8::new(234, 21, 0)
+    );
+}
+
+#[test]
+fn no_blending() {
+    assert_eq!(
+        color_from_palette(&Palette16::RAINBOW, 0x1f, 255, Blending::None),
+        Palette16::RAINBOW.entries()[1]
+    );
+}
+
+#[test]
+fn blends_back_to_the_first_entry() {
+    let palette = white_first();
+
+    assert_eq!(
+        color_from_palette(&palette, 0xf8, 255, Blending::Linear),
+        RGB8::new(128, 128, 128)
+    );
+    assert_eq!(
+        color_from_palette(&palette, 0xf8, 255, Blending::None),
+        BLACK
+    );
+}
+
+#[test]
+fn brightness() {
+    let palette = white_first();
+
+    assert_eq!(
+        color_from_palette(&palette, 0, 128, Blending::None),
+        RGB8::new(128, 128, 128)
+    );
+    assert_eq!(color_from_palette(&palette, 0, 0, Blending::None), BLACK);
+}
+
+#[test]
+fn fill_wraps_around() {
+    let palette = Palette16::from_hex(core::array::from_fn(|entry| entry as u32));
+    let mut frame = [BLACK; 4];
+
+    fill_palette(&mut frame, 0xe0, 0x10, &palette, 255, Blending::Linear);
+
+    assert_eq!(frame.map(|color| color.b), [14, 15, 0, 1]);
+}
@@ -0,0 +1,71 @@
+//! Tests of the current estimate and limit, run on the host with
+//! `cargo test --features std`.
+
+use esp_hal_smartled::{power::PowerLimit, smart_leds_trait::RGB8};
+
+const WHITE: RGB8 = RGB8::new(255, 255, 255);
+
+#[test]
+fn estimate() {
+    let limit = PowerLimit::new(1000);
+
+    assert_eq!(limit.estimate([RGB8::default(); 10]), 10);
+    assert_eq!(limit.estimate([WHITE; 10]), 610);
+    // A single channel draws a third of full white
+    assert_eq!(limit.estimate([RGB8::new(0, 255, 0); 3]), 63);
+
+    let limit = limit.with_led_milliamps(30).with_idle_milliamps(0);
+    assert_eq!(limit.estimate([WHITE; 10]), 300);
+}
+
+#[test]
+fn frame_at_the_budget_is_unchanged() {
+    let limit = PowerLimit::new(610);
+    let frame = [WHITE; 10];
+
+    assert_eq!(limit.max_brightness(frame), 255);
+    assert!(limit.limit(frame).eq(frame));
+}
+
+#[test]
+fn clamps_to_the_budget() {
+    let frame = [
+        RGB8::new(255, 0, 0),
+        RGB8::new(0, 255, 0),
+        RGB8::new(0, 0, 255),
+        WHITE,
+    ];
+
+    // Every budget below the 124 mA the frame draws is met, and scaling
+    // gives up less than 2 mA of it
+    for max_milliamps in 5..124 {
+        let limit = PowerLimit::new(max_milliamps);
+        let limited: Vec<RGB8> = limit.limit(frame).collect();
+        let estimate = limit.estimate(limited.iter().copied());
+
+        assert!(estimate <= max_milliamps, "{max_milliamps} mA");
+        assert!(estimate + 2 > max_milliamps, "{max_milliamps} mA");
+    }
+}
+
+#[test]
+fn clamps_one_over_the_budget() {
+    let limit = PowerLimit::new(609);
+    let frame = [WHITE; 10];
+
+    assert_eq!(limit.max_brightness(frame), 254);
+    assert!(limit
+        .limit(frame)
+        .all(|color| color == RGB8::new(254, 254, 254)));
+    assert!(limit.estimate(limit.limit(frame)) <= 609);
+}
+
+#[test]
+fn budget_below_the_idle_current() {
+    let limit = PowerLimit::new(5);
+
+    assert_eq!(limit.max_brightness([WHITE; 10]), 0);
+    assert!(limit
+        .limit([WHITE; 10])
+        .all(|color| color == RGB8::default()));
+}
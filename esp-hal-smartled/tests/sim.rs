@@ -0,0 +1,106 @@
+//! Tests of the encoding of frames, run on the host with
+//! `cargo test --features std`.
+
+#![cfg(feature = "std")]
+
+use esp_hal_smartled::{
+    chip::{ColorOrder, Sk6812Rgbw, Sk68xx, Ws2811},
+    color::Gamma,
+    sim::SimAdapter,
+    smart_leds_trait::{SmartLedsWrite, White, RGB8, RGBW},
+    timing::Timing,
+    LedAdapterError,
+};
+
+/// Pulse code of a `1` bit of [`Sk68xx`] LEDs at 80 MHz: high for 850 ns, then
+/// low for 400 ns
+const SK68XX_ONE: u32 = 1 << 15 | 68 | 32 << 16;
+
+/// Pulse code of a `0` bit of [`Sk68xx`] LEDs at 80 MHz: high for 400 ns, then
+/// low for 850 ns
+const SK68XX_ZERO: u32 = 1 << 15 | 32 | 68 << 16;
+
+#[test]
+fn frame_has_pulses_of_every_bit_and_end_delimiter() {
+    let mut led = SimAdapter::new();
+
+    led.write([RGB8::new(0, 0x80, 0), RGB8::new(1, 2, 3)])
+        .unwrap();
+
+    let frame = led.last_frame().unwrap();
+    assert_eq!(frame.len(), 2);
+    assert_eq!(frame.codes().len(), 2 * 24 + 1);
+
+    // Green is sent first, most significant bit first
+    assert_eq!(frame.codes()[0], SK68XX_ONE);
+    assert!(frame.codes()[1..24].iter().all(|code| *code == SK68XX_ZERO));
+
+    // The line is held low for the reset time of 80 µs
+    assert_eq!(*frame.codes().last().unwrap(), 80 * 80);
+}
+
+#[test]
+fn bytes_are_sent_in_the_color_order_of_the_chip() {
+    let mut led = SimAdapter::new_with_chip(Sk68xx);
+    led.write([RGB8::new(1, 2, 3)]).unwrap();
+    assert_eq!(led.last_frame().unwrap().bytes(), [2, 1, 3]);
+
+    let mut led = SimAdapter::new_with_chip(Ws2811);
+    led.write([RGB8::new(1, 2, 3)]).unwrap();
+    assert_eq!(led.last_frame().unwrap().bytes(), [1, 2, 3]);
+
+    led.set_color_order(Some(ColorOrder::Bgr));
+    led.write([RGB8::new(1, 2, 3)]).unwrap();
+    assert_eq!(led.last_frame().unwrap().bytes(), [3, 2, 1]);
+}
+
+#[test]
+fn rgbw_leds_send_the_white_channel_last() {
+    let mut led = SimAdapter::new_with_chip(Sk6812Rgbw);
+
+    led.write([RGBW::new_alpha(1, 2, 3, White(4))]).unwrap();
+
+    let frame = led.last_frame().unwrap();
+    assert_eq!(frame.codes().len(), 32 + 1);
+    assert_eq!(frame.leds(), [[2, 1, 3, 4]]);
+}
+
+#[test]
+fn gamma_is_applied_while_encoding() {
+    let mut led = SimAdapter::new();
+    led.set_gamma(Some(Gamma::new(2.2)));
+
+    led.write([RGB8::new(0, 255, 128)]).unwrap();
+
+    let leds = led.last_frame().unwrap().leds();
+    assert_eq!(leds[0][0], 255);
+    assert_eq!(leds[0][1], 0);
+    assert!(leds[0][2] < 128);
+}
+
+#[test]
+fn every_write_is_recorded() {
+    let mut led = SimAdapter::new();
+
+    led.write([RGB8::default(); 3]).unwrap();
+    led.write([RGB8::default(); 5]).unwrap();
+
+    let lengths: Vec<usize> = led.frames().iter().map(|frame| frame.len()).collect();
+    assert_eq!(lengths, [3, 5]);
+
+    led.clear();
+    assert!(led.last_frame().is_none());
+}
+
+#[test]
+fn slow_clock_is_rejected() {
+    let mut led = SimAdapter::new();
+    led.set_timing(Timing::WS2812);
+    led.set_src_clock(1);
+
+    assert!(matches!(
+        led.write([RGB8::default()]),
+        Err(LedAdapterError::InsufficientResolution { src_clock_mhz: 1 })
+    ));
+    assert!(led.frames().is_empty());
+}
@@ -0,0 +1,97 @@
+//! Tests of transitions and their easing curves, run on the host with
+//! `cargo test --features std`.
+
+use esp_hal_smartled::{
+    smart_leds_trait::RGB8,
+    transition::{Easing, Transition},
+};
+
+const EASINGS: [Easing; 7] = [
+    Easing::Linear,
+    Easing::EaseIn,
+    Easing::EaseOut,
+    Easing::EaseInOut,
+    Easing::CubicIn,
+    Easing::CubicOut,
+    Easing::CubicInOut,
+];
+
+const TARGET: RGB8 = RGB8::new(200, 100, 0);
+
+#[test]
+fn easing_curves() {
+    // Color after 25 and 50 of 100 milliseconds
+    let cases = [
+        (Easing::Linear, (50, 25), (100, 50)),
+        (Easing::EaseIn, (13, 6), (50, 25)),
+        (Easing::EaseOut, (88, 44), (150, 75)),
+        (Easing::EaseInOut, (25, 13), (100, 50)),
+        (Easing::CubicIn, (3, 2), (25, 13)),
+        (Easing::CubicOut, (116, 58), (175, 88)),
+        (Easing::CubicInOut, (13, 6), (100, 50)),
+    ];
+
+    for (easing, quarter, half) in cases {
+        let mut transition = Transition::<1>::new();
+        transition.set_target([TARGET], 100, easing);
+
+        let color = transition.tick(25)[0];
+        assert_eq!((color.r, color.g), quarter, "{easing:?}");
+        let color = transition.tick(25)[0];
+        assert_eq!((color.r, color.g), half, "{easing:?}");
+    }
+}
+
+#[test]
+fn easings_reach_the_target() {
+    for easing in EASINGS {
+        let mut transition = Transition::<1>::new();
+        transition.set_target([TARGET], 100, easing);
+
+        let mut previous = transition.tick(0)[0];
+        assert_eq!(previous, RGB8::default(), "{easing:?}");
+
+        while !transition.is_done() {
+            let color = transition.tick(1)[0];
+            assert!(color.r >= previous.r && color.g >= previous.g, "{easing:?}");
+            previous = color;
+        }
+
+        assert_eq!(previous, TARGET, "{easing:?}");
+        assert_eq!(transition.tick(10), &[TARGET], "{easing:?}");
+    }
+}
+
+#[test]
+fn fades_down() {
+    let mut transition = Transition::<1>::new();
+    transition.jump_to([TARGET]);
+    transition.set_target([RGB8::default()], 100, Easing::Linear);
+
+    assert_eq!(transition.tick(50), &[RGB8::new(100, 50, 0)]);
+    assert_eq!(transition.tick(50), &[RGB8::default()]);
+}
+
+#[test]
+fn new_target_starts_from_the_current_frame() {
+    let mut transition = Transition::<1>::new();
+    transition.set_target([TARGET], 100, Easing::Linear);
+    transition.tick(50);
+
+    transition.set_target([RGB8::new(0, 100, 200)], 100, Easing::Linear);
+
+    assert!(!transition.is_done());
+    assert_eq!(transition.tick(0), &[RGB8::new(100, 50, 0)]);
+    assert_eq!(transition.tick(50), &[RGB8::new(50, 75, 100)]);
+}
+
+#[test]
+fn zero_duration_jumps() {
+    let mut transition = Transition::<2>::new();
+    transition.set_target([TARGET], 0, Easing::EaseIn);
+
+    assert!(transition.is_done());
+    // LEDs beyond the end of the target are turned off
+    assert_eq!(transition.frame(), &[TARGET, RGB8::default()]);
+    assert_eq!(transition.target(), &[TARGET, RGB8::default()]);
+}
@@ -0,0 +1,9 @@
+//! Hardware-in-the-loop tests for the esp-hal-community crates.
+//!
+//! The tests live in `tests/`; this library only sets up logging and the
+//! panic handler they share.
+
+#![no_std]
+
+use defmt_rtt as _;
+use esp_backtrace as _;
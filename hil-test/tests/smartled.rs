@@ -0,0 +1,380 @@
+//! Tests of the `esp-hal-smartled` adapters.
+//!
+//! The transmit pin has to be connected to the receive pin, see the README for
+//! the pins of every chip.
+
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use esp_hal::{
+    gpio::{AnyPin, Pin},
+    peripherals::RMT,
+    prelude::*,
+    rmt::Rmt,
+    timer::timg::TimerGroup,
+};
+use esp_hal_smartled::{
+    buffer_size, buffer_size_async, buffer_size_rgbw,
+    chip::{ChipType, Sk6812Rgbw, Sk68xx},
+    count::{CountPolicy, LedCount},
+    loopback::Loopback,
+    LedAdapterError, SmartLedsAdapter, SmartLedsAdapterAsync,
+};
+use fugit::HertzU32;
+use hil_test as _;
+use smart_leds_trait::{White, RGB8, RGBW};
+
+struct Context {
+    rmt: RMT,
+    tx: AnyPin,
+    rx: AnyPin,
+}
+
+/// Frequency of the RMT peripheral
+fn rmt_frequency() -> HertzU32 {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "esp32h2")] {
+            32.MHz()
+        } else {
+            80.MHz()
+        }
+    }
+}
+
+/// Number of pulse codes in a block of RMT channel memory, the most a receive
+/// channel can take
+#[cfg(any(feature = "esp32", feature = "esp32s2"))]
+const RMT_CHANNEL_RAM_SIZE: usize = 64;
+/// Number of pulse codes in a block of RMT channel memory, the most a receive
+/// channel can take
+#[cfg(not(any(feature = "esp32", feature = "esp32s2")))]
+const RMT_CHANNEL_RAM_SIZE: usize = 48;
+
+/// Create a [`Loopback`] receiving on `rx` with the first receive channel of
+/// `rmt`
+macro_rules! loopback {
+    ( $rmt: expr, $rx: expr ) => {{
+        #[cfg(feature = "esp32s3")]
+        let loopback = Loopback::new($rmt.channel4, $rx, rmt_frequency());
+        #[cfg(not(feature = "esp32s3"))]
+        let loopback = Loopback::new($rmt.channel2, $rx, rmt_frequency());
+        loopback
+    }};
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = esp_hal_embassy::Executor::new())]
+mod tests {
+    use embassy_futures::poll_once;
+    use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync};
+
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg0 = TimerGroup::new(peripherals.TIMG0);
+        esp_hal_embassy::init(timg0.timer0);
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "esp32")] {
+                let (tx, rx) = (peripherals.GPIO2.degrade(), peripherals.GPIO4.degrade());
+            } else if #[cfg(any(feature = "esp32s2", feature = "esp32s3"))] {
+                let (tx, rx) = (peripherals.GPIO9.degrade(), peripherals.GPIO10.degrade());
+            } else {
+                let (tx, rx) = (peripherals.GPIO2.degrade(), peripherals.GPIO3.degrade());
+            }
+        }
+
+        Context {
+            rmt: peripherals.RMT,
+            tx,
+            rx,
+        }
+    }
+
+    #[test]
+    fn write_single_led(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        led.write([RGB8::new(255, 0, 0)]).unwrap();
+        led.write([RGB8::new(0, 0, 0)]).unwrap();
+    }
+
+    #[test]
+    fn write_long_frame(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(64) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        // Longer than the memory of the channel, so it has to be refilled
+        led.write((0..64).map(|i| RGB8::new(i, 255 - i, 0)))
+            .unwrap();
+    }
+
+    #[test]
+    fn write_raw_frame(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(2) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        // The trailing byte of an incomplete LED is ignored
+        led.write_raw(&[0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x01])
+            .unwrap();
+
+        // Frames are still checked against the buffer
+        assert!(matches!(
+            led.write_raw(&[0; 9]),
+            Err(LedAdapterError::BufferSizeExceeded { led: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn gapless_mode_rejects_parts(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(8) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+        led.set_streaming(true);
+        led.set_gapless(true);
+
+        assert!(matches!(
+            led.write((0..16).map(|i| RGB8::new(i, 0, 0))),
+            Err(LedAdapterError::GapUnavoidable { required, available })
+                if required == buffer_size(16) && available == buffer_size(8)
+        ));
+
+        // Frames which fit into the buffer are still sent
+        led.write((0..8).map(|i| RGB8::new(i, 0, 0))).unwrap();
+    }
+
+    #[test]
+    fn done_hook_is_called(ctx: Context) {
+        static DONE: AtomicBool = AtomicBool::new(false);
+
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+        led.set_done_hook(Some(|| DONE.store(true, Ordering::Relaxed)));
+
+        led.start_write([RGB8::new(255, 0, 0)]).unwrap();
+        assert!(!DONE.load(Ordering::Relaxed));
+
+        led.flush().unwrap();
+        assert!(DONE.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn frame_has_no_gaps(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(2) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+        let mut loopback = loopback!(rmt, ctx.rx);
+
+        // The loopback checks the bits in the order of `Sk68xx`, so only the
+        // timing of the other chips is applied
+        for chip in [
+            ChipType::Sk68xx,
+            ChipType::Ws2813,
+            ChipType::Ws2815,
+            ChipType::Ws2818,
+        ] {
+            led.set_timing(chip.timing());
+
+            let mut buffer = [0u32; RMT_CHANNEL_RAM_SIZE];
+            loopback
+                .write_and_verify(
+                    &mut led,
+                    &[RGB8::new(0x01, 0x80, 0xff), RGB8::new(0xaa, 0x55, 0x0f)],
+                    &mut buffer,
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn rgbw_frame_has_no_gaps(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size_rgbw(1) }, _>::new_owned_with_chip(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+            Sk6812Rgbw,
+        );
+        let mut loopback = loopback!(rmt, ctx.rx);
+
+        let mut buffer = [0u32; RMT_CHANNEL_RAM_SIZE];
+        loopback
+            .write_and_verify(
+                &mut led,
+                &[RGBW::new_alpha(0x01, 0x80, 0xff, White(0x3c))],
+                &mut buffer,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn too_many_leds(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        let result = led.write([RGB8::default(); 2]);
+        assert!(matches!(
+            result,
+            Err(LedAdapterError::BufferSizeExceeded {
+                led: 1,
+                required,
+                available,
+            }) if required == buffer_size(2) && available == buffer_size(1)
+        ));
+
+        // The adapter is still usable
+        led.write([RGB8::default()]).unwrap();
+    }
+
+    #[test]
+    fn led_count_mismatch(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::new_with_led_count(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+            [0; buffer_size(3)],
+            Sk68xx,
+            LedCount::new(3, CountPolicy::Reject),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            led.write([RGB8::default(); 2]),
+            Err(LedAdapterError::LedCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        led.write([RGB8::default(); 3]).unwrap();
+    }
+
+    #[test]
+    fn recover_after_start_write(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let mut led = SmartLedsAdapter::<_, { buffer_size(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        // A frame started with `start_write` has to fit into channel memory
+        led.start_write([RGB8::new(0, 0, 255)]).unwrap();
+        led.recover().unwrap();
+
+        led.write([RGB8::default()]).unwrap();
+    }
+
+    #[test]
+    async fn write_async(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap().into_async();
+        let mut led = SmartLedsAdapterAsync::<_, { buffer_size_async(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        led.write([RGB8::new(0, 255, 0)]).await.unwrap();
+        led.write([RGB8::new(0, 0, 0)]).await.unwrap();
+    }
+
+    #[test]
+    async fn too_many_leds_async(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap().into_async();
+        let mut led = SmartLedsAdapterAsync::<_, { buffer_size_async(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        let result = led.write([RGB8::default(); 2]).await;
+        assert!(matches!(
+            result,
+            Err(LedAdapterError::BufferSizeExceeded { led: 1, .. })
+        ));
+
+        led.write([RGB8::default()]).await.unwrap();
+    }
+
+    #[test]
+    async fn cancelled_write(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap().into_async();
+        let mut led = SmartLedsAdapterAsync::<_, { buffer_size_async(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        // Start a frame, and drop the future while it is being sent
+        assert!(poll_once(led.write([RGB8::new(255, 255, 255)])).is_pending());
+
+        led.write([RGB8::default()]).await.unwrap();
+    }
+
+    #[test]
+    async fn convert_between_modes(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap().into_async();
+        let led = SmartLedsAdapterAsync::<_, { buffer_size_async(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        let mut led = led.into_blocking();
+        led.write([RGB8::new(0, 0, 255)]).unwrap();
+
+        let Ok(mut led) = led.into_async() else {
+            panic!("the adapter was created in async mode");
+        };
+        led.write([RGB8::default()]).await.unwrap();
+    }
+
+    #[test]
+    fn blocking_adapter_stays_blocking(ctx: Context) {
+        let rmt = Rmt::new(ctx.rmt, rmt_frequency()).unwrap();
+        let led = SmartLedsAdapter::<_, { buffer_size(1) }>::new_owned(
+            rmt.channel0,
+            ctx.tx,
+            rmt_frequency(),
+        );
+
+        // No interrupt handler wakes async transmissions, so the conversion
+        // is refused and the adapter keeps working in blocking mode
+        let Err(mut led) = led.into_async() else {
+            panic!("the adapter was created in blocking mode");
+        };
+        led.write([RGB8::new(0, 0, 255)]).unwrap();
+    }
+}